@@ -4,14 +4,24 @@
 //!   numr-cli "300$ in rub"           # Single expression
 //!   echo "100 + 50" | numr-cli       # Pipe mode
 //!   numr-cli -f calculations.txt     # File mode
+//!   numr-cli --watch -f budget.numr  # Re-run file mode on every save
 //!   numr-cli -i                      # Interactive REPL
+//!   numr-cli -i -f session.numr      # REPL continuing from a file
 //!   numr-cli --server                # JSON-RPC server mode
+//!   numr-cli --offline "10 usd in eur" # Never fetch exchange rates
 
 use std::io::{self, BufRead, IsTerminal, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{CommandFactory, Parser};
-use numr_core::{Engine, Value};
+use numr_core::types::UNITS;
+use numr_core::{
+    DefaultRates, Engine, EvalOptions, FormatOptions, GroupSeparator, GroupSize, NumberLocale,
+    RoundingMode, YenSymbol,
+};
+
+#[cfg(feature = "watch")]
+mod watch;
 
 #[derive(Parser, Debug)]
 #[command(name = "numr-cli")]
@@ -24,7 +34,7 @@ struct Args {
     #[arg(short, long, value_name = "FILE")]
     file: Option<PathBuf>,
 
-    /// Interactive REPL mode
+    /// Interactive REPL mode; with --file, continue from the file's lines
     #[arg(short, long)]
     interactive: bool,
 
@@ -39,17 +49,123 @@ struct Args {
     /// Show running total
     #[arg(short, long)]
     total: bool,
+
+    /// Show conversion results beside their original value (5 km → 3.11 mi)
+    #[arg(long)]
+    show_source: bool,
+
+    /// With --file, print the evaluated document as a Markdown table with totals
+    #[arg(long, requires = "file")]
+    markdown: bool,
+
+    /// With --file, re-evaluate and reprint whenever the file changes (Ctrl+C to stop)
+    #[cfg(feature = "watch")]
+    #[arg(long, requires = "file")]
+    watch: bool,
+
+    /// Omit blank and comment lines from aligned output instead of echoing them
+    #[arg(long)]
+    skip_empty: bool,
+
+    /// Group result digits in thousands: comma, space, or dot
+    #[arg(long, value_name = "SEP", value_parser = parse_group_separator)]
+    group: Option<GroupSeparator>,
+
+    /// Digit group sizes for --group: thousands, or indian (12,34,567)
+    #[arg(long, value_name = "SIZES", value_parser = parse_group_size, default_value = "thousands")]
+    group_size: GroupSize,
+
+    /// Use a frozen set of built-in rates (e.g. v1) instead of cached or fetched ones
+    #[arg(long, value_name = "VERSION", value_parser = parse_default_rates)]
+    default_rates: Option<DefaultRates>,
+
+    /// Never fetch rates: use the cache if it is fresh, otherwise the built-in rates
+    #[arg(long, conflicts_with = "refresh")]
+    offline: bool,
+
+    /// Fetch rates before evaluating even when the cache is fresh
+    #[arg(long, conflicts_with_all = ["default_rates", "server"])]
+    refresh: bool,
+
+    /// Show quantities in their most readable unit (9500000 m as 9500 km)
+    #[arg(long)]
+    normalize_units: bool,
+
+    /// Show negative money in parentheses: ($100.00) instead of $-100.00
+    #[arg(long)]
+    accounting: bool,
+
+    /// Append … to results whose shown digits were rounded (1/3 = 0.33…)
+    #[arg(long)]
+    mark_rounded: bool,
+
+    /// How input numbers are written: us (1,234.56) or eu (1.234,56)
+    #[arg(long, value_name = "LOCALE", value_parser = parse_number_locale)]
+    number_locale: Option<NumberLocale>,
+
+    /// Currency a bare ¥ stands for: jpy (default) or cny
+    #[arg(long, value_name = "CURRENCY", value_parser = parse_yen_symbol)]
+    yen: Option<YenSymbol>,
+
+    /// Rounding for round() and displayed results: half-even, half-up, or truncate
+    #[arg(long, value_name = "MODE", value_parser = parse_rounding_mode)]
+    rounding: Option<RoundingMode>,
+}
+
+fn parse_group_separator(name: &str) -> Result<GroupSeparator, String> {
+    GroupSeparator::parse(name).ok_or_else(|| "expected comma, space, or dot".to_string())
+}
+
+fn parse_group_size(name: &str) -> Result<GroupSize, String> {
+    GroupSize::parse(name).ok_or_else(|| "expected thousands or indian".to_string())
+}
+
+fn parse_default_rates(name: &str) -> Result<DefaultRates, String> {
+    DefaultRates::parse(name).ok_or_else(|| "expected a rate set version such as v1".to_string())
+}
+
+fn parse_number_locale(name: &str) -> Result<NumberLocale, String> {
+    NumberLocale::parse(name).ok_or_else(|| "expected us or eu".to_string())
+}
+
+fn parse_yen_symbol(name: &str) -> Result<YenSymbol, String> {
+    YenSymbol::parse(name).ok_or_else(|| "expected jpy or cny".to_string())
+}
+
+fn parse_rounding_mode(name: &str) -> Result<RoundingMode, String> {
+    RoundingMode::parse(name).ok_or_else(|| "expected half-even, half-up, or truncate".to_string())
 }
 
 fn main() -> io::Result<()> {
     let args = Args::parse();
 
     let mut engine = Engine::new();
-    let cache_loaded = match engine.load_rates_from_cache() {
-        Ok(loaded) => loaded,
-        Err(error) => {
-            eprintln!("Warning: failed to load the exchange-rate cache: {error}");
-            false
+    let rounding = args.rounding.unwrap_or_default();
+    engine.set_format_options(FormatOptions {
+        grouping: args.group,
+        group_size: args.group_size,
+        rounding,
+        normalize_units: args.normalize_units,
+        accounting_negatives: args.accounting,
+        mark_rounded: args.mark_rounded,
+    });
+    engine.set_options(EvalOptions {
+        rounding,
+        number_locale: args.number_locale.unwrap_or_default(),
+        yen_symbol: args.yen.unwrap_or_default(),
+        ..EvalOptions::default()
+    });
+    // Pinned rates are used as they are, without the cache or a network refresh
+    let rates_ready = if let Some(set) = args.default_rates {
+        engine.use_default_rates(set);
+        true
+    } else {
+        match engine.load_rates_from_cache() {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                eprintln!("Warning: failed to load the exchange-rate cache: {error}");
+                false
+            }
         }
     };
 
@@ -60,8 +176,9 @@ fn main() -> io::Result<()> {
         return Ok(());
     }
 
-    // Fetch fresh rates if the explicit cache load found no usable entry.
-    if !cache_loaded {
+    // Fetch fresh rates if the explicit cache load found no usable entry, or when asked to.
+    // A failed fetch leaves the cached or built-in rates in place.
+    if args.refresh || (!rates_ready && !args.offline) {
         let rt = tokio::runtime::Runtime::new()?;
         match rt.block_on(numr_core::fetch_rates()) {
             Ok(result) => match engine.apply_raw_rates(&result.rates) {
@@ -75,7 +192,10 @@ fn main() -> io::Result<()> {
                 }
                 Err(error) => eprintln!("Warning: rejected exchange rates: {error}"),
             },
-            Err(e) => eprintln!("Warning: {e}"),
+            Err(e) => {
+                let fallback = if rates_ready { "cached" } else { "built-in" };
+                eprintln!("Warning: {e}; using {fallback} rates");
+            }
         }
     }
 
@@ -83,23 +203,43 @@ fn main() -> io::Result<()> {
     // File mode defaults to verbose (aligned output), everything else to quiet
     if let Some(expr) = &args.expression {
         // Single expression mode
-        eval_and_print(&mut engine, expr, !args.verbose);
+        eval_and_print(&mut engine, expr, output_style(&args, !args.verbose));
+    } else if args.interactive {
+        // Interactive REPL, continuing from the file when one is given
+        if let Some(path) = &args.file {
+            let content = std::fs::read_to_string(path)?;
+            print_file(&mut engine, &content, &args);
+        }
+        run_repl(&mut engine, output_style(&args, true), args.file.as_deref())?;
     } else if let Some(path) = &args.file {
-        // File mode — verbose by default
-        let content = std::fs::read_to_string(path)?;
-        let document = engine.evaluate_document(&content);
-        for line in document.lines {
-            print_evaluated(&line.input, &line.value, false);
+        #[cfg(feature = "watch")]
+        if args.watch {
+            return watch::watch_file(path, || {
+                if io::stdout().is_terminal() {
+                    // Clear the screen so each run replaces the last
+                    print!("\x1b[2J\x1b[H");
+                }
+                // A save in progress can briefly remove the file; the next event retries
+                match std::fs::read_to_string(path) {
+                    Ok(content) => {
+                        print_file(&mut engine, &content, &args);
+                        if args.total {
+                            print_total(&engine);
+                        }
+                    }
+                    Err(error) => eprintln!("Warning: could not read {}: {error}", path.display()),
+                }
+                io::stdout().flush()
+            });
         }
-    } else if args.interactive {
-        // Interactive REPL
-        run_repl(&mut engine)?;
+        let content = std::fs::read_to_string(path)?;
+        print_file(&mut engine, &content, &args);
     } else if !io::stdin().is_terminal() {
         // Pipe mode (stdin is not a tty)
         let stdin = io::stdin();
         for line in stdin.lock().lines() {
             let line = line?;
-            eval_and_print(&mut engine, &line, !args.verbose);
+            eval_and_print(&mut engine, &line, output_style(&args, !args.verbose));
         }
     } else {
         Args::command().print_help()?;
@@ -109,37 +249,79 @@ fn main() -> io::Result<()> {
 
     // Show total if requested
     if args.total {
-        let sum = engine.sum();
-        println!("─────────────");
-        println!("Total: {sum}");
+        print_total(&engine);
     }
 
     Ok(())
 }
 
-fn eval_and_print(engine: &mut Engine, input: &str, quiet: bool) {
-    let result = engine.eval(input);
-    print_evaluated(input, &result, quiet);
+/// File mode: evaluate a whole document, verbose by default
+fn print_file(engine: &mut Engine, content: &str, args: &Args) {
+    let document = engine.evaluate_document(content);
+    let style = output_style(args, false);
+    if args.markdown {
+        print!("{}", engine.to_markdown());
+    } else {
+        for line in document.lines {
+            print_evaluated(
+                &line.input,
+                &engine.format_line(&line, style.show_source),
+                style,
+            );
+        }
+    }
+}
+
+fn print_total(engine: &Engine) {
+    let sum = engine.format(&engine.sum());
+    println!("─────────────");
+    println!("Total: {sum}");
+}
+
+/// How evaluated lines are printed
+#[derive(Clone, Copy)]
+struct OutputStyle {
+    /// Print results only, without the input
+    quiet: bool,
+    /// Show conversion results beside their original value
+    show_source: bool,
+    /// Drop lines without a result rather than echoing their input
+    skip_empty: bool,
+}
+
+fn output_style(args: &Args, quiet: bool) -> OutputStyle {
+    OutputStyle {
+        quiet,
+        show_source: args.show_source,
+        skip_empty: args.skip_empty,
+    }
+}
+
+fn eval_and_print(engine: &mut Engine, input: &str, style: OutputStyle) {
+    engine.eval(input);
+    let line = engine.lines().last().expect("eval records a line");
+    print_evaluated(input, &engine.format_line(line, style.show_source), style);
 }
 
-fn print_evaluated(input: &str, result: &Value, quiet: bool) {
-    if quiet {
-        if !result.is_empty() {
-            println!("{result}");
+fn print_evaluated(input: &str, result_str: &str, style: OutputStyle) {
+    if style.quiet {
+        if !result_str.is_empty() {
+            println!("{result_str}");
         }
-    } else {
-        let result_str = result.to_string();
-        if result_str.is_empty() {
+    } else if result_str.is_empty() {
+        if !style.skip_empty {
             println!("{input}");
-        } else {
-            // Pad input to align results
-            let padding = 40usize.saturating_sub(input.len());
-            println!("{}{:>width$} = {}", input, "", result_str, width = padding);
         }
+    } else {
+        // Pad input to align results
+        let padding = 40usize.saturating_sub(input.len());
+        println!("{}{:>width$} = {}", input, "", result_str, width = padding);
     }
 }
 
-fn run_repl(engine: &mut Engine) -> io::Result<()> {
+/// Read lines interactively. `save` writes the session to `session`, the
+/// file it was loaded from, unless given another path.
+fn run_repl(engine: &mut Engine, style: OutputStyle, session: Option<&Path>) -> io::Result<()> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
@@ -163,7 +345,24 @@ fn run_repl(engine: &mut Engine) -> io::Result<()> {
         }
 
         // Special commands
-        match line.to_lowercase().as_str() {
+        let command = line.to_lowercase();
+        let (word, kind) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((&command, ""));
+        // "units = 5", "units * 2" and "unitsold" are still expressions
+        let kind = kind.trim();
+        if word == "units" && kind.chars().all(char::is_alphabetic) {
+            print_units(kind);
+            continue;
+        }
+        if let Some(target) = save_target(line) {
+            match target.map(Path::new).or(session) {
+                Some(path) => save_session(engine, path),
+                None => println!("Usage: save FILE"),
+            }
+            continue;
+        }
+        match command.as_str() {
             "quit" | "exit" => break,
             "clear" => {
                 engine.clear();
@@ -171,7 +370,11 @@ fn run_repl(engine: &mut Engine) -> io::Result<()> {
                 continue;
             }
             "total" | "sum" => {
-                println!("Total: {}", engine.sum());
+                println!("Total: {}", engine.format(&engine.sum()));
+                continue;
+            }
+            "rates" => {
+                print_rates(engine);
                 continue;
             }
             "help" => {
@@ -181,12 +384,124 @@ fn run_repl(engine: &mut Engine) -> io::Result<()> {
             _ => {}
         }
 
-        eval_and_print(engine, line, true);
+        eval_and_print(engine, line, style);
     }
 
     Ok(())
 }
 
+/// `save` or `save FILE`, as opposed to an expression such as `save * 2`.
+/// The outer `None` means the line is not a save command.
+fn save_target(line: &str) -> Option<Option<&str>> {
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    if !word.eq_ignore_ascii_case("save") {
+        return None;
+    }
+    let path = rest.trim();
+    if path.is_empty() {
+        return Some(None);
+    }
+    let is_path = !path.contains(char::is_whitespace)
+        && !path.starts_with(|c: char| "=+-*/^(%&|<>".contains(c));
+    is_path.then_some(Some(path))
+}
+
+/// Write the lines entered so far, one per line, so `-i -f` can continue them
+fn save_session(engine: &Engine, path: &Path) {
+    let mut content: String = engine
+        .lines()
+        .iter()
+        .map(|line| line.input.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    content.push('\n');
+    match std::fs::write(path, content) {
+        Ok(()) => println!("Saved {} lines to {}", engine.lines().len(), path.display()),
+        Err(error) => println!("Error: could not save {}: {error}", path.display()),
+    }
+}
+
+/// List the registered units of one type ("length"), or the types themselves
+fn print_units(kind: &str) {
+    let mut kinds: Vec<String> = Vec::new();
+    for def in UNITS.iter() {
+        let name = def.dimensions.to_string();
+        if !kinds.contains(&name) {
+            kinds.push(name);
+        }
+    }
+    if kind.is_empty() {
+        println!("Unit types: {}", kinds.join(", "));
+        return;
+    }
+
+    let units: Vec<_> = UNITS
+        .iter()
+        .filter(|def| def.dimensions.to_string() == kind)
+        .collect();
+    if units.is_empty() {
+        println!(
+            "Unknown unit type: {kind}. Known types: {}",
+            kinds.join(", ")
+        );
+        return;
+    }
+    let width = units
+        .iter()
+        .map(|def| def.symbol.chars().count())
+        .max()
+        .unwrap_or(0);
+    for def in units {
+        let aliases: Vec<&str> = def
+            .aliases
+            .iter()
+            .copied()
+            .filter(|alias| !alias.eq_ignore_ascii_case(def.symbol))
+            .collect();
+        println!("  {:<width$}  {}", def.symbol, aliases.join(", "));
+    }
+}
+
+/// Show the exchange rates in use and how old they are
+fn print_rates(engine: &Engine) {
+    match engine.rates_updated_at() {
+        Some(fetched) => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(fetched, |duration| duration.as_secs());
+            println!(
+                "Rates fetched {}",
+                describe_age(now.saturating_sub(fetched))
+            );
+        }
+        None => println!("Built-in rates (nothing fetched)"),
+    }
+
+    let rates = engine.rates_snapshot();
+    let width = rates
+        .iter()
+        .map(|(from, _, _)| from.code().len())
+        .max()
+        .unwrap_or(0);
+    for (from, to, rate) in rates {
+        println!(
+            "  1 {:<width$} = {} {}",
+            from.code(),
+            rate.normalize(),
+            to.code()
+        );
+    }
+}
+
+fn describe_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86_400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86_400),
+    }
+}
+
 fn print_help() {
     println!(
         r#"
@@ -194,6 +509,9 @@ Commands:
   help     Show this help
   clear    Clear all variables and history
   total    Show sum of all results
+  units    List unit types; "units length" lists length units
+  rates    Show the exchange rates in use and their age
+  save     Write this session's lines to the -f file, or "save FILE"
   quit     Exit the REPL
 
 Examples:
@@ -66,6 +66,9 @@ pub struct RpcEvalResult {
     pub unit: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
+    /// Machine-readable error kind, present for `error` results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
     pub display: String,
 }
 
@@ -81,6 +84,12 @@ struct EvalLinesParams {
     lines: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UnsetParams {
+    name: String,
+}
+
 #[derive(Debug, Serialize)]
 struct VariableInfo {
     name: String,
@@ -143,6 +152,7 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
             value: Some(format_number(*n)),
             unit: None,
             message: None,
+            code: None,
             display: value.to_string(),
         },
         NumrValue::BaseNumber { amount, .. } => RpcEvalResult {
@@ -150,6 +160,7 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
             value: Some(format_number(*amount)),
             unit: None,
             message: None,
+            code: None,
             display: value.to_string(),
         },
         NumrValue::Percentage(p) => match p.checked_mul(Decimal::from(100)) {
@@ -160,6 +171,7 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
                     value: Some(formatted.clone()),
                     unit: None,
                     message: None,
+                    code: None,
                     display: format!("{formatted}%"),
                 }
             }
@@ -168,6 +180,7 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
                 value: None,
                 unit: None,
                 message: Some("percentage is outside the displayable range".to_string()),
+                code: Some("overflow"),
                 display: "Error: percentage is outside the displayable range".to_string(),
             },
         },
@@ -176,6 +189,7 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
             value: Some(format_currency_value(*amount, *currency)),
             unit: Some(currency.code().to_string()),
             message: None,
+            code: None,
             display: value.to_string(),
         },
         NumrValue::WithCompoundUnit { amount, unit } => RpcEvalResult {
@@ -183,6 +197,27 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
             value: Some(format_number(*amount)),
             unit: Some(unit.symbol.clone()),
             message: None,
+            code: None,
+            display: value.to_string(),
+        },
+        NumrValue::CurrencyRate {
+            amount,
+            currency,
+            per,
+        } => RpcEvalResult {
+            result_type: "currency_rate",
+            value: Some(format_currency_value(*amount, *currency)),
+            unit: Some(format!("{}/{}", currency.code(), per.symbol)),
+            message: None,
+            code: None,
+            display: value.to_string(),
+        },
+        NumrValue::Date(date) => RpcEvalResult {
+            result_type: "date",
+            value: Some(date.to_string()),
+            unit: None,
+            message: None,
+            code: None,
             display: value.to_string(),
         },
         NumrValue::Empty => RpcEvalResult {
@@ -190,13 +225,15 @@ pub fn value_to_result(value: &NumrValue) -> RpcEvalResult {
             value: None,
             unit: None,
             message: None,
+            code: None,
             display: String::new(),
         },
-        NumrValue::Error(message) => RpcEvalResult {
+        NumrValue::Error(error) => RpcEvalResult {
             result_type: "error",
             value: None,
             unit: None,
-            message: Some(message.to_string()),
+            message: Some(error.to_string()),
+            code: Some(error.code()),
             display: value.to_string(),
         },
     }
@@ -279,6 +316,11 @@ impl<'a> JsonRpcHandler<'a> {
                 self.engine.clear();
                 Ok(serde_json::json!({"message": "Cleared"}))
             }
+            "unset" => {
+                let params: UnsetParams = parse_params(params)?;
+                let removed = self.engine.unset_variable(&params.name).is_some();
+                Ok(serde_json::json!({ "removed": removed }))
+            }
             "get_totals" => {
                 ensure_no_params(params)?;
                 to_json(
@@ -348,7 +390,9 @@ impl<'a> JsonRpcHandler<'a> {
                 } else {
                     format!("Rates reloaded ({})", warnings.join("; "))
                 };
-                Ok(serde_json::json!({"message": message}))
+                // Codes numr does not model, so a client can see what was skipped
+                let unsupported: Vec<&String> = self.engine.unsupported_rates().keys().collect();
+                Ok(serde_json::json!({"message": message, "unsupported": unsupported}))
             }
             Err(error) => Err(RpcFailure::new(SERVER_ERROR, "Server error")
                 .with_data(format!("failed to fetch exchange rates: {error}"))),
@@ -668,6 +712,36 @@ mod tests {
         });
     }
 
+    #[test]
+    fn unset_removes_one_variable() {
+        handler_test(|handler| {
+            handler.engine.eval("tax = 15%");
+            handler.engine.eval("price = $100");
+
+            let response = handler
+                .handle(r#"{"jsonrpc":"2.0","method":"unset","params":{"name":"tax"},"id":1}"#)
+                .expect("response");
+            assert_eq!(response["result"]["removed"], true);
+
+            let response = handler
+                .handle(r#"{"jsonrpc":"2.0","method":"unset","params":{"name":"tax"},"id":2}"#)
+                .expect("response");
+            assert_eq!(response["result"]["removed"], false);
+
+            let response = handler
+                .handle(
+                    r#"{"jsonrpc":"2.0","method":"eval","params":{"expr":"price + tax"},"id":3}"#,
+                )
+                .expect("response");
+            assert_eq!(response["result"]["code"], "unknown_variable");
+
+            let response = handler
+                .handle(r#"{"jsonrpc":"2.0","method":"unset","id":4}"#)
+                .expect("response");
+            assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        });
+    }
+
     #[test]
     fn eval_lines_uses_the_shared_result_shape() {
         handler_test(|handler| {
@@ -683,6 +757,33 @@ mod tests {
         });
     }
 
+    #[test]
+    fn eval_errors_carry_a_machine_readable_code() {
+        handler_test(|handler| {
+            for (expr, code) in [
+                ("missing + 1", "unknown_variable"),
+                ("1 / 0", "division_by_zero"),
+                ("5 km in kg", "incompatible_units"),
+                ("10 + )", "parse"),
+            ] {
+                let request = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "eval",
+                    "params": {"expr": expr},
+                    "id": 1,
+                });
+                let response = handler.handle(&request.to_string()).expect("response");
+                assert_eq!(response["result"]["type"], "error", "{expr}");
+                assert_eq!(response["result"]["code"], code, "{expr}");
+            }
+
+            let response = handler
+                .handle(r#"{"jsonrpc":"2.0","method":"eval","params":{"expr":"2 + 2"},"id":2}"#)
+                .expect("response");
+            assert!(response["result"].get("code").is_none());
+        });
+    }
+
     #[test]
     fn transport_rejects_oversized_frame_and_recovers() {
         let mut input = vec![b' '; MAX_REQUEST_BYTES + 1];
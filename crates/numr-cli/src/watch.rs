@@ -0,0 +1,102 @@
+//! `--watch`: re-evaluate a file whenever it changes on disk
+
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+
+/// Quiet period that ends a burst of writes (editors often save in several steps)
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// What the watch loop reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchEvent {
+    Changed,
+    Quit,
+}
+
+/// Call `render` now and again after every change to `path`, until Ctrl+C
+pub fn watch_file(path: &Path, mut render: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+
+    let quit = sender.clone();
+    ctrlc::set_handler(move || {
+        let _ = quit.send(WatchEvent::Quit);
+    })
+    .map_err(io::Error::other)?;
+
+    // Watch the directory: editors that save by renaming replace the file itself
+    let file_name = path.file_name().map(ToOwned::to_owned);
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let touches_file = event
+            .paths
+            .iter()
+            .any(|changed| changed.file_name() == file_name.as_deref());
+        if touches_file && !event.kind.is_access() {
+            let _ = sender.send(WatchEvent::Changed);
+        }
+    })
+    .map_err(io::Error::other)?;
+    watcher
+        .watch(directory, RecursiveMode::NonRecursive)
+        .map_err(io::Error::other)?;
+
+    render()?;
+    while wait_for_change(&receiver, DEBOUNCE) {
+        render()?;
+    }
+    Ok(())
+}
+
+/// Block until a burst of changes has been quiet for `quiet`.
+/// Returns false on Ctrl+C or when the watcher stops.
+fn wait_for_change(receiver: &Receiver<WatchEvent>, quiet: Duration) -> bool {
+    if receiver.recv() != Ok(WatchEvent::Changed) {
+        return false;
+    }
+    loop {
+        match receiver.recv_timeout(quiet) {
+            Ok(WatchEvent::Changed) => {}
+            Ok(WatchEvent::Quit) | Err(RecvTimeoutError::Disconnected) => return false,
+            Err(RecvTimeoutError::Timeout) => return true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_burst_of_changes_renders_once() {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..5 {
+            sender.send(WatchEvent::Changed).unwrap();
+        }
+        assert!(wait_for_change(&receiver, Duration::from_millis(10)));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn quit_stops_watching_even_mid_burst() {
+        let (sender, receiver) = mpsc::channel();
+        sender.send(WatchEvent::Quit).unwrap();
+        assert!(!wait_for_change(&receiver, Duration::from_millis(10)));
+
+        sender.send(WatchEvent::Changed).unwrap();
+        sender.send(WatchEvent::Quit).unwrap();
+        assert!(!wait_for_change(&receiver, Duration::from_millis(10)));
+
+        drop(sender);
+        assert!(!wait_for_change(&receiver, Duration::from_millis(10)));
+    }
+}
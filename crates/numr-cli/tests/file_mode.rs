@@ -0,0 +1,106 @@
+//! End-to-end tests for evaluating a file with `--file`.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DOCUMENT: &str = "# Budget\nrent = 1000\n\nrent * 2\n";
+
+/// Run `numr-cli --offline -f` on `DOCUMENT` with extra arguments and return its stdout lines
+fn run_file_mode(extra_args: &[&str]) -> Vec<String> {
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("numr-file-mode-{nonce}.numr"));
+    std::fs::write(&path, DOCUMENT).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_numr-cli"))
+        .args(["--offline", "-f"])
+        .arg(&path)
+        .args(extra_args)
+        .output()
+        .expect("failed to run numr-cli");
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        output.status.success(),
+        "numr-cli failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn comments_and_blank_lines_are_echoed_by_default() {
+    let lines = run_file_mode(&[]);
+    assert_eq!(lines.len(), 4, "{lines:?}");
+    assert_eq!(lines[0], "# Budget");
+    assert!(lines[1].starts_with("rent = 1000") && lines[1].ends_with("= 1000"));
+    assert_eq!(lines[2], "");
+    assert!(lines[3].starts_with("rent * 2") && lines[3].ends_with("= 2000"));
+}
+
+#[test]
+fn skip_empty_omits_comments_and_blank_lines() {
+    let lines = run_file_mode(&["--skip-empty"]);
+    assert_eq!(lines.len(), 2, "{lines:?}");
+    assert!(lines[0].ends_with("= 1000"));
+    assert!(lines[1].ends_with("= 2000"));
+}
+
+#[test]
+fn markdown_renders_headings_and_tables() {
+    let lines = run_file_mode(&["--markdown"]);
+    assert_eq!(
+        lines,
+        [
+            "## Budget",
+            "",
+            "| Input | Result |",
+            "| --- | --- |",
+            "| `rent = 1000` | 1000 |",
+            "",
+            "| Input | Result |",
+            "| --- | --- |",
+            "| `rent * 2` | 2000 |",
+        ]
+    );
+}
+
+#[test]
+fn offline_and_refresh_conflict() {
+    let output = Command::new(env!("CARGO_BIN_EXE_numr-cli"))
+        .args(["--offline", "--refresh", "1 + 1"])
+        .output()
+        .expect("failed to run numr-cli");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn offline_never_reports_a_fetch() {
+    let home = std::env::temp_dir().join(format!(
+        "numr-offline-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    let output = Command::new(env!("CARGO_BIN_EXE_numr-cli"))
+        .args(["--offline", "1 + 1"])
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", &home)
+        .output()
+        .expect("failed to run numr-cli");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "2");
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
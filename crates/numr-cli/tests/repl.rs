@@ -0,0 +1,109 @@
+//! End-to-end tests for the interactive REPL (`-i`).
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Feed `input` to `numr-cli -i` with pinned rates and return its stdout lines
+fn run_repl(input: &str) -> Vec<String> {
+    run_repl_with(&[], input)
+}
+
+/// `run_repl` with extra arguments
+fn run_repl_with(extra_args: &[&str], input: &str) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_numr-cli"))
+        .args(["-i", "--default-rates", "v1"])
+        .args(extra_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run numr-cli");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "numr-cli failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.trim_start_matches("> ").trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn units_lists_types_and_the_units_of_one_type() {
+    let lines = run_repl("units\nunits length\nunits nope\n");
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("Unit types: length, area")));
+    assert!(lines
+        .iter()
+        .any(|line| line.trim() == "km   kilometer, kilometers"));
+    assert!(lines.iter().any(|line| line.trim() == "ft   foot, feet"));
+    assert!(!lines.iter().any(|line| line.trim().starts_with("kg")));
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("Unknown unit type: nope")));
+}
+
+#[test]
+fn units_is_still_a_variable_name() {
+    let lines = run_repl("units = 5\nunits * 2\nunitsold = 7\nunitsold\n");
+    assert!(lines.contains(&"5".to_string()), "{lines:?}");
+    assert!(lines.contains(&"10".to_string()), "{lines:?}");
+    assert_eq!(
+        lines.iter().filter(|line| *line == "7").count(),
+        2,
+        "{lines:?}"
+    );
+}
+
+#[test]
+fn rates_prints_the_pinned_table() {
+    let lines = run_repl("rates\n");
+    assert!(lines.contains(&"Built-in rates (nothing fetched)".to_string()));
+    assert!(
+        lines.iter().any(|line| line.trim() == "1 USD   = 0.92 EUR"),
+        "{lines:?}"
+    );
+    assert!(lines
+        .iter()
+        .any(|line| line.trim() == "1 BTC   = 95000 USD"));
+    assert!(!lines.iter().any(|line| line.trim().starts_with("1 EUR")));
+}
+
+#[test]
+fn a_session_continues_from_a_file_and_saves_back() {
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("numr-session-{nonce}.numr"));
+    std::fs::write(&path, "# Budget\nrent = 1000\n").unwrap();
+    let file = path.to_str().unwrap();
+
+    let lines = run_repl_with(&["-f", file], "rent * 2\nsave = 3\nsave\n");
+    assert!(lines.contains(&"2000".to_string()), "{lines:?}");
+    assert!(lines.contains(&"3".to_string()), "{lines:?}");
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("Saved 4 lines to")));
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(saved, "# Budget\nrent = 1000\nrent * 2\nsave = 3\n");
+}
+
+#[test]
+fn save_without_a_file_needs_a_path() {
+    let lines = run_repl("1 + 1\nsave\n");
+    assert!(lines.contains(&"Usage: save FILE".to_string()), "{lines:?}");
+}
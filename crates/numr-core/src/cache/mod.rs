@@ -9,7 +9,7 @@ use crate::types::Currency;
 use rust_decimal::Decimal;
 #[cfg(not(target_arch = "wasm32"))]
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[cfg(not(target_arch = "wasm32"))]
 use directories::ProjectDirs;
@@ -40,6 +40,12 @@ struct CachedRates {
 #[derive(Clone)]
 pub struct RateCache {
     pub(crate) rates: HashMap<(Currency, Currency), Decimal>,
+    /// Pairs whose rate was set directly rather than derived as a reciprocal
+    stated: HashSet<(Currency, Currency)>,
+    /// Raw rates from the last applied set whose codes are not a `Currency`
+    unsupported: BTreeMap<String, Decimal>,
+    /// Unix time the applied raw rates were fetched; `None` for built-in rates
+    updated_at: Option<u64>,
 }
 
 impl RateCache {
@@ -47,6 +53,9 @@ impl RateCache {
     pub fn new() -> Self {
         Self {
             rates: HashMap::new(),
+            stated: HashSet::new(),
+            unsupported: BTreeMap::new(),
+            updated_at: None,
         }
     }
 
@@ -56,6 +65,12 @@ impl RateCache {
     }
 
     /// Set a rate and its reciprocal without allowing Decimal overflow.
+    ///
+    /// A reverse rate that was itself set directly is kept rather than replaced
+    /// by the reciprocal: `1 / rate` is rounded to 28 digits, and a quoted
+    /// rate in each direction is what a source that publishes both means.
+    /// Quoting a pair again makes that reverse quote stale, so it is then
+    /// replaced by the new reciprocal.
     pub fn try_set_rate(
         &mut self,
         from: Currency,
@@ -71,8 +86,13 @@ impl RateCache {
             operation: "inverting an exchange rate",
         })?;
         self.rates.insert((from, to), rate);
-        // Also store the inverse rate
-        self.rates.insert((to, from), inverse);
+        if !self.stated.insert((from, to)) {
+            self.stated.remove(&(to, from));
+        }
+        // Also store the inverse rate, unless it was quoted directly
+        if !self.stated.contains(&(to, from)) {
+            self.rates.insert((to, from), inverse);
+        }
         Ok(())
     }
 
@@ -91,7 +111,25 @@ impl RateCache {
             return Ok(Some(*rate));
         }
 
-        // BFS to find conversion path
+        // Resolve each unordered pair along a single path so that converting
+        // there and back is consistent: one direction is the inverse of the other.
+        if from.code() > to.code() {
+            return match self.find_path_rate(to, from)? {
+                Some(rate) => Decimal::ONE
+                    .checked_div(rate)
+                    .map(Some)
+                    .ok_or(EvalError::Overflow {
+                        operation: "inverting an exchange rate",
+                    }),
+                None => Ok(None),
+            };
+        }
+        self.find_path_rate(from, to)
+    }
+
+    /// Breadth-first search for a triangulated rate, visiting neighbours in
+    /// registry order so the chosen path is deterministic.
+    fn find_path_rate(&self, from: Currency, to: Currency) -> Result<Option<Decimal>, EvalError> {
         let mut queue = std::collections::VecDeque::new();
         let mut visited = std::collections::HashSet::new();
         let mut distances = HashMap::new();
@@ -109,14 +147,17 @@ impl RateCache {
                 continue; // Should never happen, but handle gracefully
             };
 
-            for ((start, end), rate) in &self.rates {
-                if *start == current && !visited.contains(end) {
-                    visited.insert(*end);
+            for end in Currency::all() {
+                let Some(rate) = self.rates.get(&(current, end)) else {
+                    continue;
+                };
+                if !visited.contains(&end) {
+                    visited.insert(end);
                     let combined = current_rate.checked_mul(*rate).ok_or(EvalError::Overflow {
                         operation: "combining exchange rates",
                     })?;
-                    distances.insert(*end, combined);
-                    queue.push_back(*end);
+                    distances.insert(end, combined);
+                    queue.push_back(end);
                 }
             }
         }
@@ -159,6 +200,7 @@ impl RateCache {
         }
 
         self.apply_raw_rates(&cached.rates)?;
+        self.updated_at = Some(cached.timestamp);
         Ok(true)
     }
 
@@ -223,24 +265,30 @@ impl RateCache {
     /// Apply raw rates from API response
     /// - Fiat rates: "1 USD = X currency" (from exchangerate-api)
     /// - Crypto rates: "1 TOKEN = X USD" (from coingecko)
+    ///
+    /// Codes that are not a supported `Currency` are skipped and kept in
+    /// [`RateCache::unsupported_rates`] until the next set is applied.
     pub fn apply_raw_rates(
         &mut self,
         raw_rates: &HashMap<String, Decimal>,
     ) -> Result<usize, RateError> {
         let mut staged = self.clone();
+        staged.unsupported.clear();
         let mut applied = 0usize;
         for (code, rate) in raw_rates {
-            if let Ok(currency) = code.parse::<Currency>() {
-                let result = if currency.is_crypto() {
-                    // Crypto: 1 TOKEN = X USD
-                    staged.try_set_rate(currency, Currency::USD, *rate)
-                } else {
-                    // Fiat: 1 USD = X currency
-                    staged.try_set_rate(Currency::USD, currency, *rate)
-                };
-                result.map_err(|error| RateError::InvalidRates(format!("{code}: {error}")))?;
-                applied += 1;
-            }
+            let Ok(currency) = code.parse::<Currency>() else {
+                staged.unsupported.insert(code.clone(), *rate);
+                continue;
+            };
+            let result = if currency.is_crypto() {
+                // Crypto: 1 TOKEN = X USD
+                staged.try_set_rate(currency, Currency::USD, *rate)
+            } else {
+                // Fiat: 1 USD = X currency
+                staged.try_set_rate(Currency::USD, currency, *rate)
+            };
+            result.map_err(|error| RateError::InvalidRates(format!("{code}: {error}")))?;
+            applied += 1;
         }
         if applied == 0 {
             return Err(RateError::InvalidRates(
@@ -248,50 +296,147 @@ impl RateCache {
             ));
         }
         self.rates = staged.rates;
+        self.stated = staged.stated;
+        self.unsupported = staged.unsupported;
+        // Rates applied directly were just fetched; a cache load overrides this
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.updated_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|duration| duration.as_secs());
+        }
         Ok(applied)
     }
 
-    /// Load default/fallback rates (for offline use when no cache exists)
+    /// Unix time the current raw rates were fetched, from the cache file or
+    /// the last [`RateCache::apply_raw_rates`]. `None` for built-in rates.
+    #[must_use]
+    pub fn updated_at(&self) -> Option<u64> {
+        self.updated_at
+    }
+
+    /// Loaded rates as `(from, to, rate)`, sorted by currency codes.
+    /// Reciprocals derived from a rate that was set directly are left out.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<(Currency, Currency, Decimal)> {
+        let mut rates: Vec<_> = self
+            .rates
+            .iter()
+            .filter(|(&(from, to), _)| {
+                self.stated.contains(&(from, to)) || !self.stated.contains(&(to, from))
+            })
+            .map(|(&(from, to), &rate)| (from, to, rate))
+            .collect();
+        rates.sort_by(|a, b| (a.0.code(), a.1.code()).cmp(&(b.0.code(), b.1.code())));
+        rates
+    }
+
+    /// Raw rates skipped by the last [`RateCache::apply_raw_rates`] because
+    /// numr does not model their currency, sorted by code
+    #[must_use]
+    pub fn unsupported_rates(&self) -> &BTreeMap<String, Decimal> {
+        &self.unsupported
+    }
+
+    /// Load default/fallback rates (for offline use when no cache exists).
+    /// These are the latest built-in set and may change between releases.
     pub fn load_defaults(&mut self) {
+        self.load_default_set(DefaultRates::LATEST);
+    }
+
+    /// Load a frozen, versioned set of built-in rates over the current ones
+    pub fn load_default_set(&mut self, set: DefaultRates) {
         use std::str::FromStr;
 
-        // Helper to create Decimal from string
-        let d = |s: &str| Decimal::from_str(s).unwrap();
+        for (currency, rate) in set.rates() {
+            let rate = Decimal::from_str(rate).expect("built-in rates are valid decimals");
+            if currency.is_crypto() {
+                self.set_rate(*currency, Currency::USD, rate);
+            } else {
+                self.set_rate(Currency::USD, *currency, rate);
+            }
+        }
+    }
+
+    /// A cache holding exactly one versioned set of built-in rates
+    #[must_use]
+    pub fn with_default_set(set: DefaultRates) -> Self {
+        let mut cache = Self::new();
+        cache.load_default_set(set);
+        cache
+    }
+}
+
+/// A frozen set of built-in rates. Pinning one keeps documentation and
+/// examples reproducible when the latest defaults are updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefaultRates {
+    /// Approximate rates from late 2024 (1 USD = 0.92 EUR, 1 BTC = $95,000)
+    V1,
+}
+
+impl DefaultRates {
+    /// The set loaded by [`RateCache::load_defaults`]
+    pub const LATEST: Self = Self::V1;
 
-        // Fiat rates (1 USD = X currency) - approximate values
-        self.set_rate(Currency::USD, Currency::EUR, d("0.92"));
-        self.set_rate(Currency::USD, Currency::GBP, d("0.79"));
-        self.set_rate(Currency::USD, Currency::JPY, d("150"));
-        self.set_rate(Currency::USD, Currency::CHF, d("0.88"));
-        self.set_rate(Currency::USD, Currency::CNY, d("7.25"));
-        self.set_rate(Currency::USD, Currency::CAD, d("1.40"));
-        self.set_rate(Currency::USD, Currency::AUD, d("1.55"));
-        self.set_rate(Currency::USD, Currency::INR, d("84"));
-        self.set_rate(Currency::USD, Currency::KRW, d("1400"));
-        self.set_rate(Currency::USD, Currency::RUB, d("92"));
-        self.set_rate(Currency::USD, Currency::ILS, d("3.65"));
-        self.set_rate(Currency::USD, Currency::PLN, d("4"));
-        self.set_rate(Currency::USD, Currency::UAH, d("41"));
-
-        // Crypto rates (1 TOKEN = X USD) - approximate values
-        self.set_rate(Currency::BTC, Currency::USD, d("95000"));
-        self.set_rate(Currency::ETH, Currency::USD, d("3500"));
-        self.set_rate(Currency::SOL, Currency::USD, d("150"));
-        self.set_rate(Currency::USDT, Currency::USD, d("1"));
-        self.set_rate(Currency::USDC, Currency::USD, d("1"));
-        self.set_rate(Currency::BNB, Currency::USD, d("650"));
-        self.set_rate(Currency::XRP, Currency::USD, d("1.5"));
-        self.set_rate(Currency::ADA, Currency::USD, d("1"));
-        self.set_rate(Currency::DOGE, Currency::USD, d("0.40"));
-        self.set_rate(Currency::DOT, Currency::USD, d("8"));
-        self.set_rate(Currency::LTC, Currency::USD, d("100"));
-        self.set_rate(Currency::LINK, Currency::USD, d("18"));
-        self.set_rate(Currency::AVAX, Currency::USD, d("45"));
-        self.set_rate(Currency::MATIC, Currency::USD, d("0.55"));
-        self.set_rate(Currency::TON, Currency::USD, d("6"));
+    /// Parse a version name such as `v1`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "v1" | "1" => Some(Self::V1),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+        }
+    }
+
+    /// Fiat rates as 1 USD = X currency, crypto rates as 1 token = X USD
+    fn rates(self) -> &'static [(Currency, &'static str)] {
+        match self {
+            Self::V1 => DEFAULT_RATES_V1,
+        }
     }
 }
 
+/// Frozen: add a new version rather than editing these values
+const DEFAULT_RATES_V1: &[(Currency, &str)] = &[
+    // Fiat (1 USD = X currency)
+    (Currency::EUR, "0.92"),
+    (Currency::GBP, "0.79"),
+    (Currency::JPY, "150"),
+    (Currency::CHF, "0.88"),
+    (Currency::CNY, "7.25"),
+    (Currency::CAD, "1.40"),
+    (Currency::AUD, "1.55"),
+    (Currency::INR, "84"),
+    (Currency::KRW, "1400"),
+    (Currency::RUB, "92"),
+    (Currency::ILS, "3.65"),
+    (Currency::PLN, "4"),
+    (Currency::UAH, "41"),
+    // Crypto (1 TOKEN = X USD)
+    (Currency::BTC, "95000"),
+    (Currency::ETH, "3500"),
+    (Currency::SOL, "150"),
+    (Currency::USDT, "1"),
+    (Currency::USDC, "1"),
+    (Currency::BNB, "650"),
+    (Currency::XRP, "1.5"),
+    (Currency::ADA, "1"),
+    (Currency::DOGE, "0.40"),
+    (Currency::DOT, "8"),
+    (Currency::LTC, "100"),
+    (Currency::LINK, "18"),
+    (Currency::AVAX, "45"),
+    (Currency::MATIC, "0.55"),
+    (Currency::TON, "6"),
+];
+
 impl Default for RateCache {
     fn default() -> Self {
         let mut cache = Self::new();
@@ -333,6 +478,54 @@ mod tests {
         assert!(cache.get_rate(Currency::EUR, Currency::USD).is_some());
     }
 
+    fn assert_round_trip(cache: &RateCache, from: Currency, to: Currency) {
+        let there = cache.get_rate(from, to).unwrap();
+        let back = cache.get_rate(to, from).unwrap();
+        let drift = (there * back - Decimal::ONE).abs();
+        assert!(
+            drift < Decimal::from_str("1e-20").unwrap(),
+            "{from} -> {to} -> {from} drifted by {drift}"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_direct_and_triangulated() {
+        let mut cache = RateCache::new();
+        cache.load_defaults();
+        assert_round_trip(&cache, Currency::USD, Currency::EUR);
+        assert_round_trip(&cache, Currency::EUR, Currency::GBP);
+        assert_round_trip(&cache, Currency::JPY, Currency::BTC);
+        assert_round_trip(&cache, Currency::SOL, Currency::UAH);
+    }
+
+    #[test]
+    fn test_round_trip_with_inconsistent_paths() {
+        // Two routes from EUR to GBP disagree; both directions must use the same one.
+        let mut cache = RateCache::new();
+        cache.set_rate(
+            Currency::USD,
+            Currency::EUR,
+            Decimal::from_str("0.92").unwrap(),
+        );
+        cache.set_rate(
+            Currency::USD,
+            Currency::GBP,
+            Decimal::from_str("0.79").unwrap(),
+        );
+        cache.set_rate(
+            Currency::EUR,
+            Currency::CHF,
+            Decimal::from_str("0.95").unwrap(),
+        );
+        cache.set_rate(
+            Currency::CHF,
+            Currency::GBP,
+            Decimal::from_str("0.91").unwrap(),
+        );
+        assert_round_trip(&cache, Currency::EUR, Currency::GBP);
+        assert_round_trip(&cache, Currency::GBP, Currency::EUR);
+    }
+
     #[test]
     fn test_same_currency() {
         let cache = RateCache::new();
@@ -353,6 +546,31 @@ mod tests {
         assert!(cache.get_rate(Currency::PLN, Currency::USD).is_some());
     }
 
+    #[test]
+    fn test_versioned_default_rates_are_pinned() {
+        let cache = RateCache::with_default_set(DefaultRates::V1);
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+        assert_eq!(
+            cache.get_rate(Currency::USD, Currency::EUR),
+            Some(d("0.92"))
+        );
+        assert_eq!(
+            cache.get_rate(Currency::BTC, Currency::USD),
+            Some(d("95000"))
+        );
+        assert_eq!(cache.get_rate(Currency::USD, Currency::JPY), Some(d("150")));
+
+        // Every supported currency has a pinned rate
+        for currency in Currency::all() {
+            assert!(
+                cache.get_rate(currency, Currency::USD).is_some(),
+                "{currency:?}"
+            );
+        }
+        assert_eq!(DefaultRates::parse("V1"), Some(DefaultRates::V1));
+        assert_eq!(DefaultRates::parse("v0"), None);
+    }
+
     #[test]
     fn test_cross_conversion() {
         // Use load_defaults() directly instead of default() to avoid file cache interference
@@ -416,6 +634,113 @@ mod tests {
         fs::remove_dir_all(directory).unwrap();
     }
 
+    #[test]
+    fn quoted_reverse_rates_are_not_replaced_by_reciprocals() {
+        let quoted = Decimal::from_str("0.3334").unwrap();
+
+        // Without a quote for the reverse direction, the reciprocal is used
+        let mut derived = RateCache::new();
+        derived.set_rate(Currency::EUR, Currency::USD, Decimal::from(3));
+        assert_eq!(
+            derived.get_rate(Currency::USD, Currency::EUR),
+            Some(Decimal::ONE / Decimal::from(3))
+        );
+
+        // Both directions quoted, in either order: each lookup uses its quote
+        for reverse_first in [false, true] {
+            let mut cache = RateCache::new();
+            if reverse_first {
+                cache.set_rate(Currency::USD, Currency::EUR, quoted);
+            }
+            cache.set_rate(Currency::EUR, Currency::USD, Decimal::from(3));
+            if !reverse_first {
+                cache.set_rate(Currency::USD, Currency::EUR, quoted);
+            }
+            assert_eq!(cache.get_rate(Currency::USD, Currency::EUR), Some(quoted));
+            assert_eq!(
+                cache.get_rate(Currency::EUR, Currency::USD),
+                Some(Decimal::from(3))
+            );
+        }
+
+        // A new quote for a pair replaces the older quote in the other direction
+        let mut cache = RateCache::new();
+        cache.set_rate(
+            Currency::USD,
+            Currency::EUR,
+            Decimal::from_str("0.5").unwrap(),
+        );
+        cache.set_rate(Currency::EUR, Currency::USD, Decimal::TWO);
+        cache.set_rate(
+            Currency::USD,
+            Currency::EUR,
+            Decimal::from_str("0.25").unwrap(),
+        );
+        assert_eq!(
+            cache.get_rate(Currency::EUR, Currency::USD),
+            Some(Decimal::from(4))
+        );
+
+        // ...including a manual quote when raw rates are applied again
+        let mut cache = RateCache::new();
+        let raw = HashMap::from([("EUR".to_string(), Decimal::from_str("0.5").unwrap())]);
+        cache.apply_raw_rates(&raw).unwrap();
+        cache.set_rate(Currency::EUR, Currency::USD, Decimal::from(3));
+        let raw = HashMap::from([("EUR".to_string(), Decimal::from_str("0.25").unwrap())]);
+        cache.apply_raw_rates(&raw).unwrap();
+        assert_eq!(
+            cache.get_rate(Currency::EUR, Currency::USD),
+            Some(Decimal::from(4))
+        );
+    }
+
+    #[test]
+    fn unsupported_codes_are_kept_aside() {
+        let mut cache = RateCache::new();
+        let raw = HashMap::from([
+            ("EUR".to_string(), Decimal::new(92, 2)),
+            ("XCG".to_string(), Decimal::new(179, 2)),
+            ("ANG".to_string(), Decimal::new(179, 2)),
+        ]);
+        assert_eq!(cache.apply_raw_rates(&raw).unwrap(), 1);
+        let codes: Vec<&str> = cache
+            .unsupported_rates()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(codes, ["ANG", "XCG"]);
+        assert_eq!(cache.unsupported_rates()["XCG"], Decimal::new(179, 2));
+
+        // A rejected set leaves the previous report in place
+        let invalid = HashMap::from([("ZZZ".to_string(), Decimal::ONE)]);
+        assert!(cache.apply_raw_rates(&invalid).is_err());
+        assert_eq!(cache.unsupported_rates().len(), 2);
+
+        let supported = HashMap::from([("EUR".to_string(), Decimal::ONE)]);
+        cache.apply_raw_rates(&supported).unwrap();
+        assert!(cache.unsupported_rates().is_empty());
+    }
+
+    #[test]
+    fn snapshot_lists_quoted_rates_and_their_age() {
+        let mut cache = RateCache::with_default_set(DefaultRates::V1);
+        let snapshot = cache.snapshot();
+        assert_eq!(snapshot.len(), DEFAULT_RATES_V1.len());
+        assert!(snapshot.contains(&(Currency::USD, Currency::EUR, Decimal::new(92, 2))));
+        assert!(snapshot.contains(&(Currency::BTC, Currency::USD, Decimal::from(95_000))));
+        assert!(!snapshot
+            .iter()
+            .any(|&(from, to, _)| (from, to) == (Currency::EUR, Currency::USD)));
+        assert!(snapshot.windows(2).all(
+            |pair| (pair[0].0.code(), pair[0].1.code()) < (pair[1].0.code(), pair[1].1.code())
+        ));
+        assert_eq!(cache.updated_at(), None);
+
+        let raw = HashMap::from([("EUR".to_string(), Decimal::new(9, 1))]);
+        cache.apply_raw_rates(&raw).unwrap();
+        assert!(cache.updated_at().is_some());
+    }
+
     #[test]
     fn raw_rates_are_validated_before_mutating_or_persisting() {
         let mut cache = RateCache::new();
@@ -1,5 +1,6 @@
 //! Language metadata shared by evaluators, editors, and other adapters.
 
+use rust_decimal::Decimal;
 use serde::Serialize;
 
 use crate::CURRENCIES;
@@ -13,9 +14,12 @@ pub const BUILTIN_FUNCTIONS: &[&str] = &[
     "min",
     "max",
     "median",
+    "count",
+    "count_nonzero",
     "clamp",
     "abs",
     "round",
+    "round_to",
     "floor",
     "ceil",
     "sin",
@@ -35,10 +39,58 @@ pub const BUILTIN_FUNCTIONS: &[&str] = &[
     "factorial",
     "mod",
     "log_y",
+    "apr_to_apy",
+    "apy_to_apr",
+    "pct_of_total",
 ];
 
 /// Word operators recognized by the grammar.
-pub const KEYWORDS: &[&str] = &["of", "in", "to"];
+pub const KEYWORDS: &[&str] = &[
+    "of", "in", "to", "as", "per", "more", "less", "than", "until", "ago", "at", "each", "repeat",
+    "xor",
+];
+
+/// Scale words accepted after a number ("2 million"), with their power of ten.
+pub const SCALE_WORDS: &[(&str, u32)] = &[
+    ("thousand", 3),
+    ("million", 6),
+    ("billion", 9),
+    ("trillion", 12),
+];
+
+/// Words read as quantities ("a dozen", "half of 200"), with their value as a
+/// mantissa and decimal scale. Kept in sync with `quantity_name` in the grammar.
+pub const QUANTITY_WORDS: &[(&str, i64, u32)] =
+    &[("half", 5, 1), ("quarter", 25, 2), ("dozen", 12, 0)];
+
+/// SI prefixes accepted as multipliers when written directly after a number
+/// ("3k", "2.5M", "40µ"), with their power of ten. Matched case-sensitively so
+/// that "300K" stays kelvin and "5g" grams. Lowercase `m` is always meters.
+pub const SI_SUFFIXES: &[(&str, i32)] = &[
+    ("k", 3),
+    ("M", 6),
+    ("G", 9),
+    ("T", 12),
+    ("µ", -6),
+    ("μ", -6),
+];
+
+/// SI suffixes that also spell a unit (metres, grams, tonnes). They keep the
+/// unit reading when a unit follows: "1T in kg" is a tonne, "5M km" five metres.
+pub const UNIT_SI_SUFFIXES: &[&str] = &["M", "G", "T"];
+
+/// Shorthand multipliers written straight after a money amount ("$1.5k",
+/// "€2m", "$3bn"), with their power of ten. Kept in sync with `money_suffix`
+/// in the grammar.
+pub const MONEY_SUFFIXES: &[(&str, u32)] = &[
+    ("k", 3),
+    ("K", 3),
+    ("m", 6),
+    ("M", 6),
+    ("b", 9),
+    ("B", 9),
+    ("bn", 9),
+];
 
 /// Built-in mathematical constants.
 pub const MATH_CONSTANTS: &[&str] = &["pi", "e", "phi"];
@@ -46,6 +98,9 @@ pub const MATH_CONSTANTS: &[&str] = &["pi", "e", "phi"];
 /// Aliases for the previous successful value.
 pub const ANSWER_ALIASES: &[&str] = &["_", "ANS", "ans"];
 
+/// Words that stand for earlier line results inside aggregates (`sum(above)`).
+pub const LINE_REFERENCES: &[&str] = &["above"];
+
 #[must_use]
 pub fn is_builtin_function(name: &str) -> bool {
     BUILTIN_FUNCTIONS
@@ -53,6 +108,52 @@ pub fn is_builtin_function(name: &str) -> bool {
         .any(|candidate| candidate.eq_ignore_ascii_case(name))
 }
 
+/// Power of ten for a scale word, ignoring ASCII case.
+#[must_use]
+pub fn scale_word_exponent(word: &str) -> Option<u32> {
+    SCALE_WORDS
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(word))
+        .map(|(_, exponent)| *exponent)
+}
+
+/// Value of a quantity word or a standalone scale word ("thousand"), ignoring ASCII case.
+#[must_use]
+pub fn quantity_word_value(word: &str) -> Option<Decimal> {
+    QUANTITY_WORDS
+        .iter()
+        .find(|(candidate, _, _)| candidate.eq_ignore_ascii_case(word))
+        .map(|(_, mantissa, scale)| Decimal::new(*mantissa, *scale))
+        .or_else(|| {
+            let exponent = scale_word_exponent(word)?;
+            10i64.checked_pow(exponent).map(Decimal::from)
+        })
+}
+
+/// Power of ten for an SI suffix attached to a number, matching case exactly.
+#[must_use]
+pub fn si_suffix_exponent(suffix: &str) -> Option<i32> {
+    SI_SUFFIXES
+        .iter()
+        .find(|(candidate, _)| *candidate == suffix)
+        .map(|(_, exponent)| *exponent)
+}
+
+/// Power of ten for a money shorthand suffix ("k" in "$1.5k"). Single
+/// letters are case-sensitive like SI suffixes; "bn" is not.
+#[must_use]
+pub fn money_suffix_exponent(suffix: &str) -> Option<u32> {
+    let suffix = if suffix.eq_ignore_ascii_case("bn") {
+        "bn"
+    } else {
+        suffix
+    };
+    MONEY_SUFFIXES
+        .iter()
+        .find(|(candidate, _)| *candidate == suffix)
+        .map(|(_, exponent)| *exponent)
+}
+
 /// Stable transport metadata for currency pickers and rate providers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct CurrencyMetadata {
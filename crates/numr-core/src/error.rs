@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::types::Currency;
+
 /// Why an input line could not be parsed.
 #[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
 pub enum ParseError {
@@ -13,11 +15,25 @@ pub enum ParseError {
     #[error("expression is nested too deeply ({actual}; maximum is {max})")]
     TooDeep { actual: usize, max: usize },
     #[error("could not understand line")]
-    InvalidSyntax,
+    InvalidSyntax {
+        /// Character (not byte) offset where parsing stopped
+        offset: usize,
+    },
     #[error("invalid expression: {0}")]
     InvalidExpression(String),
 }
 
+impl ParseError {
+    /// Character offset of the failure, for pointing at the offending column
+    #[must_use]
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::InvalidSyntax { offset } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
 /// Why a parsed expression could not be evaluated.
 #[derive(Debug, Clone, PartialEq, Eq, Error, Serialize)]
 pub enum EvalError {
@@ -31,8 +47,24 @@ pub enum EvalError {
     UnknownVariable(String),
     #[error("unknown function: {0}")]
     UnknownFunction(String),
+    #[error("function calls are nested too deeply (maximum is {max})")]
+    CallDepth { max: usize },
+    #[error("too many function calls on one line (maximum is {max})")]
+    CallBudget { max: usize },
     #[error("unknown target unit: {0}")]
     UnknownTarget(String),
+    #[error("no exchange rate for {from} to {to}")]
+    NoRate { from: Currency, to: Currency },
+    /// Quantities that cannot be added, subtracted, or converted into one another
+    #[error("Cannot {operation} {left} {} {right}", if *.operation == "convert" { "to" } else { "and" })]
+    IncompatibleUnits {
+        operation: &'static str,
+        left: String,
+        right: String,
+    },
+    /// Statements and calls that sandboxed evaluation refuses
+    #[error("{0} is not allowed in sandbox")]
+    NotAllowed(&'static str),
     #[error("{0}")]
     InvalidOperands(String),
     #[error("{0}")]
@@ -46,6 +78,28 @@ impl EvalError {
     pub fn overflow(operation: &'static str) -> Self {
         Self::Overflow { operation }
     }
+
+    /// Stable machine-readable name of the error kind, for front-ends that
+    /// branch on failures instead of matching display text
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Parse(_) => "parse",
+            Self::Overflow { .. } => "overflow",
+            Self::DivisionByZero => "division_by_zero",
+            Self::UnknownVariable(_) => "unknown_variable",
+            Self::UnknownFunction(_) => "unknown_function",
+            Self::CallDepth { .. } => "call_depth",
+            Self::CallBudget { .. } => "call_budget",
+            Self::UnknownTarget(_) => "unknown_target",
+            Self::NoRate { .. } => "no_rate",
+            Self::IncompatibleUnits { .. } => "incompatible_units",
+            Self::NotAllowed(_) => "not_allowed",
+            Self::InvalidOperands(_) => "invalid_operands",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::Message(_) => "error",
+        }
+    }
 }
 
 impl From<String> for EvalError {
@@ -86,3 +140,16 @@ pub enum RateError {
     #[error("invalid rate response: {0}")]
     Response(String),
 }
+
+/// Failures exporting or importing engine state snapshots.
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("invalid engine state: {0}")]
+    Deserialize(#[source] serde_json::Error),
+    #[error("failed to serialize engine state: {0}")]
+    Serialize(#[source] serde_json::Error),
+    #[error("unsupported engine state version {0}")]
+    UnsupportedVersion(u32),
+    #[error("invalid exchange rate in engine state: {0}")]
+    InvalidRate(String),
+}
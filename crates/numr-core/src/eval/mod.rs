@@ -1,5 +1,7 @@
 //! Expression evaluation engine
 
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
@@ -7,15 +9,174 @@ use rust_decimal::Decimal;
 use rust_decimal::MathematicalOps;
 
 use crate::cache::RateCache;
-use crate::error::EvalError;
+use crate::catalog::{is_builtin_function, ANSWER_ALIASES, LINE_REFERENCES};
+use crate::error::{EvalError, ParseError};
 use crate::parser::{Ast, BinaryOp, Expr};
-use crate::types::{unit, Currency, NumberBase, Value};
+use crate::types::{unit, Currency, Date, NumberBase, RoundingMode, Value};
+use serde::{Deserialize, Serialize};
+
+mod totals;
+
+pub use totals::Totals;
+pub(crate) use totals::{group_totals, plain_total, totals_breakdown};
+
+/// Evaluation and reporting policies that are off by default
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalOptions {
+    /// Add or subtract values with mismatched units as raw amounts, producing a
+    /// plain number and a warning instead of an error (`5 km + 2 kg` = 7)
+    pub lenient_units: bool,
+    /// Keep groups that net to zero in grouped totals (`$0.00`) instead of omitting them
+    pub include_zero_totals: bool,
+    /// Add the sum of plain numbers to grouped totals as a row of its own,
+    /// after the currency and unit totals
+    pub include_plain_totals: bool,
+    /// Reject adding or subtracting a plain number and a currency or unit amount
+    /// (`1000 + 1000 RUB`) instead of reading the number in the other side's terms
+    pub strict_units: bool,
+    /// Keep at most this many user variables, evicting the least recently used.
+    /// `total` and the previous-result aliases are managed by the engine and never count.
+    pub max_variables: Option<usize>,
+    /// Let `sum(above)` and the other aggregates read at most this many of the
+    /// most recent lines in their block, bounding their cost in long documents
+    pub max_aggregate_lines: Option<usize>,
+    /// Read textual currency indicators from pasted data (`US$100`, `Rs 500`)
+    /// as the currencies they stand for before parsing
+    pub normalize_currency_symbols: bool,
+    /// How `round()` treats midpoints and extra digits
+    pub rounding: RoundingMode,
+    /// What multiplying a value by a percentage produces
+    pub percent_multiply: PercentMultiply,
+    /// Warn when multiplying or dividing units gives dimensions that no
+    /// registered unit measures, such as `kg·°C` or `m·s`
+    pub warn_unusual_units: bool,
+    /// Evaluate untrusted input read-only: assignments, function definitions,
+    /// `repeat`, and calls to user functions fail with `EvalError::NotAllowed`
+    pub sandbox: bool,
+    /// Reject lines whose expression tree is deeper than this, below the
+    /// parser's own nesting limit; see [`crate::Ast::depth`]
+    pub max_depth: Option<usize>,
+    /// Reject powers whose exponent is larger than this in magnitude (`2 ^ 10000000`)
+    pub max_exponent: Option<u32>,
+    /// How numbers in the input group digits and mark decimals
+    pub number_locale: NumberLocale,
+    /// Which currency a bare `¥` stands for; `JP¥` and `CN¥` always name their own
+    pub yen_symbol: YenSymbol,
+}
+
+/// How `value * percentage` is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PercentMultiply {
+    /// Scale the value, keeping its currency or unit, in either operand order:
+    /// `$100 * 20%` and `20% * $100` are `$20.00`, the same as `20% of $100`
+    #[default]
+    Scale,
+    /// Read the percentage as a bare ratio, giving a plain number: `$100 * 20%` = `20`
+    Ratio,
+}
+
+/// How numbers are written in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberLocale {
+    /// `1,234.56`: commas group digits and `.` is the decimal point
+    #[default]
+    Us,
+    /// `1.234,56`: dots group digits and `,` is the decimal point.
+    /// Inside a function call a comma still separates arguments: `max(1,5)`.
+    European,
+}
+
+impl NumberLocale {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "us" | "en" => Some(Self::Us),
+            "eu" | "european" => Some(Self::European),
+            _ => None,
+        }
+    }
+}
+
+/// The currency written as a bare `¥`, which yen and yuan share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YenSymbol {
+    /// Japanese yen
+    #[default]
+    Jpy,
+    /// Chinese yuan
+    Cny,
+}
+
+impl YenSymbol {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "jpy" | "yen" => Some(Self::Jpy),
+            "cny" | "rmb" | "yuan" => Some(Self::Cny),
+            _ => None,
+        }
+    }
+
+    /// The currency `¥` reads as
+    pub fn currency(self) -> Currency {
+        match self {
+            Self::Jpy => Currency::JPY,
+            Self::Cny => Currency::CNY,
+        }
+    }
+}
+
+/// Deepest chain of user function calls, which bounds runaway recursion
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// Most user function calls one line may make, which bounds functions that
+/// call themselves more than once (`f(n) = f(n) + f(n)`) below the depth limit
+pub const MAX_CALLS_PER_LINE: usize = 100_000;
+
+/// A formula defined with `name(params) = body`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunction {
+    pub params: Vec<String>,
+    pub body: Expr,
+}
+
+/// Names the engine sets itself before each line, as opposed to user variables
+pub(crate) fn is_engine_variable(name: &str) -> bool {
+    name == "total" || ANSWER_ALIASES.contains(&name)
+}
 
 /// Evaluation context with variables and rates
 #[derive(Clone)]
 pub struct EvalContext {
     pub(crate) variables: HashMap<String, Value>,
     pub(crate) rate_cache: RateCache,
+    pub(crate) options: EvalOptions,
+    /// Results of the lines above the one being evaluated, read by `sum(above)`
+    above: Vec<Value>,
+    /// Whether an aggregate such as `sum(above)` read those results since the last take
+    read_above: Cell<bool>,
+    /// Totals of the lines above by kind, read by `pct_of_total`
+    running_totals: Option<Totals>,
+    /// Non-fatal diagnostics raised while evaluating the current line
+    warnings: RefCell<Vec<String>>,
+    /// Logical time of each user variable's last read or write, for `max_variables`
+    variable_use: RefCell<HashMap<String, u64>>,
+    clock: Cell<u64>,
+    /// Value the last top-level conversion started from (`5 km` in `5 km in mi`)
+    conversion_source: Option<Value>,
+    /// Result and absolute change of the latest `value ± percentage` in the current line
+    percentage_step: RefCell<Option<(Value, Value)>>,
+    /// Absolute change behind the last line's result (`20` in `100 + 20%`)
+    percentage_change: Option<Value>,
+    pub(crate) functions: HashMap<String, UserFunction>,
+    /// Arguments of the user function calls in progress, innermost last
+    call_stack: RefCell<Vec<HashMap<String, Value>>>,
+    /// User function calls made so far on the current line
+    calls: Cell<usize>,
+    /// Date that relative date expressions count from; the system clock when unset
+    today: Option<Date>,
 }
 
 impl EvalContext {
@@ -24,9 +185,82 @@ impl EvalContext {
         Self {
             variables: HashMap::new(),
             rate_cache: RateCache::default(),
+            options: EvalOptions::default(),
+            above: Vec::new(),
+            read_above: Cell::new(false),
+            running_totals: None,
+            warnings: RefCell::new(Vec::new()),
+            variable_use: RefCell::new(HashMap::new()),
+            clock: Cell::new(0),
+            conversion_source: None,
+            percentage_step: RefCell::new(None),
+            percentage_change: None,
+            functions: HashMap::new(),
+            call_stack: RefCell::new(Vec::new()),
+            calls: Cell::new(0),
+            today: None,
         }
     }
 
+    /// Evaluation policies in effect
+    #[must_use]
+    pub fn options(&self) -> &EvalOptions {
+        &self.options
+    }
+
+    /// Replace the evaluation policies
+    pub fn set_options(&mut self, options: EvalOptions) {
+        self.options = options;
+        self.evict_variables(None);
+    }
+
+    /// Fix the date that "days until" counts from, or go back to the system clock with `None`
+    pub fn set_today(&mut self, today: Option<Date>) {
+        self.today = today;
+    }
+
+    /// The date relative date expressions count from
+    #[must_use]
+    pub fn today(&self) -> Option<Date> {
+        self.today.or_else(Date::today)
+    }
+
+    /// Set the results that `above` refers to inside aggregates like `sum(above)`
+    pub fn set_lines_above(&mut self, values: Vec<Value>) {
+        self.above = values;
+    }
+
+    /// Set the totals `pct_of_total` divides by, split by currency and unit
+    pub fn set_running_totals(&mut self, totals: Option<Totals>) {
+        self.running_totals = totals;
+    }
+
+    /// Drain warnings raised since the last call
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    /// Take the value the last evaluated line converted from, if it was a conversion
+    pub fn take_conversion_source(&mut self) -> Option<Value> {
+        self.conversion_source.take()
+    }
+
+    /// Take whether the last evaluated line aggregated the lines above it
+    /// (`sum(above)`). A variable named `above` does not count.
+    pub fn take_read_above(&mut self) -> bool {
+        self.read_above.take()
+    }
+
+    /// Take the amount the last evaluated line added or removed with a
+    /// percentage (`20` in `100 + 20%`, `-10` in `$50 - 20%`), if it did
+    pub fn take_percentage_change(&mut self) -> Option<Value> {
+        self.percentage_change.take()
+    }
+
+    fn warn(&self, message: String) {
+        self.warnings.borrow_mut().push(message);
+    }
+
     /// Set exchange rates (for testing or offline mode)
     pub fn set_exchange_rate(&mut self, from: Currency, to: Currency, rate: Decimal) {
         self.rate_cache.set_rate(from, to, rate);
@@ -44,17 +278,86 @@ impl EvalContext {
     /// Get a variable value
     #[must_use]
     pub fn get_variable(&self, name: &str) -> Option<&Value> {
-        self.variables.get(name)
+        let value = self.variables.get(name)?;
+        self.touch_variable(name);
+        Some(value)
     }
 
-    /// Set a variable
+    /// Set a variable, evicting the least recently used user variable when
+    /// `max_variables` is exceeded
     pub fn set_variable(&mut self, name: String, value: Value) {
-        self.variables.insert(name, value);
+        self.touch_variable(&name);
+        self.variables.insert(name.clone(), value);
+        self.evict_variables(Some(&name));
+    }
+
+    /// Remove one variable, returning its value if it was set
+    pub fn remove_variable(&mut self, name: &str) -> Option<Value> {
+        self.variable_use.get_mut().remove(name);
+        self.variables.remove(name)
+    }
+
+    /// Define or replace a user function
+    pub fn define_function(&mut self, name: String, function: UserFunction) {
+        self.functions.insert(name, function);
+    }
+
+    /// Clear all user functions
+    pub fn clear_functions(&mut self) {
+        self.functions.clear();
+    }
+
+    /// Value of `name` as seen by the expression being evaluated: the
+    /// arguments of the innermost user function call shadow variables
+    fn lookup(&self, name: &str) -> Option<Value> {
+        let local = self
+            .call_stack
+            .borrow()
+            .last()
+            .and_then(|arguments| arguments.get(name).cloned());
+        local.or_else(|| self.get_variable(name).cloned())
     }
 
     /// Clear all variables
     pub fn clear_variables(&mut self) {
         self.variables.clear();
+        self.variable_use.get_mut().clear();
+    }
+
+    fn touch_variable(&self, name: &str) {
+        if is_engine_variable(name) {
+            return;
+        }
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
+        self.variable_use.borrow_mut().insert(name.to_string(), now);
+    }
+
+    /// Drop least recently used user variables beyond the cap, sparing `keep`
+    fn evict_variables(&mut self, keep: Option<&str>) {
+        let Some(limit) = self.options.max_variables else {
+            return;
+        };
+        let variable_use = self.variable_use.get_mut();
+        let mut user_variables: Vec<(u64, String)> = self
+            .variables
+            .keys()
+            .filter(|name| !is_engine_variable(name))
+            .map(|name| (variable_use.get(name).copied().unwrap_or(0), name.clone()))
+            .collect();
+        if user_variables.len() <= limit {
+            return;
+        }
+        user_variables.sort();
+        let excess = user_variables.len() - limit;
+        for (_, name) in user_variables
+            .into_iter()
+            .filter(|(_, name)| Some(name.as_str()) != keep)
+            .take(excess)
+        {
+            self.variables.remove(&name);
+            variable_use.remove(&name);
+        }
     }
 }
 
@@ -66,16 +369,102 @@ impl Default for EvalContext {
 
 /// Evaluate an AST node
 pub fn evaluate(ast: &Ast, ctx: &mut EvalContext) -> Value {
+    ctx.calls.set(0);
+    evaluate_statement(ast, ctx)
+}
+
+fn evaluate_statement(ast: &Ast, ctx: &mut EvalContext) -> Value {
+    if let Some(max) = ctx.options.max_depth {
+        let actual = ast.depth();
+        if actual > max {
+            return Value::Error(EvalError::Parse(ParseError::TooDeep { actual, max }));
+        }
+    }
+    if ctx.options.sandbox {
+        let refused = match ast {
+            Ast::Assignment { .. } => Some("assignment"),
+            Ast::FunctionDefinition { .. } => Some("function definition"),
+            Ast::Repeat { .. } => Some("repeat"),
+            Ast::Empty | Ast::Expression(_) => None,
+        };
+        if let Some(statement) = refused {
+            return Value::Error(EvalError::NotAllowed(statement));
+        }
+    }
     match ast {
         Ast::Empty => Value::Empty,
-        Ast::Assignment { name, expr } => {
-            let value = eval_expr(expr, ctx);
+        Ast::Assignment { names, expr } => {
+            let value = eval_line(expr, ctx);
             if !value.is_error() {
-                ctx.set_variable(name.clone(), value.clone());
+                for name in names {
+                    ctx.set_variable(name.clone(), value.clone());
+                }
             }
             value
         }
-        Ast::Expression(expr) => eval_expr(expr, ctx),
+        Ast::FunctionDefinition { name, params, body } => {
+            if is_builtin_function(name) {
+                return Value::Error(EvalError::InvalidArgument(format!(
+                    "cannot redefine built-in function {name}"
+                )));
+            }
+            if let Some(duplicate) = params
+                .iter()
+                .enumerate()
+                .find_map(|(index, param)| params[..index].contains(param).then_some(param))
+            {
+                return Value::Error(EvalError::InvalidArgument(format!(
+                    "parameter {duplicate} appears twice in {name}"
+                )));
+            }
+            ctx.define_function(
+                name.clone(),
+                UserFunction {
+                    params: params.clone(),
+                    body: (**body).clone(),
+                },
+            );
+            Value::Empty
+        }
+        Ast::Expression(expr) => eval_line(expr, ctx),
+        Ast::Repeat { count, body } => {
+            let mut value = Value::Empty;
+            for _ in 0..*count {
+                value = evaluate_statement(body, ctx);
+                if value.is_error() {
+                    break;
+                }
+            }
+            value
+        }
+    }
+}
+
+/// Evaluate a line's expression, remembering the source of a trailing conversion
+/// and the change made by a percentage step that produced the result
+fn eval_line(expr: &Expr, ctx: &mut EvalContext) -> Value {
+    ctx.percentage_step.take();
+    let (value, source) = eval_conversion_chain(expr, ctx);
+    ctx.conversion_source = source.filter(|_| !value.is_error());
+    // Only a step whose result is the line's result describes the line
+    ctx.percentage_change = ctx
+        .percentage_step
+        .take()
+        .filter(|(result, _)| *result == value)
+        .map(|(_, change)| change);
+    value
+}
+
+/// Evaluate `expr`, returning the value before its first conversion when it ends
+/// in one or more (`5 km in m in mi` starts from 5 km)
+fn eval_conversion_chain(expr: &Expr, ctx: &EvalContext) -> (Value, Option<Value>) {
+    match expr {
+        Expr::Conversion { value, target_unit } => {
+            let (val, source) = eval_conversion_chain(value, ctx);
+            let source = source.unwrap_or_else(|| val.clone());
+            (eval_conversion(val, target_unit, ctx), Some(source))
+        }
+        _ => (eval_expr(expr, ctx), None),
     }
 }
 
@@ -87,14 +476,20 @@ fn eval_expr(expr: &Expr, ctx: &EvalContext) -> Value {
         Expr::WithCompoundUnit { amount, unit } => Value::with_compound_unit(*amount, unit.clone()),
 
         Expr::Variable(name) => ctx
-            .get_variable(name)
-            .cloned()
+            .lookup(name)
             .or_else(|| math_constant(name))
             .unwrap_or_else(|| Value::Error(EvalError::UnknownVariable(name.clone()))),
 
         Expr::BinaryOp { op, left, right } => {
             let lval = eval_expr(left, ctx);
-            let rval = eval_expr(right, ctx);
+            // The left side's failure is the result, so the right side is not evaluated
+            if lval.is_error() {
+                return lval;
+            }
+            let rval = match (op, right.as_ref()) {
+                (BinaryOp::Divide, Expr::Variable(name)) => eval_divisor_word(name, ctx),
+                _ => eval_expr(right, ctx),
+            };
             eval_binary_op(*op, lval, rval, ctx)
         }
 
@@ -114,22 +509,243 @@ fn eval_expr(expr: &Expr, ctx: &EvalContext) -> Value {
                         .map(|amount| Value::with_compound_unit(amount, unit))
                         .unwrap_or_else(error_value)
                 }
+                Value::CurrencyRate { amount, .. } => {
+                    checked_mul(amount, *percentage, "calculating a percentage")
+                        .map(|amount| val.with_scaled_amount(amount))
+                        .unwrap_or_else(error_value)
+                }
+                // A share of a rate: 5% of 3% = 0.15%
+                Value::Percentage(p) => checked_mul(p, *percentage, "calculating a percentage")
+                    .map(Value::Percentage)
+                    .unwrap_or_else(error_value),
                 _ => Value::error("Cannot calculate percentage of this value"),
             }
         }
 
+        Expr::PercentageOfWhole { part, whole } => {
+            eval_percentage_of_whole(eval_expr(part, ctx), eval_expr(whole, ctx), ctx)
+        }
+
         Expr::Conversion { value, target_unit } => {
             let val = eval_expr(value, ctx);
             eval_conversion(val, target_unit, ctx)
         }
 
         Expr::FunctionCall { name, args } => {
+            if let Some(function) = ctx.functions.get(name) {
+                // User functions can recurse, which makes their cost unbounded
+                if ctx.options.sandbox {
+                    return Value::Error(EvalError::NotAllowed("calling a user function"));
+                }
+                return call_user_function(name, function, args, ctx);
+            }
+            if let [Expr::Variable(reference)] = args.as_slice() {
+                if is_lines_above(reference, ctx) {
+                    ctx.read_above.set(true);
+                    return eval_aggregate_above(name, ctx);
+                }
+            }
             let evaluated_args: Vec<Value> = args.iter().map(|a| eval_expr(a, ctx)).collect();
-            eval_function(name, &evaluated_args)
+            if name.eq_ignore_ascii_case("pct_of_total") {
+                return percent_of_total(&evaluated_args, ctx);
+            }
+            if let Some(result) = typed_aggregate_call(name, &evaluated_args, ctx) {
+                return result;
+            }
+            eval_function(name, &evaluated_args, ctx)
+        }
+
+        Expr::TimeUntil { unit, date } => {
+            let Some(today) = ctx.today() else {
+                return Value::error("Today's date is not available");
+            };
+            let seconds = Decimal::from(today.days_until(*date)) * Decimal::from(86_400);
+            match unit.checked_from_si(seconds) {
+                Some(amount) => Value::WithCompoundUnit {
+                    amount,
+                    unit: unit.clone(),
+                },
+                None => Value::Error(EvalError::overflow("counting days")),
+            }
+        }
+
+        Expr::RelativeDate { days, months } => {
+            let Some(today) = ctx.today() else {
+                return Value::error("Today's date is not available");
+            };
+            today
+                .add_months(*months)
+                .and_then(|date| date.add_days(*days))
+                .map(Value::Date)
+                .unwrap_or_else(|| Value::Error(EvalError::overflow("counting days")))
+        }
+    }
+}
+
+/// `pct_of_total(x)`: `x` as a share of the running total of its own kind,
+/// which covers the lines above this one rather than the whole document.
+/// Money is measured against the money total and quantities against the
+/// total of their dimension, converting first: `pct_of_total(€5)` of a $ total.
+fn percent_of_total(args: &[Value], ctx: &EvalContext) -> Value {
+    let [value] = args else {
+        return Value::error("pct_of_total requires exactly one argument");
+    };
+    match value {
+        Value::Error(_) => return value.clone(),
+        Value::Number(_)
+        | Value::BaseNumber { .. }
+        | Value::Currency { .. }
+        | Value::WithCompoundUnit { .. } => {}
+        _ => return Value::error("pct_of_total requires a number, money, or quantity"),
+    }
+    let Some(totals) = &ctx.running_totals else {
+        return Value::error("pct_of_total requires a running total");
+    };
+    let candidates = totals
+        .currencies
+        .iter()
+        .map(|(currency, amount)| Value::currency(*amount, *currency))
+        .chain(
+            totals
+                .units_by_type
+                .iter()
+                .map(|(_, unit, amount)| Value::with_compound_unit(*amount, unit.clone())),
+        )
+        .chain(std::iter::once(Value::Number(totals.plain)));
+    let mut first_error = None;
+    for total in candidates {
+        let amount = match amount_in_kind_of(value, &total, ctx) {
+            Ok(amount) => amount,
+            Err(error) => {
+                first_error.get_or_insert(error);
+                continue;
+            }
+        };
+        let total = total.as_decimal().unwrap_or_default();
+        if total.is_zero() {
+            return Value::Error(EvalError::DivisionByZero);
+        }
+        return amount
+            .checked_div(total)
+            .map(Value::Percentage)
+            .unwrap_or_else(|| Value::Error(EvalError::overflow("dividing by the total")));
+    }
+    error_value(first_error.unwrap_or(EvalError::DivisionByZero))
+}
+
+/// Evaluate a user function body with its parameters bound to the arguments
+fn call_user_function(
+    name: &str,
+    function: &UserFunction,
+    args: &[Expr],
+    ctx: &EvalContext,
+) -> Value {
+    let expected = function.params.len();
+    if args.len() != expected {
+        let plural = if expected == 1 { "" } else { "s" };
+        return Value::Error(EvalError::InvalidArgument(format!(
+            "{name} takes {expected} argument{plural}, got {}",
+            args.len()
+        )));
+    }
+    if ctx.call_stack.borrow().len() >= MAX_CALL_DEPTH {
+        return Value::Error(EvalError::CallDepth {
+            max: MAX_CALL_DEPTH,
+        });
+    }
+    let calls = ctx.calls.get() + 1;
+    if calls > MAX_CALLS_PER_LINE {
+        return Value::Error(EvalError::CallBudget {
+            max: MAX_CALLS_PER_LINE,
+        });
+    }
+    ctx.calls.set(calls);
+
+    let mut arguments = HashMap::with_capacity(expected);
+    for (param, arg) in function.params.iter().zip(args) {
+        let value = eval_expr(arg, ctx);
+        if value.is_error() {
+            return value;
+        }
+        arguments.insert(param.clone(), value);
+    }
+
+    ctx.call_stack.borrow_mut().push(arguments);
+    let result = eval_expr(&function.body, ctx);
+    ctx.call_stack.borrow_mut().pop();
+    result
+}
+
+/// `above` refers to earlier lines unless a variable of that name shadows it.
+fn is_lines_above(name: &str, ctx: &EvalContext) -> bool {
+    LINE_REFERENCES.contains(&name) && ctx.lookup(name).is_none()
+}
+
+/// Aggregate the lines above: `sum(above)`, `avg(above)`, `max(above)`.
+/// Sums and averages keep currencies and units; other functions see the values
+/// as ordinary arguments.
+fn eval_aggregate_above(name: &str, ctx: &EvalContext) -> Value {
+    let values = &ctx.above;
+    match name.to_lowercase().as_str() {
+        "sum" | "total" | "avg" | "average" => typed_sum_or_average(name, values, ctx),
+        _ => {
+            // Failed lines above are skipped rather than failing the aggregate
+            let values: Vec<Value> = values.iter().filter(|v| !v.is_error()).cloned().collect();
+            eval_function(name, &values, ctx)
         }
     }
 }
 
+/// `sum` or `avg` keeping currency and unit, converting to the last one used
+/// as totals do. Values that cannot share one type are an error.
+fn typed_sum_or_average(name: &str, values: &[Value], ctx: &EvalContext) -> Value {
+    let total = totals::typed_total(values, &ctx.rate_cache);
+    if matches!(name.to_lowercase().as_str(), "sum" | "total") {
+        return total.unwrap_or_else(error_value);
+    }
+    let count = totals::total_count(values);
+    if count == 0 {
+        return Value::error(format!("{name} requires at least one value"));
+    }
+    total
+        .and_then(|total| {
+            let amount = total
+                .as_decimal()
+                .unwrap_or_default()
+                .checked_div(Decimal::from(count))
+                .ok_or(EvalError::Overflow {
+                    operation: "averaging values",
+                })?;
+            Ok(total.with_scaled_amount(amount))
+        })
+        .unwrap_or_else(error_value)
+}
+
+/// `sum($100, €50)` and `avg(5 km, 800 m)` over explicit arguments. Calls
+/// without a currency or unit argument are left to `eval_function`.
+fn typed_aggregate_call(name: &str, args: &[Value], ctx: &EvalContext) -> Option<Value> {
+    if !matches!(
+        name.to_lowercase().as_str(),
+        "sum" | "total" | "avg" | "average"
+    ) || args.iter().any(Value::is_error)
+        || !args.iter().any(|arg| arg.is_currency() || arg.is_unit())
+    {
+        return None;
+    }
+    // Totals skip percentages, prices per unit and dates, but an argument is never dropped
+    if let Some(arg) = args.iter().find(|arg| {
+        matches!(
+            arg,
+            Value::Percentage(_) | Value::CurrencyRate { .. } | Value::Date(_)
+        )
+    }) {
+        return Some(Value::Error(EvalError::InvalidArgument(format!(
+            "{name} cannot add {arg} to amounts"
+        ))));
+    }
+    Some(typed_sum_or_average(name, args, ctx))
+}
+
 fn error_value(error: EvalError) -> Value {
     Value::Error(error)
 }
@@ -144,8 +760,47 @@ fn checked_mul(
 }
 
 fn eval_binary_op(op: BinaryOp, left: Value, right: Value, ctx: &EvalContext) -> Value {
+    // Report the operand's own failure ("unknown variable: x") rather than a
+    // generic complaint about the operation
+    if left.is_error() {
+        return left;
+    }
+    if right.is_error() {
+        return right;
+    }
+
+    if op.is_bitwise() {
+        return eval_bitwise(op, &left, &right);
+    }
+
+    if let (BinaryOp::Power, Some(limit), Some(exponent)) =
+        (op, ctx.options.max_exponent, right.as_decimal())
+    {
+        if exponent.abs() > Decimal::from(limit) {
+            return Value::Error(EvalError::InvalidOperands(format!(
+                "exponent {exponent} is larger than the limit of {limit}"
+            )));
+        }
+    }
+
     // Handle percentage operations (e.g., 100 + 20% = 120)
-    if let Some(result) = try_percentage_op(op, &left, &right) {
+    if let Some(result) = try_percentage_op(op, &left, &right, ctx.options.percent_multiply) {
+        if matches!(op, BinaryOp::Add | BinaryOp::Subtract) {
+            // The change is only extra information: one too large to hold is left out
+            let change = result
+                .as_decimal()
+                .zip(left.as_decimal())
+                .and_then(|(after, before)| after.checked_sub(before));
+            if let Some(change) = change {
+                let change = result.with_scaled_amount(change);
+                *ctx.percentage_step.borrow_mut() = Some((result.clone(), change));
+            }
+        }
+        return result;
+    }
+
+    // Handle prices per unit (e.g., $20 per kg * 3 kg = $60)
+    if let Some(result) = try_currency_rate_op(op, &left, &right, ctx) {
         return result;
     }
 
@@ -158,7 +813,7 @@ fn eval_binary_op(op: BinaryOp, left: Value, right: Value, ctx: &EvalContext) ->
 
     // Handle compound unit operations (multiply, divide, add, subtract)
     // e.g., 5m * 10m = 50 m², 100km / 2h = 50 km/h, 12 m² + 15 m² = 27 m²
-    if let Some(result) = try_unit_compound_op(op, &left, &right) {
+    if let Some(result) = try_unit_compound_op(op, &left, &right, ctx) {
         return result;
     }
 
@@ -182,17 +837,90 @@ fn eval_binary_op(op: BinaryOp, left: Value, right: Value, ctx: &EvalContext) ->
     }
 }
 
+/// Bitwise operators on non-negative integers. The result keeps the number
+/// base of an operand shown in hex or binary ("x & 0x0F" where x is 0xff).
+fn eval_bitwise(op: BinaryOp, left: &Value, right: &Value) -> Value {
+    let integer = |value: &Value| {
+        plain_decimal(value)
+            .filter(|n| n.fract().is_zero() && *n >= Decimal::ZERO)
+            .and_then(|n| n.to_u128())
+    };
+    let (Some(l), Some(r)) = (integer(left), integer(right)) else {
+        return Value::Error(EvalError::InvalidOperands(
+            "Bitwise operators require non-negative integers".to_string(),
+        ));
+    };
+    let result = match op {
+        BinaryOp::BitAnd => Some(l & r),
+        BinaryOp::BitOr => Some(l | r),
+        BinaryOp::BitXor => Some(l ^ r),
+        BinaryOp::ShiftLeft => u32::try_from(r)
+            .ok()
+            .filter(|&shift| l == 0 || l.leading_zeros() >= shift)
+            .map(|shift| l.checked_shl(shift).unwrap_or(0)),
+        _ => Some(
+            u32::try_from(r)
+                .ok()
+                .and_then(|shift| l.checked_shr(shift))
+                .unwrap_or(0),
+        ),
+    };
+    let Some(amount) = result.and_then(Decimal::from_u128) else {
+        return Value::Error(EvalError::overflow("shifting bits"));
+    };
+    let base = [left, right].into_iter().find_map(|value| match value {
+        Value::BaseNumber { base, .. } => Some(*base),
+        _ => None,
+    });
+    match base {
+        Some(base) => Value::with_base(amount, base),
+        None => Value::Number(amount),
+    }
+}
+
 /// Try to handle percentage operations (e.g., 100 + 20% = 120)
-fn try_percentage_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Value> {
+fn try_percentage_op(
+    op: BinaryOp,
+    left: &Value,
+    right: &Value,
+    multiply: PercentMultiply,
+) -> Option<Value> {
+    let (left, right) = match (left, right) {
+        // let coerce_operands handle Percentage ± Percentage
+        (Value::Percentage(_), Value::Percentage(_)) => return None,
+        // A rate scaled by a number stays a rate: 5% * 2 = 10%, 5% / 2 = 2.5%
+        (Value::Percentage(p), Value::Number(n)) if multiply == PercentMultiply::Scale => {
+            let scaled = match op {
+                BinaryOp::Multiply => checked_mul(*p, *n, "scaling a percentage"),
+                BinaryOp::Divide if n.is_zero() => Err(EvalError::DivisionByZero),
+                BinaryOp::Divide => p.checked_div(*n).ok_or(EvalError::Overflow {
+                    operation: "scaling a percentage",
+                }),
+                _ => return None,
+            };
+            return Some(scaled.map(Value::Percentage).unwrap_or_else(error_value));
+        }
+        // Multiplication commutes, so "20% * $100" scales the value on the right
+        (Value::Percentage(_), _)
+            if op == BinaryOp::Multiply && multiply == PercentMultiply::Scale =>
+        {
+            (right, left)
+        }
+        _ => (left, right),
+    };
     let Value::Percentage(p) = right else {
         return None;
     };
-    if matches!(left, Value::Percentage(_)) {
-        return None; // let coerce_operands handle Percentage ± Percentage
-    }
     let base = left.as_decimal()?;
 
     let amount = match op {
+        BinaryOp::Multiply if multiply == PercentMultiply::Ratio => {
+            return Some(
+                checked_mul(base, *p, "applying a percentage")
+                    .map(Value::Number)
+                    .unwrap_or_else(error_value),
+            );
+        }
         BinaryOp::Add => base
             .checked_mul(*p)
             .and_then(|delta| base.checked_add(delta)),
@@ -202,8 +930,17 @@ fn try_percentage_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Value>
         BinaryOp::Multiply => base.checked_mul(*p),
         BinaryOp::Divide if p.is_zero() => return Some(error_value(EvalError::DivisionByZero)),
         BinaryOp::Divide => base.checked_div(*p),
-        BinaryOp::Power => base.checked_powd(*p),
-        BinaryOp::Conversion => return None,
+        BinaryOp::Power => match checked_power(base, *p) {
+            Ok(amount) => Some(amount),
+            Err(error) => return Some(error_value(error)),
+        },
+        BinaryOp::Conversion
+        | BinaryOp::AsPercentOf
+        | BinaryOp::ShiftLeft
+        | BinaryOp::ShiftRight
+        | BinaryOp::BitAnd
+        | BinaryOp::BitXor
+        | BinaryOp::BitOr => return None,
     };
     Some(
         amount
@@ -252,9 +989,248 @@ fn try_multiply_mixed(left: &Value, right: &Value) -> Option<Value> {
     }
 }
 
+/// Try to handle prices per unit: building them ($20 / 1 kg), applying them to a
+/// quantity ($20/kg * 3 kg = $60), scaling, and combining them.
+fn try_currency_rate_op(
+    op: BinaryOp,
+    left: &Value,
+    right: &Value,
+    ctx: &EvalContext,
+) -> Option<Value> {
+    let result = match (op, left, right) {
+        // currency / quantity → price per unit
+        (
+            BinaryOp::Divide,
+            Value::Currency { amount, currency },
+            Value::WithCompoundUnit {
+                amount: quantity,
+                unit,
+            },
+        ) => apply_op(BinaryOp::Divide, *amount, *quantity)
+            .map(|amount| Value::currency_rate(amount, *currency, unit.clone())),
+
+        // price per unit × quantity → currency
+        (
+            BinaryOp::Multiply,
+            Value::CurrencyRate {
+                amount,
+                currency,
+                per,
+            },
+            Value::WithCompoundUnit {
+                amount: quantity,
+                unit,
+            },
+        )
+        | (
+            BinaryOp::Multiply,
+            Value::WithCompoundUnit {
+                amount: quantity,
+                unit,
+            },
+            Value::CurrencyRate {
+                amount,
+                currency,
+                per,
+            },
+        ) => quantity_in_rate_unit(*quantity, unit, per).and_then(|quantity| {
+            checked_mul(*amount, quantity, "applying a price per unit")
+                .map(|amount| Value::currency(amount, *currency))
+        }),
+
+        // currency / price per unit → quantity
+        (
+            BinaryOp::Divide,
+            Value::Currency { .. },
+            Value::CurrencyRate {
+                amount,
+                currency,
+                per,
+            },
+        ) => currency_amount_in(left, *currency, ctx).and_then(|total| {
+            apply_op(BinaryOp::Divide, total, *amount)
+                .map(|quantity| Value::with_compound_unit(quantity, per.clone()))
+        }),
+
+        // price per unit × or ÷ number → price per unit
+        (
+            BinaryOp::Multiply | BinaryOp::Divide,
+            Value::CurrencyRate { amount, .. },
+            Value::Number(n) | Value::BaseNumber { amount: n, .. },
+        ) => apply_op(op, *amount, *n).map(|amount| left.with_scaled_amount(amount)),
+        (
+            BinaryOp::Multiply,
+            Value::Number(n) | Value::BaseNumber { amount: n, .. },
+            Value::CurrencyRate { amount, .. },
+        ) => apply_op(op, *n, *amount).map(|amount| right.with_scaled_amount(amount)),
+
+        // price per unit ± or ÷ price per unit, in the left side's currency and unit
+        (
+            BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Divide,
+            Value::CurrencyRate {
+                amount,
+                currency,
+                per,
+            },
+            Value::CurrencyRate {
+                amount: other,
+                currency: other_currency,
+                per: other_per,
+            },
+        ) => currency_amount_in(&Value::currency(*other, *other_currency), *currency, ctx)
+            .and_then(|other| rate_in_unit(other, other_per, per))
+            .and_then(|other| apply_op(op, *amount, other))
+            .map(|result| match op {
+                BinaryOp::Divide => Value::Number(result),
+                _ => left.with_scaled_amount(result),
+            }),
+
+        (_, Value::CurrencyRate { .. }, _) | (_, _, Value::CurrencyRate { .. }) => Err(
+            EvalError::InvalidOperands("invalid operands for a price per unit".to_string()),
+        ),
+        _ => return None,
+    };
+    Some(result.unwrap_or_else(error_value))
+}
+
+/// Express a quantity in the unit a price is quoted per
+fn quantity_in_rate_unit(
+    quantity: Decimal,
+    unit: &unit::CompoundUnit,
+    per: &unit::CompoundUnit,
+) -> Result<Decimal, EvalError> {
+    unit.try_convert_to(quantity, per)?.ok_or_else(|| {
+        EvalError::InvalidOperands(format!(
+            "Cannot apply a price per {} to {}",
+            per.dimensions, unit.dimensions
+        ))
+    })
+}
+
+/// Re-quote a price per `from` as a price per `to` ($1/g = $1000/kg)
+fn rate_in_unit(
+    amount: Decimal,
+    from: &unit::CompoundUnit,
+    to: &unit::CompoundUnit,
+) -> Result<Decimal, EvalError> {
+    if from.dimensions != to.dimensions {
+        return Err(EvalError::IncompatibleUnits {
+            operation: "convert",
+            left: format!("a price per {}", from.dimensions),
+            right: format!("a price per {}", to.dimensions),
+        });
+    }
+    amount
+        .checked_mul(to.factor)
+        .and_then(|amount| amount.checked_div(from.factor))
+        .ok_or(EvalError::Overflow {
+            operation: "converting a price per unit",
+        })
+}
+
+/// Amount of a currency value after converting it to `target`
+fn currency_amount_in(
+    value: &Value,
+    target: Currency,
+    ctx: &EvalContext,
+) -> Result<Decimal, EvalError> {
+    let Value::Currency { amount, currency } = value else {
+        return Err(EvalError::InvalidOperands(
+            "expected a currency".to_string(),
+        ));
+    };
+    if *currency == target {
+        return Ok(*amount);
+    }
+    let rate = ctx
+        .rate_cache
+        .try_get_rate(*currency, target)?
+        .ok_or(EvalError::NoRate {
+            from: *currency,
+            to: target,
+        })?;
+    checked_mul(*amount, rate, "converting currency")
+}
+
+/// Temperature readings sit on offset scales, so only a difference ("5 C°") can
+/// be added to a reading, and subtracting two readings yields a difference.
+fn add_temperatures(
+    op: BinaryOp,
+    l_amount: Decimal,
+    l_unit: &unit::CompoundUnit,
+    r_amount: Decimal,
+    r_unit: &unit::CompoundUnit,
+    ctx: &EvalContext,
+) -> Value {
+    // Put the reading first: "5 C° + 20 °C" is the same as "20 °C + 5 C°"
+    let (reading, reading_unit, other, other_unit) = if l_unit.is_delta {
+        if op == BinaryOp::Subtract {
+            return Value::error("Cannot subtract a temperature reading from a difference");
+        }
+        (r_amount, r_unit, l_amount, l_unit)
+    } else {
+        (l_amount, l_unit, r_amount, r_unit)
+    };
+
+    let target = if other_unit.is_delta {
+        reading_unit.to_delta()
+    } else {
+        reading_unit.clone()
+    };
+    let other = match other_unit.try_convert_to(other, &target) {
+        Ok(Some(converted)) => converted,
+        Ok(None) => {
+            return error_value(EvalError::IncompatibleUnits {
+                operation: "convert",
+                left: other_unit.symbol.clone(),
+                right: target.symbol,
+            })
+        }
+        Err(error) => return error_value(error),
+    };
+
+    let result_unit = match (other_unit.is_delta, op) {
+        (true, _) => reading_unit.clone(),
+        (false, BinaryOp::Subtract) => reading_unit.to_delta(),
+        (false, _) => {
+            ctx.warn(format!(
+                "Added two temperature readings; write a change as a difference such as 5 {}",
+                reading_unit.to_delta().symbol
+            ));
+            reading_unit.clone()
+        }
+    };
+    let result_amount = match op {
+        BinaryOp::Add => reading.checked_add(other),
+        _ => reading.checked_sub(other),
+    };
+    result_amount
+        .map(|amount| Value::with_compound_unit(amount, result_unit))
+        .unwrap_or_else(|| {
+            error_value(EvalError::Overflow {
+                operation: "adding temperatures",
+            })
+        })
+}
+
+/// Flag a unit product or quotient no registered unit measures, under `warn_unusual_units`
+fn warn_if_unusual(unit: &unit::CompoundUnit, ctx: &EvalContext) {
+    if ctx.options.warn_unusual_units && !unit.has_named_dimensions() {
+        ctx.warn(format!(
+            "Unusual unit: no known quantity is measured in {} ({})",
+            unit.symbol, unit.dimensions
+        ));
+    }
+}
+
 /// Try to handle unit operations to create/manipulate compound units
 /// e.g., 5m * 10m = 50 m², 100km / 2h = 50 km/h, 12 m² + 15 m² = 27 m²
-fn try_unit_compound_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Value> {
+fn try_unit_compound_op(
+    op: BinaryOp,
+    left: &Value,
+    right: &Value,
+    ctx: &EvalContext,
+) -> Option<Value> {
     // Extract the canonical unit representation.
     let (l_amount, l_unit) = match left {
         Value::WithCompoundUnit { amount, unit } => (*amount, unit.clone()),
@@ -307,16 +1283,36 @@ fn try_unit_compound_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Val
         BinaryOp::Add | BinaryOp::Subtract => {
             // Can only add/subtract compound units with same dimensions
             if l_unit.dimensions != r_unit.dimensions {
-                return Some(Value::error(format!(
-                    "Cannot {} {} and {} (incompatible dimensions)",
-                    if op == BinaryOp::Add {
-                        "add"
-                    } else {
-                        "subtract"
-                    },
-                    l_unit.symbol,
-                    r_unit.symbol
-                )));
+                let verb = if op == BinaryOp::Add {
+                    "add"
+                } else {
+                    "subtract"
+                };
+                if !ctx.options.lenient_units {
+                    return Some(error_value(EvalError::IncompatibleUnits {
+                        operation: verb,
+                        left: l_unit.dimensions.to_string(),
+                        right: r_unit.dimensions.to_string(),
+                    }));
+                }
+                ctx.warn(format!(
+                    "Mixed units: used raw amounts to {verb} {} ({}) and {} ({})",
+                    l_unit.dimensions, l_unit.symbol, r_unit.dimensions, r_unit.symbol
+                ));
+                let result_amount = match op {
+                    BinaryOp::Add => l_amount.checked_add(r_amount),
+                    _ => l_amount.checked_sub(r_amount),
+                };
+                return Some(result_amount.map(Value::Number).unwrap_or_else(|| {
+                    error_value(EvalError::Overflow {
+                        operation: "adding unit values",
+                    })
+                }));
+            }
+            if l_unit.is_absolute_temperature() || r_unit.is_absolute_temperature() {
+                return Some(add_temperatures(
+                    op, l_amount, &l_unit, r_amount, &r_unit, ctx,
+                ));
             }
             // Convert right to left's unit scale
             let r_converted = if l_unit.symbol == r_unit.symbol {
@@ -325,7 +1321,13 @@ fn try_unit_compound_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Val
                 // Convert through SI base
                 match r_unit.try_convert_to(r_amount, &l_unit) {
                     Ok(Some(converted)) => converted,
-                    Ok(None) => return Some(Value::error("Incompatible unit scales")),
+                    Ok(None) => {
+                        return Some(error_value(EvalError::IncompatibleUnits {
+                            operation: "convert",
+                            left: r_unit.symbol,
+                            right: l_unit.symbol,
+                        }))
+                    }
                     Err(error) => return Some(error_value(error)),
                 }
             };
@@ -347,6 +1349,9 @@ fn try_unit_compound_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Val
         BinaryOp::Multiply => {
             let result_amount = checked_mul(l_amount, r_amount, "multiplying unit values");
             let result_unit = l_unit.try_multiply(&r_unit);
+            if let Ok(unit) = &result_unit {
+                warn_if_unusual(unit, ctx);
+            }
             Some(match (result_amount, result_unit) {
                 (Ok(amount), Ok(unit)) => Value::with_compound_unit(amount, unit),
                 (Err(error), _) | (_, Err(error)) => error_value(error),
@@ -360,6 +1365,7 @@ fn try_unit_compound_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Val
                 Ok(unit) => unit,
                 Err(error) => return Some(error_value(error)),
             };
+            warn_if_unusual(&result_unit, ctx);
             // If the result is dimensionless, return a plain number
             if result_unit.dimensions.is_dimensionless() {
                 let result = l_unit.checked_to_si(l_amount).and_then(|left| {
@@ -386,7 +1392,13 @@ fn try_unit_compound_op(op: BinaryOp, left: &Value, right: &Value) -> Option<Val
             }
         }
         BinaryOp::Power => Some(Value::error("Power not supported for unit values")),
-        BinaryOp::Conversion => None,
+        BinaryOp::Conversion
+        | BinaryOp::AsPercentOf
+        | BinaryOp::ShiftLeft
+        | BinaryOp::ShiftRight
+        | BinaryOp::BitAnd
+        | BinaryOp::BitXor
+        | BinaryOp::BitOr => None,
     }
 }
 
@@ -404,6 +1416,9 @@ fn coerce_operands(
     op: BinaryOp,
     ctx: &EvalContext,
 ) -> Result<(Decimal, Decimal, ResultType), EvalError> {
+    if ctx.options.strict_units && matches!(op, BinaryOp::Add | BinaryOp::Subtract) {
+        check_tagged_operands(op, left, right)?;
+    }
     match (left, right) {
         // Same currency
         (
@@ -428,9 +1443,7 @@ fn coerce_operands(
                     operation: "converting currency",
                 })?
             } else {
-                return Err(EvalError::InvalidOperands(format!(
-                    "no exchange rate for {rc} to {lc}"
-                )));
+                return Err(EvalError::NoRate { from: *rc, to: *lc });
             };
             let result_type = if op == BinaryOp::Divide {
                 ResultType::Number
@@ -508,6 +1521,31 @@ fn coerce_operands(
     }
 }
 
+/// Under `strict_units`, a plain number may not be added to or subtracted
+/// from a currency or unit amount
+fn check_tagged_operands(op: BinaryOp, left: &Value, right: &Value) -> Result<(), EvalError> {
+    let describe = |value: &Value| match value {
+        Value::Number(_) | Value::BaseNumber { .. } => Some(None),
+        Value::Currency { currency, .. } => Some(Some(currency.code().to_string())),
+        Value::WithCompoundUnit { unit, .. } => Some(Some(unit.symbol.clone())),
+        _ => None,
+    };
+    let (left, right) = match (describe(left), describe(right)) {
+        (Some(None), Some(Some(tag))) => ("a plain number".to_string(), tag),
+        (Some(Some(tag)), Some(None)) => (tag, "a plain number".to_string()),
+        _ => return Ok(()),
+    };
+    Err(EvalError::IncompatibleUnits {
+        operation: if op == BinaryOp::Add {
+            "add"
+        } else {
+            "subtract"
+        },
+        left,
+        right,
+    })
+}
+
 /// Apply arithmetic operation
 fn apply_op(op: BinaryOp, l: Decimal, r: Decimal) -> Result<Decimal, EvalError> {
     match op {
@@ -524,25 +1562,103 @@ fn apply_op(op: BinaryOp, l: Decimal, r: Decimal) -> Result<Decimal, EvalError>
         BinaryOp::Divide => l.checked_div(r).ok_or(EvalError::Overflow {
             operation: "dividing values",
         }),
-        BinaryOp::Power if l.is_sign_negative() && r.fract() != Decimal::ZERO => {
-            Err(EvalError::InvalidOperands(
-                "cannot raise negative number to a fractional power".to_string(),
-            ))
-        }
-        BinaryOp::Power => l.checked_powd(r).ok_or(EvalError::Overflow {
-            operation: "raising a value to a power",
-        }),
-        BinaryOp::Conversion => Err(EvalError::InvalidOperands(
+        BinaryOp::Power => checked_power(l, r),
+        BinaryOp::Conversion | BinaryOp::AsPercentOf => Err(EvalError::InvalidOperands(
             "conversion is not an arithmetic operation".to_string(),
         )),
+        BinaryOp::ShiftLeft
+        | BinaryOp::ShiftRight
+        | BinaryOp::BitAnd
+        | BinaryOp::BitXor
+        | BinaryOp::BitOr => Err(EvalError::InvalidOperands(
+            "Bitwise operators require non-negative integers".to_string(),
+        )),
+    }
+}
+
+/// Raise `base` to `exponent`, rejecting results that are not real numbers.
+/// `0 ^ 0` is 1; `0` to a negative power divides by zero.
+fn checked_power(base: Decimal, exponent: Decimal) -> Result<Decimal, EvalError> {
+    if exponent.is_zero() {
+        return Ok(Decimal::ONE);
+    }
+    if base.is_zero() && exponent.is_sign_negative() {
+        return Err(EvalError::DivisionByZero);
+    }
+    if base.is_sign_negative() && !exponent.fract().is_zero() {
+        return Err(EvalError::InvalidOperands(
+            "cannot raise negative number to a fractional power".to_string(),
+        ));
+    }
+    base.checked_powd(exponent).ok_or(EvalError::Overflow {
+        operation: "raising a value to a power",
+    })
+}
+
+/// Express `part` as a percentage of `whole` ("$200 as % of $1000" = 20%).
+/// A plain number on either side takes on the other side's unit.
+fn eval_percentage_of_whole(part: Value, whole: Value, ctx: &EvalContext) -> Value {
+    if part.is_error() {
+        return part;
+    }
+    if whole.is_error() {
+        return whole;
+    }
+
+    let ratio = if part.is_number() || whole.is_number() {
+        match (part.as_decimal(), whole.as_decimal()) {
+            (Some(part), Some(whole)) => apply_op(BinaryOp::Divide, part, whole)
+                .map(Value::Number)
+                .unwrap_or_else(error_value),
+            _ => return Value::error("Cannot express this value as a percentage"),
+        }
+    } else {
+        eval_binary_op(BinaryOp::Divide, part, whole, ctx)
+    };
+
+    match ratio {
+        Value::Number(ratio) | Value::Percentage(ratio) => Value::Percentage(ratio),
+        Value::Error(_) => ratio,
+        _ => Value::error("Cannot express a percentage of values with different units"),
     }
 }
 
 fn eval_conversion(value: Value, target: &str, ctx: &EvalContext) -> Value {
+    if target == "%" {
+        return eval_percent_conversion(value);
+    }
+
+    // A variable holding an amount stands for its unit or currency ("in preferred")
+    let names_target = NumberBase::parse(target).is_some()
+        || Currency::parse(target).is_some()
+        || Currency::parse_subunit(target).is_some()
+        || unit::parse_unit_expression(target).is_some();
+    if !names_target {
+        if let Some(held) = ctx.lookup(target) {
+            return match held {
+                Value::Currency { currency, .. } => eval_conversion(value, currency.code(), ctx),
+                Value::WithCompoundUnit { unit, .. } => eval_conversion(value, &unit.symbol, ctx),
+                Value::Error(error) => Value::Error(error),
+                _ => Value::Error(EvalError::InvalidArgument(format!(
+                    "{target} does not hold a unit or currency to convert to"
+                ))),
+            };
+        }
+    }
+
     if let Some(base) = NumberBase::parse(target) {
         return eval_number_base_conversion(value, base);
     }
 
+    if let Value::CurrencyRate {
+        amount,
+        currency,
+        per,
+    } = value
+    {
+        return eval_currency_rate_conversion(amount, currency, per, target, ctx);
+    }
+
     // Try as currency first
     if let Some(target_currency) = Currency::parse(target) {
         if let Value::Currency { amount, currency } = value {
@@ -563,19 +1679,43 @@ fn eval_conversion(value: Value, target: &str, ctx: &EvalContext) -> Value {
                 Err(error) => return error_value(error),
                 Ok(None) => {}
             }
-            return Value::error(format!(
-                "No exchange rate for {currency} to {target_currency}"
-            ));
+            return error_value(EvalError::NoRate {
+                from: currency,
+                to: target_currency,
+            });
+        }
+    }
+
+    // Subunit targets yield a plain count (1 BTC in sats -> 100000000)
+    if let Some(subunit) = Currency::parse_subunit(target) {
+        if value.is_currency() {
+            return match eval_conversion(value, subunit.currency.code(), ctx) {
+                Value::Currency { amount, .. } => amount
+                    .checked_mul(subunit.per_unit())
+                    .map(Value::Number)
+                    .unwrap_or_else(|| {
+                        error_value(EvalError::Overflow {
+                            operation: "converting to subunit",
+                        })
+                    }),
+                other => other,
+            };
         }
     }
 
     // Try as unit (simple or compound)
-    if let Some(target_compound) = unit::parse_unit(target) {
+    if let Some(target_compound) = unit::parse_unit_expression(target) {
         match value {
             Value::WithCompoundUnit {
                 amount,
                 unit: from_unit,
             } => {
+                // A difference converts to the target scale's difference ("9 F° in C" = 5 C°)
+                let target_compound = if from_unit.is_delta {
+                    target_compound.to_delta()
+                } else {
+                    target_compound
+                };
                 match unit::try_convert(amount, &from_unit, &target_compound) {
                     Ok(Some(converted)) => {
                         return Value::with_compound_unit(converted, target_compound)
@@ -583,16 +1723,22 @@ fn eval_conversion(value: Value, target: &str, ctx: &EvalContext) -> Value {
                     Err(error) => return error_value(error),
                     Ok(None) => {}
                 }
-                return Value::error(format!(
-                    "Cannot convert {} to {}",
-                    from_unit.symbol, target_compound.symbol
-                ));
+                return error_value(EvalError::IncompatibleUnits {
+                    operation: "convert",
+                    left: from_unit.symbol,
+                    right: target_compound.symbol,
+                });
             }
             // Plain number → attach unit (e.g., "18.39 in months" → "18.39 months")
             Value::Number(n) => return Value::with_compound_unit(n, target_compound),
-            // Currency ratio → attach unit (e.g., "usd/usd in months" → dimensionless with unit)
-            Value::Currency { amount, .. } => {
-                return Value::with_compound_unit(amount, target_compound)
+            // A ratio of amounts ("usd/usd in months") is already a plain number;
+            // an amount of money has no length of time or any other unit
+            Value::Currency { currency, .. } => {
+                return error_value(EvalError::IncompatibleUnits {
+                    operation: "convert",
+                    left: currency.code().to_string(),
+                    right: target_compound.symbol,
+                });
             }
             _ => {}
         }
@@ -601,6 +1747,27 @@ fn eval_conversion(value: Value, target: &str, ctx: &EvalContext) -> Value {
     Value::Error(EvalError::UnknownTarget(target.to_string()))
 }
 
+/// Convert a price per unit to another currency ("in EUR") or unit ("in lb")
+fn eval_currency_rate_conversion(
+    amount: Decimal,
+    currency: Currency,
+    per: unit::CompoundUnit,
+    target: &str,
+    ctx: &EvalContext,
+) -> Value {
+    if let Some(target_currency) = Currency::parse(target) {
+        return currency_amount_in(&Value::currency(amount, currency), target_currency, ctx)
+            .map(|amount| Value::currency_rate(amount, target_currency, per))
+            .unwrap_or_else(error_value);
+    }
+    if let Some(target_unit) = unit::parse_unit_expression(target) {
+        return rate_in_unit(amount, &per, &target_unit)
+            .map(|amount| Value::currency_rate(amount, currency, target_unit))
+            .unwrap_or_else(error_value);
+    }
+    Value::Error(EvalError::UnknownTarget(target.to_string()))
+}
+
 fn eval_number_base_conversion(value: Value, base: NumberBase) -> Value {
     let amount = match value {
         Value::Number(n) | Value::BaseNumber { amount: n, .. } => n,
@@ -614,6 +1781,30 @@ fn eval_number_base_conversion(value: Value, base: NumberBase) -> Value {
     Value::with_base(amount, base)
 }
 
+/// "0.25 in %" shows a dimensionless ratio as a percentage: 25%
+fn eval_percent_conversion(value: Value) -> Value {
+    match value {
+        Value::Number(n) | Value::BaseNumber { amount: n, .. } | Value::Percentage(n) => {
+            Value::Percentage(n)
+        }
+        Value::Error(error) => Value::Error(error),
+        _ => Value::Error(EvalError::InvalidOperands(
+            "only a plain number can be shown as a percentage".to_string(),
+        )),
+    }
+}
+
+/// A divisor word that names no variable or constant is one of that unit,
+/// so "$30/h" is a price per hour and "10 km / h" a speed.
+fn eval_divisor_word(name: &str, ctx: &EvalContext) -> Value {
+    ctx.lookup(name)
+        .or_else(|| math_constant(name))
+        .or_else(|| {
+            unit::parse_unit(name).map(|unit| Value::with_compound_unit(Decimal::ONE, unit))
+        })
+        .unwrap_or_else(|| Value::Error(EvalError::UnknownVariable(name.to_string())))
+}
+
 fn math_constant(name: &str) -> Option<Value> {
     let value = match name.to_lowercase().as_str() {
         "pi" => std::f64::consts::PI,
@@ -667,7 +1858,8 @@ fn angle_in_radians(value: &Value) -> Result<f64, EvalError> {
     }
 }
 
-fn eval_function(name: &str, args: &[Value]) -> Value {
+fn eval_function(name: &str, args: &[Value], ctx: &EvalContext) -> Value {
+    let options = &ctx.options;
     let require_f64 = |f: fn(f64) -> f64| -> Value {
         require_number(name, args, |n| match n.to_f64() {
             Some(v) => decimal_from_f64(f(v), name),
@@ -697,10 +1889,8 @@ fn eval_function(name: &str, args: &[Value]) -> Value {
         return err.clone();
     }
 
-    // Helper to get all numeric values.
-    // NOTE: as_decimal() strips Currency/Unit types. Aggregates like sum($100, $200)
-    // return a plain number, not a currency. Supporting typed aggregates would require
-    // checking all args share the same type and preserving it in the result.
+    // Helper to get all numeric values. as_decimal() strips Currency/Unit types;
+    // sums and averages with those are handled earlier by typed_aggregate_call.
     let numbers = || args.iter().filter_map(|v| v.as_decimal());
 
     let checked_sum = |values: &[Decimal]| -> Result<Decimal, EvalError> {
@@ -713,7 +1903,18 @@ fn eval_function(name: &str, args: &[Value]) -> Value {
 
     match name.to_lowercase().as_str() {
         // Aggregate functions
+        "count" => Value::Number(Decimal::from(
+            args.iter().filter(|value| !value.is_empty()).count(),
+        )),
+
+        "count_nonzero" => Value::Number(Decimal::from(
+            numbers().filter(|value| !value.is_zero()).count(),
+        )),
+
         "sum" | "total" => {
+            if let Some(error) = date_argument_error("add", args) {
+                return error;
+            }
             let vals: Vec<_> = numbers().collect();
             if vals.is_empty() {
                 Value::error(format!("{name} requires at least one value"))
@@ -725,6 +1926,9 @@ fn eval_function(name: &str, args: &[Value]) -> Value {
         }
 
         "avg" | "average" => {
+            if let Some(error) = date_argument_error("average", args) {
+                return error;
+            }
             let vals: Vec<_> = numbers().collect();
             if vals.is_empty() {
                 Value::error(format!("{name} requires at least one value"))
@@ -741,15 +1945,27 @@ fn eval_function(name: &str, args: &[Value]) -> Value {
             }
         }
 
-        "min" => numbers()
-            .min()
-            .map(Value::Number)
-            .unwrap_or_else(|| Value::error("No values for min")),
-
-        "max" => numbers()
-            .max()
-            .map(Value::Number)
-            .unwrap_or_else(|| Value::error("No values for max")),
+        "min" | "max" => {
+            let wanted = if name.eq_ignore_ascii_case("min") {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            };
+            if let Some(extreme) = comparable_extreme(args, wanted, ctx) {
+                return extreme.clone();
+            }
+            if let Some(error) = date_argument_error("compare", args) {
+                return error;
+            }
+            let extreme = if wanted == Ordering::Less {
+                numbers().min()
+            } else {
+                numbers().max()
+            };
+            extreme
+                .map(Value::Number)
+                .unwrap_or_else(|| Value::error(format!("No values for {name}")))
+        }
 
         "median" => {
             let mut vals: Vec<_> = args.iter().filter_map(plain_decimal).collect();
@@ -795,7 +2011,44 @@ fn eval_function(name: &str, args: &[Value]) -> Value {
 
         // Single-value math functions
         "abs" => require_number(name, args, |n| Value::Number(n.abs())),
-        "round" => require_number(name, args, |n| Value::Number(n.round())),
+        "round" => require_number(name, args, |n| {
+            Value::Number(n.round_dp_with_strategy(0, options.rounding.strategy()))
+        }),
+        // Nearest multiple of a step, keeping the value's currency or unit:
+        // round_to(47, 5) = 45, round_to($12.30, 0.25) = $12.25,
+        // round_to(1.3 km, 500 m) = 1.5 km
+        "round_to" => {
+            if args.len() != 2 {
+                return Value::error("round_to requires a value and a step");
+            }
+            let (Some(value), Some(_)) = (args[0].as_decimal(), args[1].as_decimal()) else {
+                return Value::error("round_to requires numbers");
+            };
+            // A plain number step is taken as is; any other converts first
+            let step = match &args[1] {
+                Value::Number(step) | Value::BaseNumber { amount: step, .. } => *step,
+                step => match amount_in_kind_of(step, &args[0], ctx) {
+                    Ok(step) => step,
+                    Err(error) => return error_value(error),
+                },
+            };
+            if step <= Decimal::ZERO {
+                return Value::error("round_to step must be positive");
+            }
+            value
+                .checked_div(step)
+                .map(|steps| steps.round_dp_with_strategy(0, options.rounding.strategy()))
+                .and_then(|steps| steps.checked_mul(step))
+                .map(|rounded| match &args[0] {
+                    Value::Percentage(_) => Value::Percentage(rounded),
+                    value => value.with_scaled_amount(rounded),
+                })
+                .unwrap_or_else(|| {
+                    error_value(EvalError::Overflow {
+                        operation: "rounding to a step",
+                    })
+                })
+        }
         "floor" => require_number(name, args, |n| Value::Number(n.floor())),
         "ceil" => require_number(name, args, |n| Value::Number(n.ceil())),
         "sin" => require_angle(f64::sin),
@@ -865,10 +2118,181 @@ fn eval_function(name: &str, args: &[Value]) -> Value {
             }
         }
 
+        // Interest rates: nominal annual rate (APR) <-> effective annual yield (APY)
+        "apr_to_apy" | "apy_to_apr" => {
+            if args.len() != 2 {
+                return Value::error(format!("{name} requires a rate and a number of periods"));
+            }
+            let (Some(rate), Some(periods)) = (rate_fraction(&args[0]), plain_decimal(&args[1]))
+            else {
+                return Value::error(format!("{name} requires a rate and a number of periods"));
+            };
+            let periods = match periods
+                .to_i64()
+                .filter(|n| *n > 0 && periods.fract().is_zero())
+            {
+                Some(periods) => periods,
+                None => return Value::error("compounding periods must be a positive integer"),
+            };
+            let result = if name.eq_ignore_ascii_case("apr_to_apy") {
+                apr_to_apy(rate, periods)
+            } else {
+                apy_to_apr(rate, periods)
+            };
+            result.map(Value::Percentage).unwrap_or_else(error_value)
+        }
+
         _ => Value::Error(EvalError::UnknownFunction(name.to_string())),
     }
 }
 
+/// The least (`Ordering::Less`) or greatest argument when every argument can be
+/// compared with the others, converting currencies and units: `max($100, €50)`.
+/// Empty lines are skipped. `None` when any pair cannot be compared, such as
+/// money and a plain number.
+fn comparable_extreme<'a>(
+    args: &'a [Value],
+    wanted: Ordering,
+    ctx: &EvalContext,
+) -> Option<&'a Value> {
+    let mut values = args.iter().filter(|value| !value.is_empty());
+    let first = values.next()?;
+    values.try_fold(first, |best, value| {
+        let ordering = value.compare_with(best, &ctx.rate_cache)?;
+        Some(if ordering == wanted { value } else { best })
+    })
+}
+
+/// The amount of `value` in the currency or unit of `like`, so 500 m is 0.5
+/// against a value in km. Plain numbers and percentages only match their own
+/// kind; anything else that cannot be converted is `IncompatibleUnits`.
+fn amount_in_kind_of(value: &Value, like: &Value, ctx: &EvalContext) -> Result<Decimal, EvalError> {
+    let converted = match (like, value) {
+        (
+            Value::Number(_) | Value::BaseNumber { .. },
+            Value::Number(amount) | Value::BaseNumber { amount, .. },
+        )
+        | (Value::Percentage(_), Value::Percentage(amount)) => Some(*amount),
+        (
+            Value::Currency { currency, .. },
+            Value::Currency {
+                amount,
+                currency: from,
+            },
+        ) => {
+            if currency == from {
+                Some(*amount)
+            } else {
+                let rate =
+                    ctx.rate_cache
+                        .try_get_rate(*from, *currency)?
+                        .ok_or(EvalError::NoRate {
+                            from: *from,
+                            to: *currency,
+                        })?;
+                Some(amount.checked_mul(rate).ok_or(EvalError::Overflow {
+                    operation: "converting currency",
+                })?)
+            }
+        }
+        (Value::WithCompoundUnit { unit, .. }, Value::WithCompoundUnit { amount, unit: from }) => {
+            from.try_convert_to(*amount, unit)?
+        }
+        _ => None,
+    };
+    converted.ok_or_else(|| EvalError::IncompatibleUnits {
+        operation: "convert",
+        left: describe_kind(value),
+        right: describe_kind(like),
+    })
+}
+
+/// What kind of quantity a value is, for errors: `USD`, `km`, `a date`
+fn describe_kind(value: &Value) -> String {
+    match value {
+        Value::Currency { currency, .. } => currency.code().to_string(),
+        Value::WithCompoundUnit { unit, .. } => unit.symbol.clone(),
+        Value::Percentage(_) => "a percentage".to_string(),
+        Value::Date(_) => "a date".to_string(),
+        _ => "a plain number".to_string(),
+    }
+}
+
+/// A date among the arguments of `sum`, `avg`, `min` or `max` that cannot be
+/// combined with the rest, reported rather than left out: `sum(3 days ago, 2)`
+fn date_argument_error(operation: &'static str, args: &[Value]) -> Option<Value> {
+    let date = args.iter().position(|arg| matches!(arg, Value::Date(_)))?;
+    let other = args
+        .iter()
+        .enumerate()
+        .find(|(index, arg)| *index != date && !arg.is_empty())
+        .map(|(_, arg)| arg);
+    Some(error_value(match other {
+        Some(other) => EvalError::IncompatibleUnits {
+            operation,
+            left: describe_kind(&args[date]),
+            right: describe_kind(other),
+        },
+        None => EvalError::InvalidArgument(format!("Cannot {operation} a date")),
+    }))
+}
+
+/// An interest rate written as a percentage ("5%") or as a fraction (0.05).
+fn rate_fraction(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Percentage(rate) => Some(*rate),
+        other => plain_decimal(other),
+    }
+}
+
+/// Effective annual yield of a nominal rate compounded `periods` times a year:
+/// (1 + r/n)^n - 1
+fn apr_to_apy(rate: Decimal, periods: i64) -> Result<Decimal, EvalError> {
+    let overflow = EvalError::Overflow {
+        operation: "compounding an interest rate",
+    };
+    let growth = Decimal::ONE
+        .checked_add(
+            rate.checked_div(Decimal::from(periods))
+                .ok_or(overflow.clone())?,
+        )
+        .ok_or(overflow.clone())?;
+    if growth <= Decimal::ZERO {
+        return Err(EvalError::InvalidArgument(
+            "interest rate per period must be above -100%".to_string(),
+        ));
+    }
+    growth
+        .checked_powi(periods)
+        .and_then(|compounded| compounded.checked_sub(Decimal::ONE))
+        .ok_or(overflow)
+}
+
+/// Nominal rate compounded `periods` times a year that yields `rate`:
+/// n * ((1 + r)^(1/n) - 1)
+fn apy_to_apr(rate: Decimal, periods: i64) -> Result<Decimal, EvalError> {
+    let overflow = EvalError::Overflow {
+        operation: "converting an interest rate",
+    };
+    let growth = Decimal::ONE.checked_add(rate).ok_or(overflow.clone())?;
+    if growth <= Decimal::ZERO {
+        return Err(EvalError::InvalidArgument(
+            "annual yield must be above -100%".to_string(),
+        ));
+    }
+    if periods == 1 {
+        return Ok(rate);
+    }
+    let exponent = Decimal::ONE
+        .checked_div(Decimal::from(periods))
+        .ok_or(overflow.clone())?;
+    growth
+        .checked_powd(exponent)
+        .and_then(|root| root.checked_sub(Decimal::ONE))
+        .and_then(|per_period| per_period.checked_mul(Decimal::from(periods)))
+        .ok_or(overflow)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -951,12 +2375,77 @@ mod tests {
         assert_eq!(eval_str("100 * 50%").as_f64(), Some(50.0));
     }
 
+    #[test]
+    fn test_percentage_multiplication_policy() {
+        // By default the percentage scales the value and keeps its type
+        assert_eq!(eval_str("$100 * 20%").to_string(), "$20.00");
+        assert_eq!(eval_str("20% * $100").to_string(), "$20.00");
+        assert_eq!(eval_str("5 km * 20%").to_string(), "1 km");
+        assert_eq!(eval_str("20% of $100").to_string(), "$20.00");
+
+        let mut ctx = EvalContext::new();
+        ctx.set_options(EvalOptions {
+            percent_multiply: PercentMultiply::Ratio,
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            eval_with_ctx("$100 * 20%", &mut ctx),
+            Value::Number(Decimal::from(20))
+        );
+        assert_eq!(
+            eval_with_ctx("20% * $100", &mut ctx),
+            Value::Number(Decimal::from(20))
+        );
+        // "of", addition, and division are not affected
+        assert_eq!(eval_with_ctx("20% of $100", &mut ctx).to_string(), "$20.00");
+        assert_eq!(eval_with_ctx("$100 + 20%", &mut ctx).to_string(), "$120.00");
+        assert_eq!(eval_with_ctx("$100 / 50%", &mut ctx).to_string(), "$200.00");
+    }
+
     #[test]
     fn test_percentage_division() {
         // 100 / 50% = 200 (divide by 0.5)
         assert_eq!(eval_str("100 / 50%").as_f64(), Some(200.0));
     }
 
+    #[test]
+    fn test_percentage_points_can_go_negative() {
+        // Percentage minus percentage subtracts points and stays a percentage
+        let result = eval_str("5% - 8%");
+        assert_eq!(result, Value::Percentage(Decimal::new(-3, 2)));
+        assert_eq!(result.to_string(), "-3%");
+
+        assert_eq!(eval_str("5% - 8% - 1%").to_string(), "-4%");
+        assert_eq!(eval_str("2.5% - 4%").to_string(), "-1.50%");
+        assert_eq!(eval_str("-3% + 3%").to_string(), "0%");
+
+        // A negative rate applied to a base reduces it
+        assert_eq!(eval_str("100 + (5% - 8%)").as_f64(), Some(97.0));
+        assert_eq!(eval_str("$200 + (5% - 8%)").to_string(), "$194.00");
+        assert_eq!(eval_str("100 - -3%").as_f64(), Some(103.0));
+    }
+
+    #[test]
+    fn test_percentage_with_percentage() {
+        let percent = |n: i64, scale: u32| Value::Percentage(Decimal::new(n, scale));
+        assert_eq!(eval_str("5% + 3%"), percent(8, 2));
+        assert_eq!(eval_str("5% - 3%"), percent(2, 2));
+        assert_eq!(eval_str("5% of 3%"), percent(15, 4));
+        assert_eq!(eval_str("5% of 3%").to_string(), "0.15%");
+        assert_eq!(eval_str("5% * 3%").to_string(), "0.15%");
+
+        // Scaling a rate by a number keeps it a rate
+        assert_eq!(eval_str("5% * 2"), percent(10, 2));
+        assert_eq!(eval_str("5% * 2").to_string(), "10%");
+        assert_eq!(eval_str("5% / 2").to_string(), "2.50%");
+        assert!(eval_str("5% / 0").is_error());
+        // A number scaled by a rate is still a number, so the number goes
+        // first to take a share of it
+        assert_eq!(eval_str("2 * 5%").to_string(), "0.10");
+        assert_eq!(eval_str("100 * 5%").to_string(), "5");
+        assert_eq!(eval_str("50000 * 20%").to_string(), "10000");
+    }
+
     // ========================================
     // Power Operations
     // ========================================
@@ -973,6 +2462,29 @@ mod tests {
         assert_eq!(eval_str("2 ^ 3 ^ 2").as_f64(), Some(512.0));
     }
 
+    #[test]
+    fn test_power_edge_cases() {
+        assert_eq!(eval_str("0 ^ 0").as_f64(), Some(1.0));
+        assert_eq!(eval_str("(-2) ^ 3").as_f64(), Some(-8.0));
+        assert_eq!(eval_str("(-2) ^ -2").as_f64(), Some(0.25));
+
+        for input in ["0 ^ -1", "0 ^ -0.5", "0 ^ -50%"] {
+            assert_eq!(
+                eval_str(input).to_string(),
+                "Error: division by zero",
+                "{input}"
+            );
+        }
+        // A negative base with a fractional exponent has no real result
+        for input in ["(-8) ^ 0.5", "(-8) ^ (1/3)", "(-8) ^ 50%"] {
+            assert_eq!(
+                eval_str(input).to_string(),
+                "Error: cannot raise negative number to a fractional power",
+                "{input}"
+            );
+        }
+    }
+
     // ========================================
     // Currency Operations
     // ========================================
@@ -980,35 +2492,35 @@ mod tests {
     #[test]
     fn test_currency_addition() {
         let result = eval_str("$100 + $50");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(150.0));
     }
 
     #[test]
     fn test_currency_subtraction() {
         let result = eval_str("$100 - $30");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(70.0));
     }
 
     #[test]
     fn test_currency_multiply_by_number() {
         let result = eval_str("$50 * 3");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(150.0));
     }
 
     #[test]
     fn test_number_multiply_currency() {
         let result = eval_str("3 * $50");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(150.0));
     }
 
     #[test]
     fn test_currency_percentage_of() {
         let result = eval_str("20% of $100");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(20.0));
     }
 
@@ -1016,7 +2528,7 @@ mod tests {
     fn test_currency_add_percentage() {
         // $100 + 10% = $110
         let result = eval_str("$100 + 10%");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(110.0));
     }
 
@@ -1027,36 +2539,28 @@ mod tests {
     #[test]
     fn test_unit_addition() {
         let result = eval_str("5 km + 3 km");
-        assert!(
-            matches!(result, Value::WithCompoundUnit { .. }),
-            "Expected unit value, got {:?}",
-            result
-        );
+        assert!(result.is_unit(), "Expected unit value, got {:?}", result);
         assert_eq!(result.as_f64(), Some(8.0));
     }
 
     #[test]
     fn test_unit_subtraction() {
         let result = eval_str("10 kg - 3 kg");
-        assert!(
-            matches!(result, Value::WithCompoundUnit { .. }),
-            "Expected unit value, got {:?}",
-            result
-        );
+        assert!(result.is_unit(), "Expected unit value, got {:?}", result);
         assert_eq!(result.as_f64(), Some(7.0));
     }
 
     #[test]
     fn test_unit_multiply_by_number() {
         let result = eval_str("5 km * 2");
-        assert!(matches!(result, Value::WithCompoundUnit { .. }));
+        assert!(result.is_unit());
         assert_eq!(result.as_f64(), Some(10.0));
     }
 
     #[test]
     fn test_unit_divide_by_number() {
         let result = eval_str("10 km / 2");
-        assert!(matches!(result, Value::WithCompoundUnit { .. }));
+        assert!(result.is_unit());
         assert_eq!(result.as_f64(), Some(5.0));
     }
 
@@ -1072,7 +2576,7 @@ mod tests {
     fn test_unit_times_currency() {
         // 8h * $50 = $400 (hours times hourly rate)
         let result = eval_str("8h * $50");
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(400.0));
     }
 
@@ -1099,7 +2603,7 @@ mod tests {
         let mut ctx = EvalContext::new();
         eval_with_ctx("price = $100", &mut ctx);
         let result = eval_with_ctx("price + $50", &mut ctx);
-        assert!(matches!(result, Value::Currency { .. }));
+        assert!(result.is_currency());
         assert_eq!(result.as_f64(), Some(150.0));
     }
 
@@ -1133,12 +2637,43 @@ mod tests {
         assert_eq!(eval_str("abs(5)").as_f64(), Some(5.0));
     }
 
+    #[test]
+    fn test_absolute_value_bars() {
+        assert_eq!(eval_str("|-5|").as_f64(), Some(5.0));
+        assert_eq!(eval_str("|3 - 7|").as_f64(), Some(4.0));
+        assert_eq!(eval_str("|2 - 5| + |1 - 4|").as_f64(), Some(6.0));
+        assert_eq!(eval_str("||2 - 5| - 10|").as_f64(), Some(7.0));
+        assert_eq!(eval_str("2 * |-3|").as_f64(), Some(6.0));
+    }
+
     #[test]
     fn test_function_round() {
         assert_eq!(eval_str("round(3.7)").as_f64(), Some(4.0));
         assert_eq!(eval_str("round(3.2)").as_f64(), Some(3.0));
     }
 
+    #[test]
+    fn test_function_round_modes() {
+        // Banker's rounding is the default
+        assert_eq!(eval_str("round(2.5)").as_f64(), Some(2.0));
+        assert_eq!(eval_str("round(3.5)").as_f64(), Some(4.0));
+
+        let mut ctx = EvalContext::new();
+        ctx.set_options(EvalOptions {
+            rounding: RoundingMode::HalfUp,
+            ..EvalOptions::default()
+        });
+        assert_eq!(eval_with_ctx("round(2.5)", &mut ctx).as_f64(), Some(3.0));
+        assert_eq!(eval_with_ctx("round(-2.5)", &mut ctx).as_f64(), Some(-3.0));
+
+        ctx.set_options(EvalOptions {
+            rounding: RoundingMode::Truncate,
+            ..EvalOptions::default()
+        });
+        assert_eq!(eval_with_ctx("round(2.9)", &mut ctx).as_f64(), Some(2.0));
+        assert_eq!(eval_with_ctx("round(-2.9)", &mut ctx).as_f64(), Some(-2.0));
+    }
+
     #[test]
     fn test_function_floor() {
         assert_eq!(eval_str("floor(3.9)").as_f64(), Some(3.0));
@@ -1177,6 +2712,77 @@ mod tests {
         assert_eq!(eval_str("mod(10, 3)").as_f64(), Some(1.0));
     }
 
+    #[test]
+    fn test_round_to_step() {
+        assert_eq!(eval_str("round_to(47, 5)").to_string(), "45");
+        assert_eq!(eval_str("round_to(48, 5)").to_string(), "50");
+        assert_eq!(eval_str("round_to(12.3, 0.25)").to_string(), "12.25");
+        assert_eq!(eval_str("round_to(-7, 5)").to_string(), "-5");
+        assert_eq!(eval_str("round_to($47.30, 0.25)").to_string(), "$47.25");
+        assert_eq!(eval_str("round_to(50 min, 15)").to_string(), "45 min");
+        assert_eq!(
+            eval_str("round_to(47, 0)").to_string(),
+            "Error: round_to step must be positive"
+        );
+        assert!(eval_str("round_to(47, -5)").is_error());
+        assert!(eval_str("round_to(47)").is_error());
+
+        // A step with its own unit or currency converts into the value's
+        assert_eq!(eval_str("round_to(1.3 km, 500 m)").to_string(), "1.50 km");
+        assert_eq!(eval_str("round_to(50 min, 0.25 h)").to_string(), "45 min");
+        let mut ctx = EvalContext::new();
+        ctx.set_exchange_rate(Currency::EUR, Currency::USD, Decimal::TWO);
+        assert_eq!(
+            eval_with_ctx("round_to($47, 5 EUR)", &mut ctx).to_string(),
+            "$50.00"
+        );
+        assert_eq!(eval_str("round_to(12%, 5%)").to_string(), "10%");
+        assert_eq!(
+            eval_str("round_to(1.3 km, 5 min)").to_string(),
+            "Error: Cannot convert min to km"
+        );
+        assert_eq!(
+            eval_str("round_to($47, 5 km)").to_string(),
+            "Error: Cannot convert km to USD"
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert_eq!(eval_str("5 & 3").as_f64(), Some(1.0));
+        assert_eq!(eval_str("5 | 2").as_f64(), Some(7.0));
+        assert_eq!(eval_str("0xFF xor 0x0F").as_f64(), Some(240.0));
+        assert_eq!(eval_str("6 xor 3").as_f64(), Some(5.0));
+        assert_eq!(eval_str("1 << 4").as_f64(), Some(16.0));
+        assert_eq!(eval_str("256 >> 4").as_f64(), Some(16.0));
+        assert_eq!(eval_str("1 >> 200").as_f64(), Some(0.0));
+        assert_eq!(eval_str("1 + 1 << 2").as_f64(), Some(8.0));
+
+        // A hex or binary operand keeps its base
+        let mut ctx = EvalContext::new();
+        eval_with_ctx("mask = 255 in hex", &mut ctx);
+        assert_eq!(eval_with_ctx("mask & 0x0F", &mut ctx).to_string(), "0xf");
+
+        assert!(eval_str("5.5 & 1").is_error());
+        assert!(eval_str("-1 | 2").is_error());
+        assert!(eval_str("$5 & 1").is_error());
+        assert!(matches!(
+            eval_str("1 << 96"),
+            Value::Error(EvalError::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn test_count_functions() {
+        assert_eq!(eval_str("count(1, 0, 5, 0)").as_f64(), Some(4.0));
+        assert_eq!(eval_str("count()").as_f64(), Some(0.0));
+        assert_eq!(eval_str("count($5, 2 km, 10%)").as_f64(), Some(3.0));
+        assert_eq!(eval_str("count_nonzero(1, 0, 5, 0)").as_f64(), Some(2.0));
+        assert_eq!(eval_str("count_nonzero($0, $3)").as_f64(), Some(1.0));
+        assert_eq!(eval_str("count_nonzero()").as_f64(), Some(0.0));
+        assert!(eval_str("count(1, 1 / 0)").is_error());
+    }
+
     #[test]
     fn test_median_and_clamp_functions() {
         assert_eq!(eval_str("median(3, 1, 2)").as_f64(), Some(2.0));
@@ -1184,10 +2790,14 @@ mod tests {
         assert_eq!(eval_str("clamp(120, 0, 100)").as_f64(), Some(100.0));
         assert_eq!(eval_str("clamp(-5, 0, 100)").as_f64(), Some(0.0));
         assert_eq!(eval_str("clamp(40, 0, 100)").as_f64(), Some(40.0));
+        assert_eq!(eval_str("clamp(5, 0, 3)").as_f64(), Some(3.0));
+        assert_eq!(eval_str("clamp(-1, 0, 3)").as_f64(), Some(0.0));
+        assert_eq!(eval_str("clamp(7, 3, 3)").as_f64(), Some(3.0));
 
         assert!(eval_str("median()").is_error());
         assert!(eval_str("clamp(1, 10, 0)").is_error());
         assert!(eval_str("clamp(1, 2)").is_error());
+        assert!(eval_str("clamp(1, 2, 3, 4)").is_error());
     }
 
     #[test]
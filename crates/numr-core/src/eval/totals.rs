@@ -0,0 +1,296 @@
+//! Totals of several values, grouped by currency and physical dimension.
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::cache::RateCache;
+use crate::error::EvalError;
+use crate::types::unit::{self, CompoundUnit};
+use crate::types::{Currency, Dimensions, Value};
+
+/// Document totals split by kind, for front-ends that lay them out themselves
+/// rather than matching on [`Value`] variants.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Totals {
+    /// One amount per currency, in grouped-totals order
+    pub currencies: Vec<(Currency, Decimal)>,
+    /// One amount per physical dimension, in the unit it totals in
+    pub units_by_type: Vec<(Dimensions, CompoundUnit, Decimal)>,
+    /// Sum of plain numbers, which grouped totals leave out
+    pub plain: Decimal,
+}
+
+/// Break values down into [`Totals`]. Currencies and units are grouped as in
+/// [`group_totals`]; plain numbers are summed on their own.
+pub(crate) fn totals_breakdown<'a>(
+    values: impl IntoIterator<Item = &'a Value>,
+    rate_cache: &RateCache,
+    include_zero: bool,
+) -> Result<Totals, EvalError> {
+    let values: Vec<&Value> = values.into_iter().collect();
+    let mut totals = Totals {
+        plain: plain_total(values.iter().copied())?,
+        ..Totals::default()
+    };
+    for group in group_totals(values, rate_cache, include_zero)? {
+        match group {
+            Value::Currency { amount, currency } => totals.currencies.push((currency, amount)),
+            Value::WithCompoundUnit { amount, unit } => {
+                totals.units_by_type.push((unit.dimensions, unit, amount));
+            }
+            _ => {}
+        }
+    }
+    Ok(totals)
+}
+
+/// Sum values per currency and per physical dimension.
+/// - Currencies are converted and summed to the last used currency
+/// - Compatible units are converted to the last used unit
+/// - Plain numbers, percentages, and prices per unit are omitted
+/// - Groups that net to zero are omitted unless `include_zero` is set
+/// - Fiat currencies come first, then crypto, each by code; then units
+pub(crate) fn group_totals<'a>(
+    values: impl IntoIterator<Item = &'a Value>,
+    rate_cache: &RateCache,
+    include_zero: bool,
+) -> Result<Vec<Value>, EvalError> {
+    let mut currency_amounts: Vec<(Currency, Decimal)> = Vec::new();
+    let mut unit_amounts: HashMap<_, Vec<(Decimal, &CompoundUnit)>> = HashMap::new();
+
+    // Collect all values, tracking last used currency/unit
+    for value in values {
+        match value {
+            Value::Currency { amount, currency } => {
+                currency_amounts.push((*currency, *amount));
+            }
+            Value::WithCompoundUnit { amount, unit } => {
+                // Temperature differences total apart from temperature readings
+                unit_amounts
+                    .entry((unit.dimensions, unit.is_delta))
+                    .or_default()
+                    .push((*amount, unit));
+            }
+            // Prices per unit are rates, not amounts, so they never add up; nor do dates
+            Value::Number(_)
+            | Value::BaseNumber { .. }
+            | Value::Percentage(_)
+            | Value::CurrencyRate { .. }
+            | Value::Date(_)
+            | Value::Empty
+            | Value::Error(_) => {}
+        }
+    }
+
+    let mut result = Vec::new();
+
+    // Sum all currencies, converting to the last used currency
+    // Currencies that can't be converted are kept separate
+    if let Some(&(target_currency, _)) = currency_amounts.last() {
+        let mut total_in_target = Decimal::ZERO;
+        let mut unconverted: HashMap<Currency, Decimal> = HashMap::new();
+
+        for (currency, amount) in &currency_amounts {
+            if *currency == target_currency {
+                total_in_target =
+                    total_in_target
+                        .checked_add(*amount)
+                        .ok_or(EvalError::Overflow {
+                            operation: "summing currency values",
+                        })?;
+            } else if let Some(rate) = rate_cache.try_get_rate(*currency, target_currency)? {
+                let converted = amount.checked_mul(rate).ok_or(EvalError::Overflow {
+                    operation: "converting currency totals",
+                })?;
+                total_in_target =
+                    total_in_target
+                        .checked_add(converted)
+                        .ok_or(EvalError::Overflow {
+                            operation: "summing currency values",
+                        })?;
+            } else {
+                // Can't convert - keep this currency separate instead of corrupting totals
+                let total = unconverted.entry(*currency).or_default();
+                *total = total.checked_add(*amount).ok_or(EvalError::Overflow {
+                    operation: "summing unconverted currencies",
+                })?;
+            }
+        }
+
+        if include_zero || !total_in_target.is_zero() {
+            result.push(Value::Currency {
+                amount: total_in_target,
+                currency: target_currency,
+            });
+        }
+
+        // Add unconverted currencies as separate totals
+        for (currency, amount) in unconverted {
+            if include_zero || !amount.is_zero() {
+                result.push(Value::Currency { amount, currency });
+            }
+        }
+    }
+
+    // Convert each value to the last unit before summing. This is required for
+    // affine units such as Celsius and Fahrenheit, whose offsets are not additive.
+    for amounts in unit_amounts.into_values() {
+        let target_unit = amounts
+            .last()
+            .map(|(_, unit)| (*unit).clone())
+            .expect("unit groups are never empty");
+        let total = amounts
+            .into_iter()
+            .try_fold(Decimal::ZERO, |total, (amount, unit)| {
+                let converted =
+                    unit::try_convert(amount, unit, &target_unit)?.ok_or_else(|| {
+                        EvalError::IncompatibleUnits {
+                            operation: "convert",
+                            left: unit.symbol.clone(),
+                            right: target_unit.symbol.clone(),
+                        }
+                    })?;
+                total.checked_add(converted).ok_or(EvalError::Overflow {
+                    operation: "summing unit values",
+                })
+            })?;
+        if include_zero || !total.is_zero() {
+            result.push(Value::WithCompoundUnit {
+                amount: total,
+                unit: target_unit,
+            });
+        }
+    }
+
+    // Sort results for consistent display order:
+    // 1. Currencies first: fiat before crypto, each by code
+    // 2. Units by dimensions, then symbol, then readings before differences
+    result.sort_by(|a, b| match (a, b) {
+        (Value::Currency { currency: c1, .. }, Value::Currency { currency: c2, .. }) => c1
+            .is_crypto()
+            .cmp(&c2.is_crypto())
+            .then(c1.code().cmp(c2.code())),
+        (Value::Currency { .. }, _) => std::cmp::Ordering::Less,
+        (_, Value::Currency { .. }) => std::cmp::Ordering::Greater,
+
+        // Units sorted by dimensions (length, mass, time, temperature, data, angle)
+        (Value::WithCompoundUnit { unit: u1, .. }, Value::WithCompoundUnit { unit: u2, .. }) => {
+            let d1 = &u1.dimensions;
+            let d2 = &u2.dimensions;
+            d1.length
+                .cmp(&d2.length)
+                .then(d1.mass.cmp(&d2.mass))
+                .then(d1.time.cmp(&d2.time))
+                .then(d1.temperature.cmp(&d2.temperature))
+                .then(d1.data.cmp(&d2.data))
+                .then(d1.angle.cmp(&d2.angle))
+                .then(u1.symbol.cmp(&u2.symbol))
+                .then(u1.is_delta.cmp(&u2.is_delta)) // Final tiebreaker
+        }
+
+        _ => std::cmp::Ordering::Equal,
+    });
+
+    Ok(result)
+}
+
+/// Sum of the plain numbers among `values`; every other kind is skipped
+pub(crate) fn plain_total<'a>(
+    values: impl IntoIterator<Item = &'a Value>,
+) -> Result<Decimal, EvalError> {
+    values
+        .into_iter()
+        .filter_map(|value| match value {
+            Value::Number(n) | Value::BaseNumber { amount: n, .. } => Some(*n),
+            _ => None,
+        })
+        .try_fold(Decimal::ZERO, |total, value| {
+            total.checked_add(value).ok_or(EvalError::Overflow {
+                operation: "summing values",
+            })
+        })
+}
+
+/// Add up values into one result, as `sum(above)` does.
+///
+/// Currencies convert to the last used currency and units to the last used unit,
+/// as in [`group_totals`]. Plain numbers add to that amount ("$100" and "50" make
+/// $150). Values that cannot share one type, such as USD without a rate to EUR or
+/// a currency next to a length, are an error rather than a silently dropped part.
+pub(crate) fn typed_total(values: &[Value], rate_cache: &RateCache) -> Result<Value, EvalError> {
+    let plain = plain_total(values)?;
+
+    // A block that nets to $0 still sums to $0.00 rather than a plain 0
+    match group_totals(values, rate_cache, true)?.as_slice() {
+        [] => Ok(Value::Number(plain)),
+        [total] => {
+            let amount = total.as_decimal().unwrap_or_default();
+            let amount = amount.checked_add(plain).ok_or(EvalError::Overflow {
+                operation: "summing values",
+            })?;
+            Ok(total.with_scaled_amount(amount))
+        }
+        groups => {
+            let kinds: Vec<String> = groups
+                .iter()
+                .map(|group| match group {
+                    Value::Currency { currency, .. } => currency.code().to_string(),
+                    Value::WithCompoundUnit { unit, .. } => unit.dimensions.to_string(),
+                    other => other.to_string(),
+                })
+                .collect();
+            Err(EvalError::IncompatibleUnits {
+                operation: "add",
+                left: kinds[0].clone(),
+                right: kinds[1..].join(" and "),
+            })
+        }
+    }
+}
+
+/// Count the values that take part in a total.
+pub(crate) fn total_count(values: &[Value]) -> usize {
+    values
+        .iter()
+        .filter(|value| {
+            matches!(
+                value,
+                Value::Number(_)
+                    | Value::BaseNumber { .. }
+                    | Value::Currency { .. }
+                    | Value::WithCompoundUnit { .. }
+            )
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unconvertible_currencies_sort_fiat_first_then_by_code() {
+        let money = |amount: i64, currency| Value::currency(Decimal::from(amount), currency);
+        let values = [
+            money(1, Currency::BTC),
+            money(5, Currency::USD),
+            money(2, Currency::ETH),
+            money(3, Currency::GBP),
+            money(4, Currency::EUR),
+        ];
+        // Without rates nothing converts, so every currency totals on its own
+        for order in [values.to_vec(), values.iter().rev().cloned().collect()] {
+            let totals = group_totals(&order, &RateCache::new(), false).unwrap();
+            let codes: Vec<&str> = totals
+                .iter()
+                .map(|total| match total {
+                    Value::Currency { currency, .. } => currency.code(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            assert_eq!(codes, ["EUR", "GBP", "USD", "BTC", "ETH"]);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Markdown export of an evaluated session.
+//!
+//! Lines become rows of an `| Input | Result |` table. Comment lines become
+//! headings and blank lines close the current table, so the sections of a
+//! sheet stay apart. Grouped totals follow in a section of their own.
+
+use crate::Engine;
+use std::fmt::Write;
+
+const TABLE_HEADER: &str = "| Input | Result |\n| --- | --- |\n";
+
+impl Engine {
+    /// Render the evaluated lines and grouped totals as a Markdown document
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+        let mut in_table = false;
+        for line in &self.lines {
+            let input = line.input.trim();
+            if input.is_empty() || comment_text(input).is_some() {
+                if in_table {
+                    output.push('\n');
+                    in_table = false;
+                }
+                if let Some(heading) = comment_text(input).filter(|text| !text.is_empty()) {
+                    let _ = writeln!(output, "## {heading}\n");
+                }
+                continue;
+            }
+            if !in_table {
+                output.push_str(TABLE_HEADER);
+                in_table = true;
+            }
+            let result = self.format_line(line, false);
+            let _ = writeln!(
+                output,
+                "| `{}` | {} |",
+                escape_cell(input),
+                escape_cell(&result)
+            );
+        }
+        if in_table {
+            output.push('\n');
+        }
+
+        let totals = self.grouped_totals();
+        if !totals.is_empty() {
+            output.push_str("## Totals\n\n");
+            for total in &totals {
+                let _ = writeln!(output, "- {}", self.format(total));
+            }
+        }
+
+        let trimmed = output.trim_end().len();
+        output.truncate(trimmed);
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Text of a `#` or `//` comment line, without its markers
+fn comment_text(input: &str) -> Option<&str> {
+    let text = if input.starts_with('#') {
+        input.trim_start_matches('#')
+    } else if input.starts_with("//") {
+        input.trim_start_matches('/')
+    } else {
+        return None;
+    };
+    Some(text.trim())
+}
+
+/// Keep pipes inside a cell from starting a new column
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decimal, Currency};
+
+    #[test]
+    fn test_markdown_snapshot() {
+        let mut engine = Engine::new();
+        engine.set_exchange_rate(Currency::USD, Currency::EUR, decimal("0.5"));
+        for line in [
+            "# Monthly budget",
+            "rent = $1200",
+            "food = €300",
+            "rent * 2",
+            "",
+            "// Travel",
+            "5 km + 500 m",
+            "|-3| + unknown",
+        ] {
+            engine.eval(line);
+        }
+
+        let expected = "\
+## Monthly budget
+
+| Input | Result |
+| --- | --- |
+| `rent = $1200` | $1200.00 |
+| `food = €300` | €300.00 |
+| `rent * 2` | $2400.00 |
+
+## Travel
+
+| Input | Result |
+| --- | --- |
+| `5 km + 500 m` | 5.50 km |
+| `\\|-3\\| + unknown` | Error: unknown variable: unknown |
+
+## Totals
+
+- $4200.00
+- 5.50 km
+";
+        assert_eq!(engine.to_markdown(), expected);
+    }
+
+    #[test]
+    fn test_markdown_of_empty_session() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.to_markdown(), "");
+        engine.eval("");
+        engine.eval("#");
+        assert_eq!(engine.to_markdown(), "");
+    }
+}
@@ -110,8 +110,7 @@ async fn fetch_crypto_prices(
     config: &FetchConfig,
 ) -> Result<HashMap<String, Decimal>, RateError> {
     // Get crypto IDs from the currency registry (single source of truth)
-    let crypto_currencies: Vec<_> = Currency::all()
-        .filter(|c| c.is_crypto())
+    let crypto_currencies: Vec<_> = Currency::crypto()
         .filter_map(|c| c.coingecko_id().map(|id| (id, c.code())))
         .collect();
 
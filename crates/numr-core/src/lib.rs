@@ -25,14 +25,19 @@
 //! assert_eq!(result.as_f64(), Some(30.0));
 //! ```
 
+use parser::{parse_line_with, try_parse_exact_with, NumberSyntax};
 use serde::Serialize;
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::str::FromStr;
 
 pub mod cache;
 pub mod catalog;
 pub mod error;
 pub mod eval;
+mod export;
 pub mod parser;
+mod state;
 pub mod types;
 
 #[cfg(all(feature = "fetch", not(target_arch = "wasm32")))]
@@ -41,13 +46,20 @@ pub mod fetch;
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
-pub use cache::RateCache;
-pub use error::{EvalError, ParseError, RateError};
-pub use eval::EvalContext;
-pub use parser::{parse_line, try_parse_exact, Ast, BinaryOp, Expr};
+pub use cache::{DefaultRates, RateCache};
+pub use error::{EvalError, ParseError, RateError, StateError};
+pub use eval::{
+    EvalContext, EvalOptions, NumberLocale, PercentMultiply, Totals, UserFunction, YenSymbol,
+};
+pub use parser::{
+    parse_line, parse_line_detailed, strip_comment, try_parse_exact, Ast, BinaryOp, Expr,
+    ParsedLine, MAX_REPEAT_COUNT,
+};
 pub use types::{
-    format_currency_value, format_number, CompoundUnit, Currency, CurrencyDef, Dimensions,
-    NumberBase, RuntimeUnitDef, Value, CURRENCIES, UNITS,
+    format_currency_value, format_currency_value_with, format_number, format_number_with,
+    CompoundUnit, Currency, CurrencyDef, CurrencySubunit, Date, Dimensions, FormatOptions,
+    GroupSeparator, GroupSize, NumberBase, RoundingMode, RuntimeUnitDef, Value, CURRENCIES,
+    CURRENCY_SUBUNITS, CURRENCY_TEXT_ALIASES, UNITS,
 };
 
 // Re-export Decimal for tests and external use
@@ -74,10 +86,25 @@ pub use fetch::{
     DEFAULT_FIAT_RATES_URL,
 };
 
+/// Custom number format hook: returns the value of a literal it recognizes
+/// (such as accounting `(100)` or `100CR`), or `None` to leave it to the parser
+pub type NumberParser = Box<dyn Fn(&str) -> Option<Decimal> + Send + Sync>;
+
 /// Main engine for evaluating expressions
 pub struct Engine {
     context: EvalContext,
     lines: Vec<LineResult>,
+    /// Index of the first line after the last blank line, kept up to date as
+    /// lines are added so `sum(above)` never rescans the document
+    block_start: usize,
+    /// Sum of the lines [`Engine::sum`] counts, kept up to date as lines are
+    /// added or consumed so `total` never rescans the document. `None` after an
+    /// overflow, when `try_sum` recounts to report it.
+    running_sum: Option<Decimal>,
+    format_options: FormatOptions,
+    number_parser: Option<NumberParser>,
+    /// Textual currency indicators read under `EvalOptions::normalize_currency_symbols`
+    currency_aliases: Vec<(String, Currency)>,
 }
 
 /// Result of evaluating a single line
@@ -89,6 +116,26 @@ pub struct LineResult {
     pub is_continuation_source: bool,
     /// Aggregate query results are display-only and never feed another aggregate.
     pub is_aggregate: bool,
+    /// Non-fatal diagnostics, such as raw amounts used under lenient units
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// For conversion lines, the value before conversion (`5 km` in `5 km in mi`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub converted_from: Option<Value>,
+    /// For lines ending in a percentage step, the amount it added or removed
+    /// (`20` in `100 + 20%`, `$-10.00` in `$50 - 20%`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentage_change: Option<Value>,
+}
+
+/// Result of [`Engine::eval_preview_detailed`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PreviewResult {
+    pub value: Value,
+    /// The line continued the previous result (`+ 5` was read as `_ + 5`)
+    pub used_continuation: bool,
+    /// The line assigns one or more variables (`tax = 15%`)
+    pub is_assignment: bool,
 }
 
 /// Serializable result shared by CLI, TUI, and WASM document adapters.
@@ -106,16 +153,110 @@ impl Engine {
         Self {
             context: EvalContext::new(),
             lines: Vec::new(),
+            block_start: 0,
+            running_sum: Some(Decimal::ZERO),
+            format_options: FormatOptions::default(),
+            number_parser: None,
+            currency_aliases: CURRENCY_TEXT_ALIASES
+                .iter()
+                .map(|(alias, currency)| (alias.to_string(), *currency))
+                .collect(),
+        }
+    }
+
+    /// Output formatting preferences used by [`Engine::format`]
+    #[must_use]
+    pub fn format_options(&self) -> &FormatOptions {
+        &self.format_options
+    }
+
+    /// Replace the output formatting preferences
+    pub fn set_format_options(&mut self, options: FormatOptions) {
+        self.format_options = options;
+    }
+
+    /// Evaluation policies applied to subsequent lines
+    #[must_use]
+    pub fn options(&self) -> &EvalOptions {
+        self.context.options()
+    }
+
+    /// Replace the evaluation policies
+    pub fn set_options(&mut self, options: EvalOptions) {
+        self.context.set_options(options);
+    }
+
+    /// Fix the date that relative date expressions ("days until 2025-01-01")
+    /// count from. `None` goes back to the system clock (UTC).
+    pub fn set_today(&mut self, today: Option<Date>) {
+        self.context.set_today(today);
+    }
+
+    /// Read domain-specific number formats with `parser`.
+    ///
+    /// While a line is parsed, each number literal is offered to the parser as
+    /// written before the built-in formats, and so is a term that holds a number
+    /// with a word attached (`100CR`) or in parentheses (`(100)`). A literal it
+    /// recognizes reads as the returned number; anything else, including `1,234`
+    /// and `75 000`, keeps the built-in handling.
+    pub fn set_number_parser(
+        &mut self,
+        parser: impl Fn(&str) -> Option<Decimal> + Send + Sync + 'static,
+    ) {
+        self.number_parser = Some(Box::new(parser));
+    }
+
+    /// Go back to the built-in number formats only
+    pub fn clear_number_parser(&mut self) {
+        self.number_parser = None;
+    }
+
+    /// Format a value using this engine's formatting preferences
+    #[must_use]
+    pub fn format(&self, value: &Value) -> String {
+        value.format(&self.format_options)
+    }
+
+    /// Format a line's result, showing the original value of a conversion
+    /// beside its result when `show_source` is set (`5 km → 3.11 mi`). A
+    /// converted result keeps the unit it was converted to, even when units
+    /// are normalized.
+    #[must_use]
+    pub fn format_line(&self, line: &LineResult, show_source: bool) -> String {
+        match &line.converted_from {
+            Some(source) if show_source => {
+                line.value.format_with_source(source, &self.format_options)
+            }
+            Some(_) => line.value.format(&FormatOptions {
+                normalize_units: false,
+                ..self.format_options
+            }),
+            None => self.format(&line.value),
         }
     }
 
+    /// [`Engine::eval`], also returning how long it took. Plain `eval` reads no
+    /// clock, so timing costs nothing unless asked for (native only).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn eval_timed(&mut self, input: &str) -> (Value, std::time::Duration) {
+        let start = std::time::Instant::now();
+        let value = self.eval(input);
+        (value, start.elapsed())
+    }
+
     /// Evaluate a single line and store the result
     pub fn eval(&mut self, input: &str) -> Value {
-        // Computing the document sum is linear in history, so materialize the
-        // magic variable only for lines that can actually reference it.
+        // Materialize the magic variable only for lines that can reference it
         if Self::references_total(input) {
             self.context.set_variable("total".to_string(), self.sum());
         }
+        if Self::references_word(input, "pct_of_total") {
+            self.context
+                .set_running_totals(self.totals_breakdown().ok());
+        }
+        if Self::references_above(input) {
+            self.context.set_lines_above(self.lines_above());
+        }
 
         // Set '_', 'ANS', and 'ans' to the last valid result
         if let Some(last_value) = self.last_valid_line().map(|lr| lr.value.clone()) {
@@ -127,50 +268,204 @@ impl Engine {
         }
 
         // Try continuation-first if '_' exists, otherwise normal parse
-        let (result, continuation_succeeded) = self.eval_with_continuation(input);
+        self.context.take_warnings();
+        self.context.take_conversion_source();
+        self.context.take_percentage_change();
+        self.context.take_read_above();
+        let expression = self.preprocess(input);
+        let new_block = self.starts_new_block();
+        // Built from the field, since the context is borrowed mutably below
+        let numbers = NumberSyntax {
+            locale: self.context.options.number_locale,
+            custom: self.number_parser.as_deref(),
+        };
+        let (result, continuation_succeeded) =
+            Self::eval_with_context(&expression, &mut self.context, &numbers, new_block, |ctx| {
+                ctx.get_variable("_").is_some()
+            });
+        let warnings = self.context.take_warnings();
+        let converted_from = self.context.take_conversion_source();
+        let percentage_change = self.context.take_percentage_change();
+        let read_above = self.context.take_read_above();
 
         // Mark previous line as consumed if continuation succeeded or input uses '_'
         if !result.is_error() && (continuation_succeeded || Self::references_underscore(input)) {
             if let Some(last) = self.last_valid_line_mut() {
+                let counted = !last.is_continuation_source && !last.is_aggregate;
                 last.is_continuation_source = true;
+                let consumed = last.value.as_decimal().filter(|_| counted);
+                if let Some(amount) = consumed {
+                    self.running_sum = self.running_sum.and_then(|sum| sum.checked_sub(amount));
+                }
             }
         }
 
+        let is_aggregate = read_above || Self::is_aggregate_query(input);
+        if let Some(amount) = result.as_decimal().filter(|_| !is_aggregate) {
+            self.running_sum = self.running_sum.and_then(|sum| sum.checked_add(amount));
+        }
+
         self.lines.push(LineResult {
             input: input.to_string(),
             value: result.clone(),
             is_continuation_source: false,
-            is_aggregate: Self::is_aggregate_query(input),
+            is_aggregate,
+            warnings,
+            converted_from,
+            percentage_change,
         });
+        if input.trim().is_empty() {
+            self.block_start = self.lines.len();
+        }
 
         result
     }
 
-    /// Try continuation parsing first, fall back to normal parsing
-    /// Returns (result, whether_continuation_succeeded)
-    fn eval_with_continuation(&mut self, input: &str) -> (Value, bool) {
-        Self::eval_with_context(input, &mut self.context, |ctx| {
-            ctx.get_variable("_").is_some()
-        })
+    /// Read `alias` as `currency` when currency normalization is enabled, in
+    /// addition to the built-in [`CURRENCY_TEXT_ALIASES`] (`kr` for SEK, say).
+    /// Aliases match case-insensitively at the start of a word.
+    pub fn add_currency_alias(&mut self, alias: impl Into<String>, currency: Currency) {
+        let alias = alias.into();
+        self.currency_aliases
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&alias));
+        self.currency_aliases.push((alias, currency));
+    }
+
+    /// Apply the optional rewrites that run before parsing
+    fn preprocess<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        let steps: [for<'b> fn(&Self, &'b str) -> Cow<'b, str>; 2] =
+            [Self::resolve_yen_symbol, Self::normalize_currency_aliases];
+        let mut output = Cow::Borrowed(input);
+        for step in steps {
+            let rewritten = match step(self, &output) {
+                Cow::Borrowed(_) => continue,
+                Cow::Owned(rewritten) => rewritten,
+            };
+            output = Cow::Owned(rewritten);
+        }
+        output
+    }
+
+    /// Spell a bare `¥` as `CN¥` when it should read as yuan rather than yen.
+    /// `¥` directly after a letter (`JP¥`, `CN¥`) is left as written.
+    /// Comments are left untouched.
+    fn resolve_yen_symbol<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        if self.context.options.yen_symbol != YenSymbol::Cny {
+            return Cow::Borrowed(input);
+        }
+        let code = strip_comment(input);
+        if !code.contains('¥') {
+            return Cow::Borrowed(input);
+        }
+        let mut output = String::with_capacity(input.len() + 8);
+        let mut previous = None;
+        for c in code.chars() {
+            if c == '¥' && !previous.is_some_and(|p: char| p.is_alphabetic()) {
+                output.push_str("CN");
+            }
+            output.push(c);
+            previous = Some(c);
+        }
+        output.push_str(&input[code.len()..]);
+        Cow::Owned(output)
+    }
+
+    /// Rewrite textual currency indicators as currency codes the grammar reads:
+    /// `US$100` becomes `100 USD` and `500 Rs` becomes `500 INR`.
+    /// Comments are left untouched.
+    fn normalize_currency_aliases<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        if !self.context.options.normalize_currency_symbols {
+            return Cow::Borrowed(input);
+        }
+        let code_end = strip_comment(input).len();
+        let mut aliases: Vec<&(String, Currency)> = self.currency_aliases.iter().collect();
+        aliases.sort_by_key(|(alias, _)| std::cmp::Reverse(alias.len()));
+
+        let mut output = String::with_capacity(input.len() + 8);
+        let mut rest = &input[..code_end];
+        let mut at_word_start = true;
+        while let Some(c) = rest.chars().next() {
+            let found = aliases.iter().find_map(|(alias, currency)| {
+                let after = rest
+                    .get(..alias.len())
+                    .filter(|head| head.eq_ignore_ascii_case(alias))
+                    .map(|_| &rest[alias.len()..])?;
+                let ends_word = !after.starts_with(|c: char| c.is_alphabetic() || c == '_');
+                ends_word.then_some((after, *currency))
+            });
+            match found.filter(|_| at_word_start) {
+                Some((after, currency)) => {
+                    let amount = after.trim_start();
+                    let amount_len = amount
+                        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+                        .unwrap_or(amount.len());
+                    if amount.starts_with(|c: char| c.is_ascii_digit()) {
+                        // Prefix indicator: move it after the amount
+                        output.push_str(&amount[..amount_len]);
+                        output.push(' ');
+                        output.push_str(currency.code());
+                        rest = &amount[amount_len..];
+                    } else {
+                        output.push_str(currency.code());
+                        rest = after;
+                    }
+                    at_word_start = false;
+                }
+                None => {
+                    output.push(c);
+                    at_word_start = !(c.is_alphanumeric() || c == '_');
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        if output == input[..code_end] {
+            return Cow::Borrowed(input);
+        }
+        output.push_str(&input[code_end..]);
+        Cow::Owned(output)
+    }
+
+    /// How this engine reads number literals while parsing
+    fn number_syntax(&self) -> NumberSyntax<'_> {
+        NumberSyntax {
+            locale: self.context.options.number_locale,
+            custom: self.number_parser.as_deref(),
+        }
     }
 
     /// Shared continuation logic used by both eval and eval_preview.
     /// `has_previous` checks whether a previous result exists for continuation.
+    ///
+    /// Continuation rules:
+    /// - A line starting with an operator, `in`/`to`, or `as % of` continues the
+    ///   last result, skipping blank lines and comments (`100`, `- 5` = 95)
+    /// - Directly below a value, `-5` is a subtraction as well (`100`, `-5` = 95)
+    /// - After a blank line (`new_block`), a negative number written without a
+    ///   space (`-5`, `-2 kg`) starts a fresh value instead
+    /// - A leading `=` always starts a fresh expression (`100`, `=-5` = -5)
     fn eval_with_context(
         input: &str,
         ctx: &mut eval::EvalContext,
+        numbers: &NumberSyntax<'_>,
+        new_block: bool,
         has_previous: impl FnOnce(&eval::EvalContext) -> bool,
     ) -> (Value, bool) {
         // Skip continuation for empty lines and comments
         let trimmed = input.trim();
         if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("//") {
-            return (Self::parse_and_eval_with(input, ctx), false);
+            return (Self::parse_and_eval_with(input, ctx, numbers), false);
+        }
+        if let Some(expression) = trimmed.strip_prefix('=') {
+            return (Self::parse_and_eval_with(expression, ctx, numbers), false);
+        }
+        if new_block && Self::is_negative_literal(trimmed) {
+            return (Self::parse_and_eval_with(input, ctx, numbers), false);
         }
 
         // Only try continuation if it looks like one and we have a previous result
         if Self::looks_like_continuation_static(trimmed) && has_previous(ctx) {
             let continued = format!("_ {}", input);
-            if let Ok(ast) = try_parse_exact(&continued) {
+            if let Ok(ast) = try_parse_exact_with(&continued, numbers) {
                 let result = eval::evaluate(&ast, ctx);
                 if !result.is_error() {
                     return (result, true);
@@ -181,7 +476,24 @@ impl Engine {
             }
         }
         // Fall back to normal parsing
-        (Self::parse_and_eval_with(input, ctx), false)
+        (Self::parse_and_eval_with(input, ctx, numbers), false)
+    }
+
+    /// True when a blank line separates the next line from the last value
+    fn starts_new_block(&self) -> bool {
+        self.lines
+            .iter()
+            .rev()
+            .take_while(|lr| lr.value.is_empty() || lr.value.is_error())
+            .any(|lr| lr.input.trim().is_empty())
+    }
+
+    /// A minus sign attached to a number ("-5", not "- 5")
+    fn is_negative_literal(trimmed: &str) -> bool {
+        trimmed
+            .strip_prefix(['-', '−'])
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|next| next.is_ascii_digit() || next == '.')
     }
 
     /// Check if input looks like it's continuing a previous expression
@@ -193,7 +505,7 @@ impl Engine {
 
         // Starts with a single-character operator
         let first = trimmed.chars().next().expect("checked non-empty above");
-        if "+-*/÷^".contains(first) {
+        if "+-−*/÷^@".contains(first) {
             return true;
         }
 
@@ -205,6 +517,11 @@ impl Engine {
             }
         }
 
+        // Starts with "as % of" (share of a whole)
+        if trimmed.starts_with("as") && trimmed[2..].trim_start().starts_with('%') {
+            return true;
+        }
+
         // Starts with "in" or "to" (multi-character operators)
         // Check for boundary to avoid matching "interest" as "in"
         if trimmed.starts_with("in") {
@@ -225,19 +542,53 @@ impl Engine {
 
     /// Check if input contains a standalone `_` or `ANS` reference (not part of another identifier)
     fn references_underscore(input: &str) -> bool {
-        input
+        strip_comment(input)
             .split(|c: char| !c.is_alphanumeric() && c != '_')
             .any(|word| word == "_" || word.eq_ignore_ascii_case("ans"))
     }
 
+    /// Lines that report on other lines rather than adding to them. Aggregates
+    /// over `above` are recognized while evaluating, so a variable named
+    /// `above` or the word in other text does not count.
     fn is_aggregate_query(input: &str) -> bool {
-        input.trim().eq_ignore_ascii_case("total")
+        strip_comment(input).trim().eq_ignore_ascii_case("total")
+            || Self::references_word(input, "pct_of_total")
+    }
+
+    fn references_above(input: &str) -> bool {
+        strip_comment(input)
+            .split(|character: char| !character.is_alphanumeric() && character != '_')
+            .any(|word| catalog::LINE_REFERENCES.contains(&word))
+    }
+
+    /// Results of the block of lines directly above, back to the previous blank
+    /// line. Continuation sources and other aggregates are skipped, as in totals.
+    /// Only the most recent `EvalOptions::max_aggregate_lines` results are kept.
+    fn lines_above(&self) -> Vec<Value> {
+        let limit = self
+            .context
+            .options
+            .max_aggregate_lines
+            .unwrap_or(usize::MAX);
+        let mut values: Vec<Value> = self.lines[self.block_start..]
+            .iter()
+            .rev()
+            .filter(|lr| !lr.is_continuation_source && !lr.is_aggregate)
+            .take(limit)
+            .map(|lr| lr.value.clone())
+            .collect();
+        values.reverse();
+        values
     }
 
     fn references_total(input: &str) -> bool {
-        input
+        Self::references_word(input, "total")
+    }
+
+    fn references_word(input: &str, name: &str) -> bool {
+        strip_comment(input)
             .split(|character: char| !character.is_alphanumeric() && character != '_')
-            .any(|word| word.eq_ignore_ascii_case("total"))
+            .any(|word| word.eq_ignore_ascii_case(name))
     }
 
     /// Find the last valid (non-empty, non-error) line result
@@ -257,16 +608,39 @@ impl Engine {
     }
 
     /// Parse and evaluate with a given context
-    fn parse_and_eval_with(input: &str, ctx: &mut eval::EvalContext) -> Value {
-        match parse_line(input) {
+    fn parse_and_eval_with(
+        input: &str,
+        ctx: &mut eval::EvalContext,
+        numbers: &NumberSyntax<'_>,
+    ) -> Value {
+        match parse_line_with(input, numbers) {
             Ok(ast) => eval::evaluate(&ast, ctx),
             Err(e) => Value::Error(EvalError::Parse(e)),
         }
     }
 
+    /// Evaluate untrusted input without touching the engine's state.
+    ///
+    /// The line can read variables but not assign them, define functions,
+    /// `repeat`, or call user functions; those fail with [`EvalError::NotAllowed`].
+    /// The usual input size and complexity limits apply. Nothing is stored.
+    #[must_use]
+    pub fn eval_sandboxed(&self, input: &str) -> Value {
+        let mut ctx = self.context.clone();
+        ctx.options.sandbox = true;
+        ctx.set_lines_above(Vec::new());
+        Self::parse_and_eval_with(&self.preprocess(input), &mut ctx, &self.number_syntax())
+    }
+
     /// Evaluate without storing the result (for previews)
     #[must_use]
     pub fn eval_preview(&self, input: &str) -> Value {
+        self.eval_preview_detailed(input).value
+    }
+
+    /// Like [`Engine::eval_preview`], also reporting how the line was read
+    #[must_use]
+    pub fn eval_preview_detailed(&self, input: &str) -> PreviewResult {
         let mut ctx = self.context.clone();
 
         // Set '_', 'ANS', and 'ans' to the last valid result for preview context
@@ -275,10 +649,33 @@ impl Engine {
             ctx.set_variable("ANS".to_string(), last.value.clone());
             ctx.set_variable("ans".to_string(), last.value.clone());
         }
+        if Self::references_above(input) {
+            ctx.set_lines_above(self.lines_above());
+        }
 
-        let (result, _) =
-            Self::eval_with_context(input, &mut ctx, |ctx| ctx.get_variable("_").is_some());
-        result
+        let expression = self.preprocess(input);
+        let numbers = self.number_syntax();
+        let (value, used_continuation) = Self::eval_with_context(
+            &expression,
+            &mut ctx,
+            &numbers,
+            self.starts_new_block(),
+            |ctx| ctx.get_variable("_").is_some(),
+        );
+        // A continuation is always an expression, so only a plain line can assign
+        let is_assignment = !used_continuation && {
+            let trimmed = expression.trim();
+            let statement = trimmed.strip_prefix('=').unwrap_or(trimmed);
+            matches!(
+                parse_line_with(statement, &numbers),
+                Ok(Ast::Assignment { .. })
+            )
+        };
+        PreviewResult {
+            value,
+            used_continuation,
+            is_assignment,
+        }
     }
 
     /// Get the sum of all computed values (as plain number)
@@ -292,6 +689,9 @@ impl Engine {
 
     /// Checked variant of [`Engine::sum`].
     pub fn try_sum(&self) -> Result<Decimal, EvalError> {
+        if let Some(sum) = self.running_sum {
+            return Ok(sum);
+        }
         self.lines
             .iter()
             .filter(|lr| !lr.is_continuation_source && !lr.is_aggregate)
@@ -306,7 +706,9 @@ impl Engine {
     /// Get totals grouped by currency and physical dimension.
     /// - Currencies are converted and summed to the last used currency
     /// - Compatible units are converted to the last used unit
-    /// - Plain numbers and percentages are intentionally omitted
+    /// - Plain numbers are omitted unless `EvalOptions::include_plain_totals` is set,
+    ///   which adds their sum last; percentages and prices per unit are always omitted
+    /// - Groups that net to zero are omitted unless `EvalOptions::include_zero_totals` is set
     /// - Excludes lines that were consumed by continuations
     #[must_use]
     pub fn grouped_totals(&self) -> Vec<Value> {
@@ -316,151 +718,38 @@ impl Engine {
 
     /// Checked variant of [`Engine::grouped_totals`].
     pub fn try_grouped_totals(&self) -> Result<Vec<Value>, EvalError> {
-        use std::collections::HashMap;
-
-        let mut currency_amounts: Vec<(Currency, Decimal)> = Vec::new();
-        let mut unit_amounts: HashMap<_, Vec<(Decimal, &types::CompoundUnit)>> = HashMap::new();
-
-        // Collect all values, tracking last used currency/unit
-        // Skip lines that were consumed by continuations
-        for lr in self
+        let values = self
             .lines
             .iter()
             .filter(|lr| !lr.is_continuation_source && !lr.is_aggregate)
-        {
-            match &lr.value {
-                Value::Currency { amount, currency } => {
-                    currency_amounts.push((*currency, *amount));
-                }
-                Value::WithCompoundUnit { amount, unit } => {
-                    unit_amounts
-                        .entry(unit.dimensions)
-                        .or_default()
-                        .push((*amount, unit));
-                }
-                Value::Number(_)
-                | Value::BaseNumber { .. }
-                | Value::Percentage(_)
-                | Value::Empty
-                | Value::Error(_) => {}
-            }
-        }
-
-        let mut result = Vec::new();
-
-        // Sum all currencies, converting to the last used currency
-        // Currencies that can't be converted are kept separate
-        if let Some(&(target_currency, _)) = currency_amounts.last() {
-            let mut total_in_target = Decimal::ZERO;
-            let mut unconverted: HashMap<Currency, Decimal> = HashMap::new();
-
-            for (currency, amount) in &currency_amounts {
-                if *currency == target_currency {
-                    total_in_target =
-                        total_in_target
-                            .checked_add(*amount)
-                            .ok_or(EvalError::Overflow {
-                                operation: "summing currency values",
-                            })?;
-                } else if let Some(rate) = self
-                    .context
-                    .rate_cache
-                    .try_get_rate(*currency, target_currency)?
-                {
-                    let converted = amount.checked_mul(rate).ok_or(EvalError::Overflow {
-                        operation: "converting currency totals",
-                    })?;
-                    total_in_target =
-                        total_in_target
-                            .checked_add(converted)
-                            .ok_or(EvalError::Overflow {
-                                operation: "summing currency values",
-                            })?;
-                } else {
-                    // Can't convert - keep this currency separate instead of corrupting totals
-                    let total = unconverted.entry(*currency).or_default();
-                    *total = total.checked_add(*amount).ok_or(EvalError::Overflow {
-                        operation: "summing unconverted currencies",
-                    })?;
-                }
-            }
-
-            if !total_in_target.is_zero() {
-                result.push(Value::Currency {
-                    amount: total_in_target,
-                    currency: target_currency,
-                });
-            }
-
-            // Add unconverted currencies as separate totals
-            for (currency, amount) in unconverted {
-                if !amount.is_zero() {
-                    result.push(Value::Currency { amount, currency });
-                }
+            .map(|lr| &lr.value);
+        let options = &self.context.options;
+        let mut totals = eval::group_totals(
+            values.clone(),
+            &self.context.rate_cache,
+            options.include_zero_totals,
+        )?;
+        if options.include_plain_totals {
+            let has_plain = values.clone().any(Value::is_number);
+            let plain = eval::plain_total(values)?;
+            if has_plain && (options.include_zero_totals || !plain.is_zero()) {
+                totals.push(Value::Number(plain));
             }
         }
+        Ok(totals)
+    }
 
-        // Convert each value to the last unit before summing. This is required for
-        // affine units such as Celsius and Fahrenheit, whose offsets are not additive.
-        for amounts in unit_amounts.into_values() {
-            let target_unit = amounts
-                .last()
-                .map(|(_, unit)| (*unit).clone())
-                .expect("unit groups are never empty");
-            let total = amounts
-                .into_iter()
-                .try_fold(Decimal::ZERO, |total, (amount, unit)| {
-                    let converted = types::unit::try_convert(amount, unit, &target_unit)?
-                        .ok_or_else(|| {
-                            EvalError::InvalidOperands(format!(
-                                "cannot convert {} to {}",
-                                unit.symbol, target_unit.symbol
-                            ))
-                        })?;
-                    total.checked_add(converted).ok_or(EvalError::Overflow {
-                        operation: "summing unit values",
-                    })
-                })?;
-            if !total.is_zero() {
-                result.push(Value::WithCompoundUnit {
-                    amount: total,
-                    unit: target_unit,
-                });
-            }
-        }
-
-        // Sort results for consistent display order:
-        // 1. Currencies first (by code)
-        // 2. Units by dimensions, then symbol
-        result.sort_by(|a, b| match (a, b) {
-            // Currencies come first
-            (Value::Currency { currency: c1, .. }, Value::Currency { currency: c2, .. }) => {
-                c1.code().cmp(c2.code())
-            }
-            (Value::Currency { .. }, _) => std::cmp::Ordering::Less,
-            (_, Value::Currency { .. }) => std::cmp::Ordering::Greater,
-
-            // Units sorted by dimensions (length, mass, time, temperature, data, angle)
-            (
-                Value::WithCompoundUnit { unit: u1, .. },
-                Value::WithCompoundUnit { unit: u2, .. },
-            ) => {
-                let d1 = &u1.dimensions;
-                let d2 = &u2.dimensions;
-                d1.length
-                    .cmp(&d2.length)
-                    .then(d1.mass.cmp(&d2.mass))
-                    .then(d1.time.cmp(&d2.time))
-                    .then(d1.temperature.cmp(&d2.temperature))
-                    .then(d1.data.cmp(&d2.data))
-                    .then(d1.angle.cmp(&d2.angle))
-                    .then(u1.symbol.cmp(&u2.symbol)) // Final tiebreaker
-            }
-
-            _ => std::cmp::Ordering::Equal,
-        });
-
-        Ok(result)
+    /// Totals split into currencies, unit amounts by dimension, and the sum of
+    /// plain numbers. Grouping and ordering follow [`Engine::grouped_totals`].
+    pub fn totals_breakdown(&self) -> Result<Totals, EvalError> {
+        eval::totals_breakdown(
+            self.lines
+                .iter()
+                .filter(|lr| !lr.is_continuation_source && !lr.is_aggregate)
+                .map(|lr| &lr.value),
+            &self.context.rate_cache,
+            self.context.options.include_zero_totals,
+        )
     }
 
     /// Get all line results
@@ -469,7 +758,10 @@ impl Engine {
         &self.lines
     }
 
-    /// Replace engine state by evaluating a full document.
+    /// Replace engine state by evaluating a full document, one line at a time
+    /// as [`Engine::eval`] would, so variables and continuations (`* 2` on the
+    /// next line) carry over. Prior lines and variables are cleared first; use
+    /// [`Engine::append_lines`] to keep them. `lines` has one entry per input line.
     pub fn evaluate_document(&mut self, content: &str) -> DocumentResult {
         self.clear();
         for line in content.lines() {
@@ -482,6 +774,33 @@ impl Engine {
         }
     }
 
+    /// Results every line would have if line `line_idx` read `new_text`,
+    /// re-evaluating the lines from the top as [`Engine::evaluate_document`]
+    /// does. An index past the end previews `new_text` as a new last line.
+    /// Nothing is committed: lines, variables, and totals are restored afterwards.
+    pub fn preview_edit(&mut self, line_idx: usize, new_text: &str) -> Vec<Value> {
+        let mut inputs: Vec<String> = self.lines.iter().map(|lr| lr.input.clone()).collect();
+        match inputs.get_mut(line_idx) {
+            Some(input) => *input = new_text.to_string(),
+            None => inputs.push(new_text.to_string()),
+        }
+
+        let context = self.context.clone();
+        let lines = std::mem::take(&mut self.lines);
+        let (block_start, running_sum) = (self.block_start, self.running_sum);
+        self.clear();
+        for input in &inputs {
+            self.eval(input);
+        }
+        let values = self.lines.drain(..).map(|lr| lr.value).collect();
+
+        self.context = context;
+        self.lines = lines;
+        self.block_start = block_start;
+        self.running_sum = running_sum;
+        values
+    }
+
     /// Append independent lines without clearing existing state.
     pub fn append_lines<'a>(
         &mut self,
@@ -497,7 +816,28 @@ impl Engine {
     /// Clear all lines and variables
     pub fn clear(&mut self) {
         self.lines.clear();
+        self.block_start = 0;
+        self.running_sum = Some(Decimal::ZERO);
         self.context.clear_variables();
+        self.context.clear_functions();
+    }
+
+    /// Names of user-defined functions, sorted
+    #[must_use]
+    pub fn functions(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.context.functions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Delete one user variable, returning its value if it was set.
+    /// Later lines that reference it fail with an unknown-variable error;
+    /// engine-managed names ('total', '_', 'ANS', 'ans') are left alone.
+    pub fn unset_variable(&mut self, name: &str) -> Option<Value> {
+        if eval::is_engine_variable(name) {
+            return None;
+        }
+        self.context.remove_variable(name)
     }
 
     /// Get all user-defined variables (excludes 'total', '_', 'ANS', and 'ans')
@@ -507,9 +847,7 @@ impl Engine {
             .context
             .variables
             .iter()
-            .filter(|(name, _)| {
-                *name != "total" && *name != "_" && *name != "ANS" && *name != "ans"
-            })
+            .filter(|(name, _)| !eval::is_engine_variable(name))
             .map(|(name, value)| (name.clone(), value.clone()))
             .collect();
         variables.sort_by(|left, right| left.0.cmp(&right.0));
@@ -538,6 +876,33 @@ impl Engine {
         self.context.rate_cache.apply_raw_rates(raw_rates)
     }
 
+    /// Rates skipped by the last `apply_raw_rates` because their currency
+    /// is not supported, by code
+    #[must_use]
+    pub fn unsupported_rates(&self) -> &std::collections::BTreeMap<String, Decimal> {
+        self.context.rate_cache.unsupported_rates()
+    }
+
+    /// Order two values, converting currencies with the loaded exchange rates
+    /// and units to a common unit first; see [`Value::compare_with`]
+    #[must_use]
+    pub fn compare(&self, a: &Value, b: &Value) -> Option<Ordering> {
+        a.compare_with(b, &self.context.rate_cache)
+    }
+
+    /// Loaded exchange rates as `(from, to, rate)`, sorted by currency codes,
+    /// without the reciprocals derived from them
+    #[must_use]
+    pub fn rates_snapshot(&self) -> Vec<(Currency, Currency, Decimal)> {
+        self.context.rate_cache.snapshot()
+    }
+
+    /// Unix time the loaded rates were fetched; `None` for built-in rates
+    #[must_use]
+    pub fn rates_updated_at(&self) -> Option<u64> {
+        self.context.rate_cache.updated_at()
+    }
+
     /// Save rates to file cache (delegates to rate cache)
     pub fn save_rates_to_cache(
         &self,
@@ -546,6 +911,12 @@ impl Engine {
         self.context.rate_cache.save_to_file(raw_rates)
     }
 
+    /// Replace all exchange rates with one frozen set of built-in rates, so
+    /// results do not depend on fetched rates or on newer defaults
+    pub fn use_default_rates(&mut self, set: DefaultRates) {
+        self.context.rate_cache = RateCache::with_default_set(set);
+    }
+
     /// Explicitly load a non-expired filesystem cache over deterministic defaults.
     pub fn load_rates_from_cache(&mut self) -> Result<bool, RateError> {
         self.context.rate_cache.load_from_file()
@@ -578,6 +949,173 @@ mod tests {
         assert_eq!(result.as_f64(), Some(150.0));
     }
 
+    #[test]
+    fn test_augmented_assignment() {
+        let mut engine = Engine::new();
+        engine.eval("subtotal = $100");
+        assert_eq!(engine.eval("subtotal += 50").to_string(), "$150.00");
+        assert_eq!(engine.eval("subtotal -= 30").to_string(), "$120.00");
+        assert_eq!(engine.eval("subtotal *= 2").to_string(), "$240.00");
+        assert_eq!(engine.eval("subtotal /= 4").to_string(), "$60.00");
+        assert_eq!(engine.eval("subtotal").to_string(), "$60.00");
+
+        // Precedence: the whole right-hand side applies to the variable
+        engine.eval("n = 10");
+        assert_eq!(engine.eval("n *= 2 + 3").as_f64(), Some(50.0));
+
+        assert_eq!(
+            engine.eval("missing += 5").to_string(),
+            "Error: unknown variable: missing"
+        );
+        assert!(engine.variables().iter().all(|(name, _)| name != "missing"));
+    }
+
+    #[test]
+    fn test_eval_preview_detailed() {
+        let mut engine = Engine::new();
+        // Without a previous result the line is read on its own
+        let preview = engine.eval_preview_detailed("+ 5");
+        assert!(!preview.used_continuation);
+        assert_eq!(preview.value.as_f64(), Some(5.0));
+
+        engine.eval("100");
+        let preview = engine.eval_preview_detailed("+ 5");
+        assert_eq!(preview.value.as_f64(), Some(105.0));
+        assert!(preview.used_continuation);
+        assert!(!preview.is_assignment);
+
+        let preview = engine.eval_preview_detailed("tax = 15%");
+        assert!(preview.is_assignment);
+        assert!(!preview.used_continuation);
+        assert!(engine.eval_preview_detailed("=x = 4").is_assignment);
+        assert!(!engine.eval_preview_detailed("=-5").used_continuation);
+        assert!(!engine.eval_preview_detailed("2 * 3").is_assignment);
+
+        // Previews never touch engine state
+        assert_eq!(engine.lines().len(), 1);
+        assert!(engine.variables().is_empty());
+        assert_eq!(engine.eval_preview("+ 5").as_f64(), Some(105.0));
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("markup(x) = x * 1.2"), Value::Empty);
+        assert_eq!(engine.eval("markup(50)").as_f64(), Some(60.0));
+        assert_eq!(engine.eval("markup($50)").to_string(), "$60.00");
+
+        // Parameters shadow variables only inside the body
+        engine.eval("x = 10");
+        engine.eval("rate = 2");
+        engine.eval("scale(x) = x * rate");
+        assert_eq!(engine.eval("scale(3) + x").as_f64(), Some(16.0));
+        engine.eval("area(w, h) = w * h");
+        assert_eq!(engine.eval("area(2 m, 3 m)").to_string(), "6 m²");
+        assert_eq!(engine.eval("markup(area(2, 5))").as_f64(), Some(12.0));
+
+        // Redefinition replaces the formula
+        engine.eval("markup(x) = x * 1.5");
+        assert_eq!(engine.eval("markup(10)").as_f64(), Some(15.0));
+
+        // Built-ins are still reached when no user function matches
+        assert_eq!(engine.eval("sqrt(16)").as_f64(), Some(4.0));
+        assert_eq!(
+            engine.eval("sqrt(x) = x").to_string(),
+            "Error: cannot redefine built-in function sqrt"
+        );
+        assert_eq!(
+            engine.eval("f(x, x) = x").to_string(),
+            "Error: parameter x appears twice in f"
+        );
+        assert_eq!(
+            engine.eval("area(1)").to_string(),
+            "Error: area takes 2 arguments, got 1"
+        );
+        assert_eq!(
+            engine.eval("nope(1)").to_string(),
+            "Error: unknown function: nope"
+        );
+
+        engine.eval("forever(n) = forever(n + 1)");
+        let result = engine.eval("forever(1)");
+        assert_eq!(
+            result,
+            Value::Error(EvalError::CallDepth {
+                max: eval::MAX_CALL_DEPTH
+            })
+        );
+
+        assert_eq!(engine.functions(), ["area", "forever", "markup", "scale"]);
+        assert_eq!(
+            engine.sum().as_f64(),
+            Some(60.0 + 60.0 + 16.0 + 6.0 + 12.0 + 15.0 + 4.0 + 10.0 + 2.0)
+        );
+        engine.clear();
+        assert!(engine.functions().is_empty());
+    }
+
+    #[test]
+    fn test_user_function_call_budget() {
+        let mut engine = Engine::new();
+        // A failed left side stops the right side from running, so calling
+        // itself twice per level still stops at the depth limit
+        engine.eval("fan(n) = fan(n) + fan(n)");
+        assert_eq!(
+            engine.eval("fan(1)"),
+            Value::Error(EvalError::CallDepth {
+                max: eval::MAX_CALL_DEPTH
+            })
+        );
+        // Fanning out through shallow calls is bounded by the call budget:
+        // six layers of ten calls each would make a million calls
+        engine.eval("l0(n) = n");
+        for layer in 1..=6 {
+            let calls = vec![format!("l{}(n)", layer - 1); 10].join(", ");
+            engine.eval(&format!("l{layer}(n) = max({calls})"));
+        }
+        assert_eq!(engine.eval("l4(1)").to_string(), "1");
+        assert_eq!(
+            engine.eval("l6(1)"),
+            Value::Error(EvalError::CallBudget {
+                max: eval::MAX_CALLS_PER_LINE
+            })
+        );
+        engine.eval("wide(n) = max(wide(n), wide(n), wide(n))");
+        assert!(engine.eval("wide(1)").is_error());
+
+        // The budget is per line, and repeat shares it across its runs
+        engine.eval("double(n) = n * 2");
+        assert_eq!(engine.eval("double(21)").to_string(), "42");
+        engine.eval("x = 1");
+        assert_eq!(engine.eval("repeat 10: x = double(x)").to_string(), "1024");
+    }
+
+    #[test]
+    fn test_chained_assignment() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("x = y = 100").as_f64(), Some(100.0));
+        assert_eq!(engine.eval("x").as_f64(), Some(100.0));
+        assert_eq!(engine.eval("y").as_f64(), Some(100.0));
+
+        // The right-hand side is evaluated once, before any name is bound
+        assert_eq!(engine.eval("x = y = y + 1").as_f64(), Some(101.0));
+        assert_eq!(engine.eval("x + y").as_f64(), Some(202.0));
+
+        // A failed right-hand side binds nothing
+        assert!(engine.eval("a = b = missing").is_error());
+        assert!(engine
+            .variables()
+            .iter()
+            .all(|(name, _)| name != "a" && name != "b"));
+
+        // Like a single assignment, a chain never continues the previous line,
+        // but `_` on its right-hand side does
+        engine.clear();
+        engine.eval("$50");
+        assert_eq!(engine.eval("p = q = _ * 2").to_string(), "$100.00");
+        assert_eq!(engine.sum().as_f64(), Some(100.0));
+    }
+
     #[test]
     fn test_engine_sum() {
         let mut engine = Engine::new();
@@ -587,6 +1125,83 @@ mod tests {
         assert_eq!(engine.sum().as_f64(), Some(60.0));
     }
 
+    #[test]
+    fn test_engine_format_options() {
+        let mut engine = Engine::new();
+        let result = engine.eval("1234567 * 2");
+        assert_eq!(engine.format(&result), "2469134");
+
+        engine.set_format_options(FormatOptions {
+            grouping: Some(GroupSeparator::Comma),
+            ..FormatOptions::default()
+        });
+        assert_eq!(engine.format(&result), "2,469,134");
+        let price = engine.eval("2 million usd");
+        assert_eq!(engine.format(&price), "$2,000,000.00");
+        // Grouped output is display-only; parsing is unaffected
+        assert_eq!(engine.eval("1,234 + 1").as_decimal(), Some(decimal("1235")));
+    }
+
+    #[test]
+    fn test_engine_options_round_trip() {
+        let mut engine = Engine::new();
+        assert!(!engine.options().lenient_units);
+        assert!(engine.eval("1 kg - 1 h").is_error());
+
+        engine.set_options(EvalOptions {
+            lenient_units: true,
+            ..EvalOptions::default()
+        });
+        assert_eq!(engine.eval("1 kg - 1 h").as_decimal(), Some(decimal("0")));
+        assert_eq!(engine.lines()[1].warnings.len(), 1);
+        assert!(engine.lines()[0].warnings.is_empty());
+    }
+
+    #[test]
+    fn test_max_variables_evicts_least_recently_used() {
+        let mut engine = Engine::new();
+        engine.set_options(EvalOptions {
+            max_variables: Some(2),
+            ..EvalOptions::default()
+        });
+        engine.eval("a = 1");
+        engine.eval("b = 2");
+        engine.eval("a + 1"); // reading `a` makes `b` the least recently used
+        engine.eval("c = 3");
+
+        let names: Vec<String> = engine
+            .variables()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["a", "c"]);
+        assert!(engine.eval("b").is_error());
+
+        // Engine-managed names and constants are never evicted
+        engine.eval("d = total + _");
+        assert!(!engine.lines().last().unwrap().value.is_error());
+        assert!(engine.eval("pi").as_f64().is_some());
+        assert_eq!(engine.eval("_").as_f64(), engine.eval("ans").as_f64());
+        let names: Vec<String> = engine
+            .variables()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["c", "d"]);
+
+        // Lowering the cap applies immediately
+        engine.set_options(EvalOptions {
+            max_variables: Some(1),
+            ..EvalOptions::default()
+        });
+        let names: Vec<String> = engine
+            .variables()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["d"]);
+    }
+
     #[test]
     fn test_grouped_totals() {
         let mut engine = Engine::new();
@@ -625,49 +1240,420 @@ mod tests {
     }
 
     #[test]
-    fn test_grouped_totals_last_currency() {
+    fn test_totals_breakdown() {
         let mut engine = Engine::new();
         engine.set_exchange_rate(
             Currency::USD,
             Currency::EUR,
-            Decimal::from_str("0.92").unwrap(),
+            Decimal::from_str("0.5").unwrap(),
         );
 
-        engine.eval("€100");
-        engine.eval("$50"); // Last is USD, so total in USD
+        engine.eval("$100");
+        engine.eval("$50");
+        engine.eval("€200"); // Currencies total in the last used one
+        engine.eval("1000 m");
+        engine.eval("5 km");
+        engine.eval("2 kg");
+        engine.eval("42");
+        engine.eval("+ 8"); // Consumes the 42 line
 
-        let totals = engine.grouped_totals();
+        let totals = engine.totals_breakdown().unwrap();
+        assert_eq!(totals.currencies, [(Currency::EUR, Decimal::from(275))]);
+        let units: Vec<(Dimensions, &str, Decimal)> = totals
+            .units_by_type
+            .iter()
+            .map(|(dimensions, unit, amount)| (*dimensions, unit.symbol.as_str(), *amount))
+            .collect();
+        assert_eq!(
+            units,
+            [
+                (Dimensions::mass(1), "kg", Decimal::from(2)),
+                (Dimensions::length(1), "km", Decimal::from(6)),
+            ]
+        );
+        assert_eq!(totals.plain, Decimal::from(50));
 
-        // €100 = $108.70 (100 / 0.92) + $50 = $158.70
-        let expected_usd = Decimal::from_str("158.70").unwrap();
-        assert!(totals.iter().any(|v| matches!(v,
-            Value::Currency { amount, currency }
-            if *currency == Currency::USD && (*amount - expected_usd).abs() < Decimal::ONE
-        )));
+        // Same groups, in the same order, as the display totals
+        let displayed: Vec<Value> = totals
+            .currencies
+            .iter()
+            .map(|(currency, amount)| Value::currency(*amount, *currency))
+            .chain(
+                totals
+                    .units_by_type
+                    .iter()
+                    .map(|(_, unit, amount)| Value::with_compound_unit(*amount, unit.clone())),
+            )
+            .collect();
+        assert_eq!(displayed, engine.grouped_totals());
     }
 
     #[test]
-    fn test_continuation_basic() {
+    fn test_totals_breakdown_zero_net() {
         let mut engine = Engine::new();
-        assert_eq!(engine.eval("10").as_f64(), Some(10.0));
-        assert_eq!(engine.eval("+ 5").as_f64(), Some(15.0));
-        assert_eq!(engine.eval("* 2").as_f64(), Some(30.0));
-        assert_eq!(engine.eval("/ 3").as_f64(), Some(10.0));
-        assert_eq!(engine.eval("- 2").as_f64(), Some(8.0));
+        engine.eval("$100");
+        engine.eval("-$100");
+        assert_eq!(engine.totals_breakdown().unwrap(), Totals::default());
+
+        engine.set_options(EvalOptions {
+            include_zero_totals: true,
+            ..EvalOptions::default()
+        });
+        let totals = engine.totals_breakdown().unwrap();
+        assert_eq!(totals.currencies, [(Currency::USD, Decimal::ZERO)]);
     }
 
     #[test]
-    fn test_continuation_with_currency() {
+    fn test_pinned_default_rates() {
         let mut engine = Engine::new();
-        let result = engine.eval("$100");
-        assert!(matches!(result, Value::Currency { .. }));
+        engine.set_exchange_rate(
+            Currency::USD,
+            Currency::EUR,
+            Decimal::from_str("0.5").unwrap(),
+        );
+        engine.use_default_rates(DefaultRates::V1);
 
-        let result = engine.eval("- 10");
-        assert!(matches!(result, Value::Currency { amount, currency }
-            if currency == Currency::USD && (amount - Decimal::from(90)).abs() < Decimal::ONE
-        ));
+        assert_eq!(engine.eval("$100 in eur").to_string(), "€92.00");
+        assert_eq!(engine.eval("0.01 btc in usd").to_string(), "$950.00");
+        assert_eq!(engine.eval("£79 in usd").to_string(), "$100.00");
+    }
 
-        let result = engine.eval("+ $20");
+    #[test]
+    fn test_grouped_totals_plain_numbers() {
+        let mut engine = Engine::new();
+        engine.eval("$100");
+        engine.eval("42");
+        engine.eval("8");
+        engine.eval("15%"); // Percentages never total
+        engine.eval("5 km");
+        let display = |engine: &Engine| -> Vec<String> {
+            engine
+                .grouped_totals()
+                .iter()
+                .map(Value::to_string)
+                .collect()
+        };
+        assert_eq!(display(&engine), ["$100.00", "5 km"]);
+
+        engine.set_options(EvalOptions {
+            include_plain_totals: true,
+            ..EvalOptions::default()
+        });
+        assert_eq!(display(&engine), ["$100.00", "5 km", "50"]);
+
+        // A plain subtotal that nets to zero follows the zero-total option
+        engine.eval("");
+        engine.eval("-50");
+        assert_eq!(display(&engine), ["$100.00", "5 km"]);
+        engine.set_options(EvalOptions {
+            include_plain_totals: true,
+            include_zero_totals: true,
+            ..EvalOptions::default()
+        });
+        assert_eq!(display(&engine), ["$100.00", "5 km", "0"]);
+    }
+
+    #[test]
+    fn test_grouped_totals_zero_net() {
+        let mut engine = Engine::new();
+        engine.eval("$100");
+        engine.eval("-$100");
+        engine.eval("5 km");
+        let totals: Vec<String> = engine
+            .grouped_totals()
+            .iter()
+            .map(Value::to_string)
+            .collect();
+        assert_eq!(totals, ["5 km"]);
+
+        engine.set_options(EvalOptions {
+            include_zero_totals: true,
+            ..EvalOptions::default()
+        });
+        let totals: Vec<String> = engine
+            .grouped_totals()
+            .iter()
+            .map(Value::to_string)
+            .collect();
+        assert_eq!(totals, ["$0.00", "5 km"]);
+    }
+
+    #[test]
+    fn test_evaluate_document_replaces_state() {
+        let mut engine = Engine::new();
+        engine.eval("stale = 1");
+
+        let document = engine.evaluate_document("price = $20\nprice * 3\n+ $4\n\nprice");
+        let shown: Vec<String> = document
+            .lines
+            .iter()
+            .map(|line| line.value.to_string())
+            .collect();
+        assert_eq!(shown, ["$20.00", "$60.00", "$64.00", "", "$20.00"]);
+        assert!(document.lines[1].is_continuation_source);
+        assert!(engine.eval("stale").is_error());
+        assert_eq!(engine.lines().len(), 6);
+    }
+
+    #[test]
+    fn test_running_total_matches_a_recount() {
+        let mut engine = Engine::new();
+        for line in [
+            "100",
+            "* 2",
+            "50",
+            "total",
+            "_ + 1",
+            "",
+            "-5",
+            "$10",
+            "sum(above)",
+        ] {
+            engine.eval(line);
+        }
+        let recount: Decimal = engine
+            .lines()
+            .iter()
+            .filter(|lr| !lr.is_continuation_source && !lr.is_aggregate)
+            .filter_map(|lr| lr.value.as_decimal())
+            .sum();
+        assert_eq!(engine.try_sum(), Ok(recount));
+        assert_eq!(recount, decimal("506"));
+
+        engine.clear();
+        assert_eq!(engine.sum().to_string(), "0");
+    }
+
+    #[test]
+    fn test_total_in_long_documents_is_linear() {
+        // Each line reads `total`; rescanning the document per line would
+        // make this quadratic in the number of lines
+        let mut engine = Engine::new();
+        let start = std::time::Instant::now();
+        for _ in 0..5_000 {
+            engine.eval("1");
+            engine.eval("running = total");
+        }
+        assert_eq!(engine.eval("total").to_string(), engine.sum().to_string());
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_eval_timed_records_the_line() {
+        let mut engine = Engine::new();
+        let (value, elapsed) = engine.eval_timed("6 * 7");
+        assert_eq!(value.to_string(), "42");
+        assert!(elapsed < std::time::Duration::from_secs(5));
+        assert_eq!(engine.lines().len(), 1);
+        assert_eq!(engine.sum().to_string(), "42");
+    }
+
+    #[test]
+    fn test_count_above() {
+        let mut engine = Engine::new();
+        for line in ["3", "0", "$12", "", "4", "# note", "0"] {
+            engine.eval(line);
+        }
+        assert_eq!(engine.eval("count(above)").to_string(), "2");
+        assert_eq!(engine.eval("count_nonzero(above)").to_string(), "1");
+    }
+
+    #[test]
+    fn test_percent_of_running_total() {
+        let mut engine = Engine::new();
+        engine.eval("rent = $1200");
+        engine.eval("food = $400");
+        engine.eval("travel = $400");
+        // Divides by the lines above, not by the lines still to come
+        assert_eq!(engine.eval("pct_of_total(food)").to_string(), "20%");
+        assert_eq!(engine.eval("pct_of_total(rent)").to_string(), "60%");
+        engine.eval("$500");
+        assert_eq!(engine.eval("pct_of_total(rent)").to_string(), "48%");
+        assert!(engine.lines().last().unwrap().is_aggregate);
+
+        assert!(engine.eval("pct_of_total(5%)").is_error());
+        // The value must be in the total's currency or convert into it
+        engine.set_exchange_rate(Currency::EUR, Currency::USD, Decimal::TWO);
+        assert_eq!(engine.eval("pct_of_total(€125)").to_string(), "10%");
+        assert_eq!(
+            engine.eval("pct_of_total(5 km)").to_string(),
+            "Error: Cannot convert km to USD"
+        );
+        assert!(engine.eval("pct_of_total(500)").is_error());
+
+        // Each kind is a share of its own total
+        let mut engine = Engine::new();
+        for line in ["$300", "2 km", "$100", "500 m"] {
+            engine.eval(line);
+        }
+        assert_eq!(engine.eval("pct_of_total($100)").to_string(), "25%");
+        assert_eq!(engine.eval("pct_of_total(250 m)").to_string(), "10%");
+        assert!(engine.eval("pct_of_total(1, 2)").is_error());
+        assert!(Engine::new().eval("pct_of_total(10)").is_error());
+    }
+
+    #[test]
+    fn test_grouped_totals_last_currency() {
+        let mut engine = Engine::new();
+        engine.set_exchange_rate(
+            Currency::USD,
+            Currency::EUR,
+            Decimal::from_str("0.92").unwrap(),
+        );
+
+        engine.eval("€100");
+        engine.eval("$50"); // Last is USD, so total in USD
+
+        let totals = engine.grouped_totals();
+
+        // €100 = $108.70 (100 / 0.92) + $50 = $158.70
+        let expected_usd = Decimal::from_str("158.70").unwrap();
+        assert!(totals.iter().any(|v| matches!(v,
+            Value::Currency { amount, currency }
+            if *currency == Currency::USD && (*amount - expected_usd).abs() < Decimal::ONE
+        )));
+    }
+
+    #[test]
+    fn test_sum_above() {
+        let mut engine = Engine::new();
+        engine.set_exchange_rate(
+            Currency::USD,
+            Currency::EUR,
+            Decimal::from_str("0.5").unwrap(),
+        );
+
+        engine.eval("$1000");
+        engine.eval("€100"); // Converted to the last used currency, like totals
+        engine.eval("$50");
+        engine.eval("20"); // Plain numbers add to the amount
+        assert_eq!(engine.eval("sum(above)").to_string(), "$1270.00");
+        // Earlier aggregates are not counted again
+        assert_eq!(engine.eval("avg(above)").to_string(), "$317.50");
+
+        // A blank line starts a new block
+        engine.eval("");
+        engine.eval("5 km");
+        engine.eval("500 m");
+        assert_eq!(engine.eval("sum(above)").to_string(), "5500 m");
+
+        // Subtotal lines stay out of the document totals, which omit plain numbers
+        let totals = engine.grouped_totals();
+        assert_eq!(totals.len(), 2);
+        assert!(totals.contains(&Value::Currency {
+            amount: Decimal::from(1250),
+            currency: Currency::USD,
+        }));
+    }
+
+    #[test]
+    fn test_word_above_outside_an_aggregate_still_counts() {
+        let mut engine = Engine::new();
+        for line in ["10", "20", "above = 3", "above * 2"] {
+            engine.eval(line);
+        }
+        assert_eq!(engine.eval("total").to_string(), "39");
+        // With a variable of that name, sum(above) is an ordinary call
+        assert!(!engine.lines()[3].is_aggregate);
+
+        let mut engine = Engine::new();
+        engine.eval("$50");
+        engine.eval("$20 same as above");
+        assert!(!engine.lines()[1].is_aggregate);
+        assert_eq!(
+            engine.grouped_totals(),
+            vec![Value::currency(Decimal::from(70), Currency::USD)]
+        );
+        assert!(engine.eval("sum(above)").is_currency());
+        assert!(engine.lines()[2].is_aggregate);
+    }
+
+    #[test]
+    fn test_sum_above_respects_aggregate_limit() {
+        let mut engine = Engine::new();
+        engine.set_options(EvalOptions {
+            max_aggregate_lines: Some(3),
+            ..EvalOptions::default()
+        });
+
+        for amount in 1..=10 {
+            engine.eval(&format!("${amount}"));
+        }
+        // Only the three most recent lines count
+        assert_eq!(engine.eval("sum(above)").to_string(), "$27.00");
+        assert_eq!(engine.eval("avg(above)").to_string(), "$9.00");
+        // Aggregates are skipped before the cap, so they never crowd out lines
+        assert_eq!(engine.eval("max(above)").as_f64(), Some(10.0));
+
+        // A short block is read in full
+        engine.eval("");
+        engine.eval("4");
+        engine.eval("+ 1"); // Consumes the "4" line
+        assert_eq!(engine.eval("sum(above)").to_string(), "5");
+    }
+
+    #[test]
+    fn test_sum_above_in_long_document() {
+        let mut engine = Engine::new();
+        engine.set_options(EvalOptions {
+            max_aggregate_lines: Some(100),
+            ..EvalOptions::default()
+        });
+
+        // A subtotal under every line stays cheap with the cap in place
+        for line in 1..=5000u32 {
+            engine.eval(&line.to_string());
+            let expected: u32 = (line.saturating_sub(99)..=line).sum();
+            assert_eq!(
+                engine.eval("sum(above)").as_f64(),
+                Some(f64::from(expected))
+            );
+        }
+        assert_eq!(engine.lines().len(), 10_000);
+
+        // Clearing starts a fresh block
+        engine.clear();
+        engine.eval("7");
+        assert_eq!(engine.eval("sum(above)").as_f64(), Some(7.0));
+    }
+
+    #[test]
+    fn test_sum_above_rejects_mixed_types() {
+        let mut engine = Engine::new();
+        engine.eval("$10");
+        engine.eval("2 kg");
+        assert_eq!(
+            engine.eval("sum(above)").to_string(),
+            "Error: Cannot add USD and mass"
+        );
+
+        // A variable named "above" shadows the line reference
+        engine.eval("above = 3");
+        assert_eq!(engine.eval("sum(above)").to_string(), "3");
+    }
+
+    #[test]
+    fn test_continuation_basic() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("10").as_f64(), Some(10.0));
+        assert_eq!(engine.eval("+ 5").as_f64(), Some(15.0));
+        assert_eq!(engine.eval("* 2").as_f64(), Some(30.0));
+        assert_eq!(engine.eval("/ 3").as_f64(), Some(10.0));
+        assert_eq!(engine.eval("- 2").as_f64(), Some(8.0));
+    }
+
+    #[test]
+    fn test_continuation_with_currency() {
+        let mut engine = Engine::new();
+        let result = engine.eval("$100");
+        assert!(result.is_currency());
+
+        let result = engine.eval("- 10");
+        assert!(matches!(result, Value::Currency { amount, currency }
+            if currency == Currency::USD && (amount - Decimal::from(90)).abs() < Decimal::ONE
+        ));
+
+        let result = engine.eval("+ $20");
         assert!(matches!(result, Value::Currency { amount, currency }
             if currency == Currency::USD && (amount - Decimal::from(110)).abs() < Decimal::ONE
         ));
@@ -738,6 +1724,480 @@ mod tests {
         assert_eq!(engine.eval("- 5").as_f64(), Some(95.0)); // 100 - 5
     }
 
+    #[test]
+    fn test_currency_symbol_normalization() {
+        let mut engine = Engine::new();
+        // Off by default
+        assert_eq!(engine.eval("US$100").to_string(), "100");
+
+        engine.set_options(EvalOptions {
+            normalize_currency_symbols: true,
+            ..EvalOptions::default()
+        });
+        let cases = [
+            ("US$100", "$100.00"),
+            ("Rs 500", "₹500.00"),
+            ("Rs. 1,250.50", "₹1250.50"),
+            ("500 Rs", "₹500.00"),
+            ("US$5 + US$8", "$13.00"),
+            ("AU$20", "A$20.00"),
+            ("us$3 # paid in US$", "$3.00"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(engine.eval(input).to_string(), expected, "{input}");
+        }
+        // Words that only start with an alias are left alone
+        engine.eval("Rsvp = 4");
+        assert_eq!(engine.eval("Rsvp * 2").to_string(), "8");
+
+        // Callers can map indicators of their own
+        engine.add_currency_alias("eu€", Currency::EUR);
+        assert_eq!(engine.eval("EU€40").to_string(), "€40.00");
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        let mut engine = Engine::new();
+        engine.set_today(Date::from_ymd(2025, 3, 31));
+        assert_eq!(engine.eval("3 days ago").to_string(), "2025-03-28");
+        assert_eq!(engine.eval("2 weeks from now").to_string(), "2025-04-14");
+        assert_eq!(engine.eval("1 month ago").to_string(), "2025-02-28");
+        assert_eq!(engine.eval("1 year from now").to_string(), "2026-03-31");
+        assert_eq!(engine.eval("1 Day Ago").to_string(), "2025-03-30");
+        // Dates are not amounts and stay out of the sum
+        engine.eval("5");
+        assert_eq!(engine.sum().to_string(), "5");
+        // but a date passed to an aggregate is an error, not a dropped argument
+        for input in [
+            "sum(3 days ago, 2)",
+            "max(3 days ago, 1)",
+            "avg(3 days ago, 2 km)",
+        ] {
+            assert!(
+                matches!(
+                    engine.eval_preview(input),
+                    Value::Error(
+                        EvalError::IncompatibleUnits { .. } | EvalError::InvalidArgument(_)
+                    )
+                ),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            engine
+                .eval_preview("max(3 days ago, 1 day ago)")
+                .to_string(),
+            "2025-03-30"
+        );
+        // A plain duration is still a duration
+        assert_eq!(engine.eval("3 days").to_string(), "3 d");
+        // Dates past year 9999 are an overflow, not a panic
+        assert!(matches!(
+            engine.eval("700000000000000000 years from now"),
+            Value::Error(EvalError::Overflow { .. })
+        ));
+        assert!(matches!(engine.eval("8000 years ago"), Value::Date(_)));
+    }
+
+    #[test]
+    fn test_compare_converts_first() {
+        let compare = |engine: &mut Engine, a: &str, b: &str| {
+            let (a, b) = (engine.eval(a), engine.eval(b));
+            engine.compare(&a, &b)
+        };
+        let mut engine = Engine::new();
+        engine.set_exchange_rate(Currency::USD, Currency::EUR, decimal("0.5"));
+        assert_eq!(compare(&mut engine, "$100", "€40"), Some(Ordering::Greater));
+        assert_eq!(compare(&mut engine, "€40", "$100"), Some(Ordering::Less));
+        assert_eq!(compare(&mut engine, "$100", "€50"), Some(Ordering::Equal));
+        assert_eq!(compare(&mut engine, "€50", "$100"), Some(Ordering::Equal));
+        // Raw amounts would say the opposite
+        assert_eq!(compare(&mut engine, "$100", "€60"), Some(Ordering::Less));
+
+        assert_eq!(
+            compare(&mut engine, "1 km", "900 m"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare(&mut engine, "50%", "0.4"), Some(Ordering::Greater));
+        assert_eq!(compare(&mut engine, "$100", "100"), None);
+        assert_eq!(compare(&mut engine, "$100", "1 km"), None);
+        assert_eq!(compare(&mut engine, "$100", "1 BTC"), Some(Ordering::Less));
+
+        // min and max keep the winning value as written
+        assert_eq!(engine.eval("max($100, €60)").to_string(), "€60.00");
+        assert_eq!(engine.eval("min(1 km, 900 m, 2 mi)").to_string(), "900 m");
+        assert_eq!(engine.eval("max(3, 7, 5)").to_string(), "7");
+        // Values that cannot be compared fall back to their bare amounts
+        assert_eq!(engine.eval("max($5, 7)").to_string(), "7");
+    }
+
+    #[test]
+    fn test_preview_edit_leaves_the_document_alone() {
+        let mut engine = Engine::new();
+        for line in ["price = $100", "qty = 3", "price * qty", "* 2"] {
+            engine.eval(line);
+        }
+        let before = engine.lines().to_vec();
+
+        let preview = engine.preview_edit(1, "qty = 5");
+        let shown: Vec<String> = preview.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["$100.00", "5", "$500.00", "$1000.00"]);
+        assert!(engine.preview_edit(0, "price = oops")[2].is_error());
+
+        // Past the end previews a new last line
+        let preview = engine.preview_edit(99, "qty * 10");
+        assert_eq!(preview.len(), 5);
+        assert_eq!(preview[4].to_string(), "30");
+
+        // Nothing was committed
+        assert_eq!(engine.lines(), before.as_slice());
+        assert_eq!(engine.eval("qty").to_string(), "3");
+        assert_eq!(engine.eval("_ + 1").to_string(), "4");
+    }
+
+    #[test]
+    fn test_each_price() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("5 apples at $0.40 each").to_string(), "$2.00");
+        assert_eq!(engine.eval("5 at $0.40 each").to_string(), "$2.00");
+        assert_eq!(engine.eval("3 Tickets At €12 Each").to_string(), "€36.00");
+        assert_eq!(
+            engine
+                .eval("bill = 4 coffees at $3.50 each + $2")
+                .to_string(),
+            "$16.00"
+        );
+        assert_eq!(engine.eval("2 bags at 1.5 kg each").to_string(), "3 kg");
+        // Counts may be written with a multiplier or scale word
+        assert_eq!(engine.eval("2k apples at $1 each").to_string(), "$2000.00");
+        assert_eq!(
+            engine.eval("1.5m units at $2 each").to_string(),
+            "$3000000.00"
+        );
+        assert_eq!(engine.eval("2 thousand at $1 each").to_string(), "$2000.00");
+        // The noun is not looked up as a variable
+        engine.eval("apples = 100");
+        assert_eq!(engine.eval("5 apples at $1 each").to_string(), "$5.00");
+        // The words stay usable as variable names
+        engine.eval("each = 3");
+        assert_eq!(engine.eval("each * 2").to_string(), "6");
+    }
+
+    #[test]
+    fn test_conversion_to_a_variable_unit() {
+        let mut engine = Engine::new();
+        engine.set_exchange_rate(Currency::USD, Currency::EUR, decimal("0.5"));
+        engine.eval("target_unit = 1 m");
+        assert_eq!(engine.eval("5 km in target_unit").to_string(), "5000 m");
+        engine.eval("home = 0 eur");
+        assert_eq!(engine.eval("$10 in home").to_string(), "€5.00");
+        engine.eval("speed = 1 km/h");
+        assert_eq!(engine.eval("10 m/s in speed").to_string(), "36 km/h");
+
+        // Unit and currency names win over variables of the same name
+        engine.eval("km = 1 mi");
+        assert_eq!(engine.eval("1000 m in km").to_string(), "1 km");
+
+        engine.eval("count = 3");
+        assert_eq!(
+            engine.eval("5 km in count").to_string(),
+            "Error: count does not hold a unit or currency to convert to"
+        );
+        assert_eq!(
+            engine.eval("5 km in nowhere").to_string(),
+            "Error: unknown target unit: nowhere"
+        );
+    }
+
+    #[test]
+    fn test_ratio_in_percent() {
+        let mut engine = Engine::new();
+        engine.eval("liquid = $15910");
+        engine.eval("expenses = $1000");
+        assert_eq!(engine.eval("liquid / expenses").to_string(), "15.91");
+        assert_eq!(engine.eval("liquid / expenses in %").to_string(), "1591%");
+        assert_eq!(engine.eval("0.25 to %").to_string(), "25%");
+        assert_eq!(engine.eval("1 in %").to_string(), "100%");
+        assert_eq!(engine.eval("20% in %").to_string(), "20%");
+
+        // Only dimensionless numbers are ratios
+        assert_eq!(
+            engine.eval("liquid in %").to_string(),
+            "Error: only a plain number can be shown as a percentage"
+        );
+        assert!(engine.eval("5 km in %").is_error());
+    }
+
+    #[test]
+    fn test_x_multiplication() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("2x3").to_string(), "6");
+        assert_eq!(engine.eval("2 x 3").to_string(), "6");
+        assert_eq!(engine.eval("4 x $3").to_string(), "$12.00");
+        assert_eq!(engine.eval("2 x (1 + 2)").to_string(), "6");
+
+        // Standing alone, x is still a variable
+        assert_eq!(engine.eval("x = 5").to_string(), "5");
+        assert_eq!(engine.eval("x * 2").to_string(), "10");
+        assert_eq!(engine.eval("3 x").to_string(), "15");
+        assert_eq!(engine.eval("2 x x").to_string(), "10");
+        assert_eq!(engine.eval("2 x^2").to_string(), "50");
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval_preview("6 × 7").to_string(), "42");
+        assert_eq!(engine.eval_preview("10 ÷ 2").to_string(), "5");
+        assert_eq!(engine.eval_preview("10 − 3").to_string(), "7");
+        assert_eq!(engine.eval_preview("−5 + 2").to_string(), "-3");
+        assert_eq!(engine.eval_preview("2 × −3").to_string(), "-6");
+        assert_eq!(engine.eval_preview("$20 − $5").to_string(), "$15.00");
+
+        // A leading minus sign continues the previous value like "-"
+        engine.eval("100");
+        assert_eq!(engine.eval("− 5").to_string(), "95");
+    }
+
+    #[test]
+    fn test_european_number_locale() {
+        let mut engine = Engine::new();
+        // US style by default
+        assert_eq!(engine.eval_preview("1,234.56").to_string(), "1234.56");
+        assert_eq!(engine.eval_preview("1.234").to_string(), "1.23");
+
+        engine.set_options(EvalOptions {
+            number_locale: NumberLocale::European,
+            ..EvalOptions::default()
+        });
+        assert_eq!(engine.eval_preview("1.234,56").to_string(), "1234.56");
+        assert_eq!(engine.eval_preview("1.234.567").to_string(), "1234567");
+        assert_eq!(engine.eval_preview("2,5 * 2").to_string(), "5");
+        assert_eq!(engine.eval_preview("1.234,50 EUR").to_string(), "€1234.50");
+        assert_eq!(engine.eval_preview("12,5% of 200").to_string(), "25");
+        // Not grouped in threes: still a decimal point
+        assert_eq!(engine.eval_preview("1.5 + 1").to_string(), "2.50");
+        assert_eq!(engine.eval_preview("max(1, 5)").to_string(), "5");
+        // Commas in a call's arguments separate them, spaced or not
+        assert_eq!(engine.eval_preview("max(1,5)").to_string(), "5");
+        assert_eq!(engine.eval_preview("sum(1,5, 2)").to_string(), "8");
+        assert_eq!(engine.eval_preview("max(1.234,5)").to_string(), "1234");
+        assert_eq!(engine.eval_preview("(1,5 + 1) * 2").to_string(), "5");
+        assert_eq!(engine.eval_preview("7 # 1.234,5").to_string(), "7");
+        assert_eq!(engine.eval_preview("0x1F").to_string(), "31");
+        // Errors point into the line as written
+        let Value::Error(EvalError::Parse(error)) = engine.eval_preview("1.234.567 * )") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.offset(), Some(12));
+    }
+
+    #[test]
+    fn test_yen_symbol_preference() {
+        let currency_of = |engine: &mut Engine, input: &str| {
+            engine.eval_preview(input).as_currency().map(|(_, c)| c)
+        };
+        let mut engine = Engine::new();
+        assert_eq!(currency_of(&mut engine, "¥100"), Some(Currency::JPY));
+        assert_eq!(currency_of(&mut engine, "CN¥100"), Some(Currency::CNY));
+
+        engine.set_options(EvalOptions {
+            yen_symbol: YenSymbol::Cny,
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            engine.eval_preview("¥100").as_currency(),
+            Some((Decimal::from(100), Currency::CNY))
+        );
+        assert_eq!(currency_of(&mut engine, "100 ¥ + ¥5"), Some(Currency::CNY));
+        assert_eq!(currency_of(&mut engine, "¥1.5k"), Some(Currency::CNY));
+        // Spelled-out yen still means yen
+        assert_eq!(currency_of(&mut engine, "JP¥100"), Some(Currency::JPY));
+        assert_eq!(currency_of(&mut engine, "100 JPY"), Some(Currency::JPY));
+        assert_eq!(engine.eval_preview("5 # ¥").to_string(), "5");
+
+        // A bare ¥ conversion target follows the same preference
+        engine.set_exchange_rate(
+            Currency::JPY,
+            Currency::CNY,
+            Decimal::from_str("0.05").unwrap(),
+        );
+        assert_eq!(
+            currency_of(&mut engine, "100 CNY in ¥"),
+            Some(Currency::CNY)
+        );
+        assert_eq!(
+            currency_of(&mut engine, "100 JPY in ¥"),
+            Some(Currency::CNY)
+        );
+        assert_eq!(
+            currency_of(&mut engine, "100 CNY in JP¥"),
+            Some(Currency::JPY)
+        );
+        assert_eq!(engine.eval_preview("100 JPY in ¥").to_string(), "¥5.00");
+    }
+
+    #[test]
+    fn test_custom_number_parser() {
+        fn accounting(word: &str) -> Option<Decimal> {
+            if let Some(amount) = word.strip_prefix('(').and_then(|w| w.strip_suffix(')')) {
+                return Decimal::from_str(amount).ok().map(|amount| -amount);
+            }
+            word.strip_suffix("CR")
+                .or_else(|| word.strip_suffix("DR"))
+                .and_then(|amount| Decimal::from_str(amount).ok())
+        }
+
+        let mut engine = Engine::new();
+        engine.set_number_parser(accounting);
+
+        assert_eq!(engine.eval_preview("(100)").as_f64(), Some(-100.0));
+        assert_eq!(engine.eval_preview("100CR").as_f64(), Some(100.0));
+        assert_eq!(engine.eval_preview("250DR + (100)").as_f64(), Some(150.0));
+        assert_eq!(engine.eval_preview("(1 + 2) * 2").as_f64(), Some(6.0));
+        assert_eq!(engine.eval_preview("1,234 + 1").as_f64(), Some(1235.0));
+        assert_eq!(engine.eval_preview("5 # (100)").as_f64(), Some(5.0));
+
+        // Literals are found inside calls and before operators
+        assert_eq!(
+            engine.eval_preview("sum((100), (200))").as_f64(),
+            Some(-300.0)
+        );
+        assert_eq!(engine.eval_preview("(100)+5").as_f64(), Some(-95.0));
+        // Errors point into the line as written
+        let Value::Error(EvalError::Parse(error)) = engine.eval_preview("100CR * )") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.offset(), Some(8));
+
+        // A negative amount below a value starts a new value, not a subtraction
+        engine.eval("100");
+        assert_eq!(engine.eval("(30)").as_f64(), Some(-30.0));
+        assert_eq!(engine.sum().as_f64(), Some(70.0));
+        assert_eq!(engine.lines()[1].input, "(30)");
+
+        engine.clear_number_parser();
+        assert_eq!(engine.eval_preview("(100)").as_f64(), Some(100.0));
+    }
+
+    #[test]
+    fn test_unset_variable() {
+        let mut engine = Engine::new();
+        engine.eval("tax = 15%");
+        engine.eval("rate = 2");
+        engine.eval("100");
+
+        assert_eq!(
+            engine.unset_variable("tax"),
+            Some(Value::Percentage(decimal("0.15")))
+        );
+        assert_eq!(engine.unset_variable("tax"), None);
+        assert_eq!(engine.unset_variable("_"), None);
+        assert_eq!(
+            engine.eval("100 + tax").to_string(),
+            "Error: unknown variable: tax"
+        );
+        assert_eq!(engine.eval("_ * rate").as_f64(), Some(200.0));
+
+        let names: Vec<_> = engine
+            .variables()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["rate"]);
+
+        engine.eval("tax = 20%");
+        assert_eq!(engine.eval("100 + tax").as_f64(), Some(120.0));
+    }
+
+    #[test]
+    fn test_format_line_with_conversion_source() {
+        let mut engine = Engine::new();
+        engine.eval("5 km in m");
+        engine.eval("10 kg");
+        engine.eval("in g");
+        engine.eval("1 km in m in cm");
+        engine.eval("5 km + 1 km");
+        engine.eval("distance = 2 km in m");
+        engine.eval("5 kg in m");
+
+        let lines = engine.lines();
+        assert_eq!(engine.format_line(&lines[0], false), "5000 m");
+        assert_eq!(engine.format_line(&lines[0], true), "5 km → 5000 m");
+        assert_eq!(engine.format_line(&lines[2], true), "10 kg → 10000 g");
+        assert_eq!(engine.format_line(&lines[3], true), "1 km → 100000 cm");
+        assert_eq!(engine.format_line(&lines[4], true), "6 km");
+        assert_eq!(engine.format_line(&lines[5], true), "2 km → 2000 m");
+        assert!(lines[6].value.is_error());
+        assert_eq!(lines[6].converted_from, None);
+    }
+
+    #[test]
+    fn test_normalized_units_keep_an_explicit_target() {
+        let mut engine = Engine::new();
+        engine.set_format_options(FormatOptions {
+            normalize_units: true,
+            ..FormatOptions::default()
+        });
+        engine.eval("1 km in m");
+        engine.eval("5000 g in g");
+        engine.eval("1 GB in MB");
+        engine.eval("5000 g");
+
+        let lines = engine.lines();
+        assert_eq!(engine.format_line(&lines[0], false), "1000 m");
+        assert_eq!(engine.format_line(&lines[1], false), "5000 g");
+        assert_eq!(engine.format_line(&lines[1], true), "5000 g → 5000 g");
+        assert_eq!(engine.format_line(&lines[2], false), "1024 MB");
+        // Without a target the result is still normalized
+        assert_eq!(engine.format_line(&lines[3], false), "5 kg");
+    }
+
+    #[test]
+    fn test_leading_equals_skips_continuation() {
+        let mut engine = Engine::new();
+        engine.eval("100");
+        assert_eq!(engine.eval_preview("-5").as_f64(), Some(95.0));
+        assert_eq!(engine.eval_preview("=-5").as_f64(), Some(-5.0));
+        assert_eq!(engine.eval_preview("= -5").as_f64(), Some(-5.0));
+
+        assert_eq!(engine.eval("=-5").as_f64(), Some(-5.0));
+        // The explicit line did not consume 100, so both count toward the total
+        assert_eq!(engine.sum().as_f64(), Some(95.0));
+
+        // `_` and assignments still work after the "="
+        assert_eq!(engine.eval("= _ * 3").as_f64(), Some(-15.0));
+        assert_eq!(engine.eval("=x = 4").as_f64(), Some(4.0));
+        assert_eq!(engine.eval("x").as_f64(), Some(4.0));
+    }
+
+    #[test]
+    fn test_negative_literal_after_blank_line() {
+        let mut engine = Engine::new();
+        engine.eval("100");
+        engine.eval("");
+        assert_eq!(engine.eval("-5").as_f64(), Some(-5.0));
+        // The new value is what later lines continue, and nothing was consumed
+        assert_eq!(engine.eval("* 2").as_f64(), Some(-10.0));
+        assert_eq!(engine.sum().as_f64(), Some(90.0));
+
+        // A comment after the blank line does not rejoin the block
+        engine.clear();
+        engine.eval("10 kg");
+        engine.eval("");
+        engine.eval("# returned");
+        assert_eq!(engine.eval("-2 kg").to_string(), "-2 kg");
+        assert_eq!(engine.eval_preview("-1 kg").to_string(), "-3 kg");
+
+        // A spaced operator still continues across the blank line
+        engine.clear();
+        engine.eval("100");
+        engine.eval("");
+        assert_eq!(engine.eval("- 5").as_f64(), Some(95.0));
+        engine.eval("");
+        assert_eq!(engine.eval_preview("-5").as_f64(), Some(-5.0));
+    }
+
     #[test]
     fn test_continuation_skips_empty() {
         let mut engine = Engine::new();
@@ -747,6 +2207,146 @@ mod tests {
         assert_eq!(engine.eval("+ 50").as_f64(), Some(150.0));
     }
 
+    #[test]
+    fn test_depth_and_exponent_limits() {
+        let nested = |levels| format!("{}1{}", "(".repeat(levels), " + 1)".repeat(levels));
+        let mut engine = Engine::new();
+        // The parser's own nesting limit always applies
+        assert!(matches!(
+            engine.eval(&nested(200)),
+            Value::Error(EvalError::Parse(ParseError::TooDeep { .. }))
+        ));
+        assert_eq!(engine.eval(&nested(20)).to_string(), "21");
+        assert!(engine.eval("2 ^ 10000000").to_string().contains("overflow"));
+
+        engine.set_options(EvalOptions {
+            max_depth: Some(10),
+            max_exponent: Some(1000),
+            ..EvalOptions::default()
+        });
+        assert!(matches!(
+            engine.eval(&nested(20)),
+            Value::Error(EvalError::Parse(ParseError::TooDeep { max: 10, .. }))
+        ));
+        assert_eq!(engine.eval(&nested(5)).to_string(), "6");
+        assert_eq!(
+            engine.eval("2 ^ 10000000").to_string(),
+            "Error: exponent 10000000 is larger than the limit of 1000"
+        );
+        assert!(engine.eval("2 ^ -10000000").is_error());
+        assert_eq!(engine.eval("2 ^ 10").to_string(), "1024");
+    }
+
+    #[test]
+    fn test_sandboxed_eval() {
+        let mut engine = Engine::new();
+        engine.eval("rate = 5%");
+        engine.eval("double(x) = x * 2");
+
+        assert_eq!(engine.eval_sandboxed("(2 + 3) * 4").as_f64(), Some(20.0));
+        assert_eq!(engine.eval_sandboxed("$100 + rate").to_string(), "$105.00");
+        assert_eq!(engine.eval_sandboxed("sqrt(16)").as_f64(), Some(4.0));
+
+        for (input, refused) in [
+            ("rate = 10%", "assignment"),
+            ("rate += 1%", "assignment"),
+            ("triple(x) = x * 3", "function definition"),
+            ("repeat 3: 1 + 1", "repeat"),
+            ("double(4)", "calling a user function"),
+        ] {
+            assert_eq!(
+                engine.eval_sandboxed(input),
+                Value::Error(EvalError::NotAllowed(refused)),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            engine.eval_sandboxed("x = 1").to_string(),
+            "Error: assignment is not allowed in sandbox"
+        );
+
+        // The engine's state is unchanged
+        assert_eq!(engine.eval("rate").to_string(), "5%");
+        assert_eq!(engine.functions(), ["double"]);
+        assert_eq!(engine.lines().len(), 3);
+    }
+
+    #[test]
+    fn test_percentage_change_metadata() {
+        let mut engine = Engine::new();
+        let change = |engine: &Engine| {
+            engine
+                .lines()
+                .last()
+                .and_then(|line| line.percentage_change.as_ref())
+                .map(ToString::to_string)
+        };
+
+        assert_eq!(engine.eval("100 + 20%").as_f64(), Some(120.0));
+        assert_eq!(change(&engine).as_deref(), Some("20"));
+        engine.eval("$50 - 20%");
+        assert_eq!(change(&engine).as_deref(), Some("$-10.00"));
+        engine.eval("price = 5 kg + 10%");
+        assert_eq!(change(&engine).as_deref(), Some("0.50 kg"));
+        // Continuations count too
+        engine.eval("+ 10%");
+        assert_eq!(change(&engine).as_deref(), Some("0.55 kg"));
+
+        // Only when the percentage step produced the line's result
+        engine.eval("");
+        for input in ["20%", "100 * 20%", "(100 + 20%) * 2", "100 + 20% + 5", "7"] {
+            engine.eval(input);
+            assert_eq!(change(&engine), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_repeat_runs_statement() {
+        let mut engine = Engine::new();
+        engine.eval("balance = 1000");
+        let result = engine.eval("repeat 12: balance = balance * 1.01 - 50");
+        let expected = (0..12).fold(1000.0_f64, |b, _| b * 1.01 - 50.0);
+        assert!((result.as_f64().unwrap() - expected).abs() < 1e-9);
+        assert_eq!(engine.eval("balance").as_f64(), result.as_f64());
+
+        assert_eq!(engine.eval("repeat 0: balance = 0").to_string(), "");
+        // An error stops the loop and is the line's result
+        engine.eval("n = 2");
+        assert!(engine.eval("repeat 5: n = 1 / (n - 1)").is_error());
+        assert_eq!(engine.eval("n").as_f64(), Some(1.0));
+    }
+
+    #[test]
+    fn test_days_until_date() {
+        let mut engine = Engine::new();
+        engine.set_today(Date::from_ymd(2024, 12, 18));
+        assert_eq!(engine.eval("days until 2025-01-01").to_string(), "14 d");
+        assert_eq!(engine.eval("weeks until 2025-01-01").to_string(), "2 wk");
+        assert_eq!(engine.eval("days until 2024-12-18").to_string(), "0 d");
+        // Dates in the past count backwards
+        assert_eq!(engine.eval("days until 2024-12-01").to_string(), "-17 d");
+        // The result is an ordinary duration
+        assert_eq!(
+            engine.eval("(days until 2025-01-01) in hours").to_string(),
+            "336 h"
+        );
+    }
+
+    #[test]
+    fn test_trailing_comments() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("100 + 50 # groceries").as_f64(), Some(150.0));
+        assert_eq!(engine.eval("rent = 1000 // monthly").as_f64(), Some(1000.0));
+        assert_eq!(engine.eval("+ 10 # deposit").as_f64(), Some(1010.0));
+
+        // Words in a comment are not read as line references
+        engine.clear();
+        engine.eval("20");
+        assert_eq!(engine.eval("30 # not counted above").as_f64(), Some(30.0));
+        assert_eq!(engine.eval("5 # ans").as_f64(), Some(5.0));
+        assert_eq!(engine.sum().as_f64(), Some(55.0));
+    }
+
     #[test]
     fn test_empty_line_returns_empty() {
         let mut engine = Engine::new();
@@ -773,6 +2373,18 @@ mod tests {
         assert_eq!(engine.eval("^ 10").as_f64(), Some(1024.0));
     }
 
+    #[test]
+    fn test_continuation_percentage_of_whole() {
+        let mut engine = Engine::new();
+        engine.eval("$200");
+        let result = engine.eval("as % of 1000");
+        assert_eq!(result, Value::Percentage(decimal("0.2")));
+        assert!(engine.lines()[0].is_continuation_source);
+
+        engine.eval("5 km");
+        assert_eq!(engine.eval("as % of 20 km").to_string(), "25%");
+    }
+
     #[test]
     fn test_continuation_totals_not_double_counted() {
         let mut engine = Engine::new();
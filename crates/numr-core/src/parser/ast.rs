@@ -1,16 +1,54 @@
 //! Abstract Syntax Tree definitions
 
-use crate::types::{unit, CompoundUnit, Currency};
+use crate::catalog;
+use crate::types::{unit, CompoundUnit, Currency, Date};
 use pest::iterators::Pairs;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use rust_decimal::Decimal;
 use std::str::FromStr;
 
-use super::Rule;
+use super::{NumberSyntax, Rule, MAX_REPEAT_COUNT};
 
 /// Parse a number string, stripping comma/space separators (e.g., "1,234" or "75 000" -> 75000)
-fn parse_number_str(s: &str) -> Result<Decimal, String> {
-    let cleaned = s.replace([',', ' '], "");
-    Decimal::from_str(&cleaned).map_err(|e| format!("{e}"))
+/// and applying a trailing scale word ("2 million" -> 2000000). A custom number
+/// format gets the first look at the literal.
+fn parse_number_str(s: &str, numbers: &NumberSyntax<'_>) -> Result<Decimal, String> {
+    if let Some(value) = numbers.custom_value(s) {
+        return Ok(value);
+    }
+    let (digits, exponent) = match s.trim_end().rsplit_once([' ', '\t']) {
+        Some((digits, word)) => match catalog::scale_word_exponent(word) {
+            Some(exponent) => (digits, exponent),
+            None => (s, 0),
+        },
+        None => (s, 0),
+    };
+    let cleaned = digits.replace([',', ' ', '\t'], "").replace('−', "-");
+    let value = Decimal::from_str(&cleaned).map_err(|e| format!("{e}"))?;
+    if exponent == 0 {
+        return Ok(value);
+    }
+    10i64
+        .checked_pow(exponent)
+        .and_then(|scale| value.checked_mul(Decimal::from(scale)))
+        .ok_or_else(|| format!("Number too large: {s}"))
+}
+
+/// Parse a hexadecimal or binary literal: "0xFF" -> 255, "-0b101" -> -5
+fn parse_base_literal(s: &str) -> Result<Decimal, String> {
+    let (negative, literal) = match s.strip_prefix(['-', '−']) {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (radix, digits) = match &literal[..2] {
+        "0x" | "0X" => (16, &literal[2..]),
+        _ => (2, &literal[2..]),
+    };
+    let magnitude = u128::from_str_radix(digits, radix)
+        .ok()
+        .and_then(Decimal::from_u128)
+        .ok_or_else(|| format!("Number too large: {s}"))?;
+    Ok(if negative { -magnitude } else { magnitude })
 }
 
 /// Top-level AST node for a line
@@ -18,10 +56,19 @@ fn parse_number_str(s: &str) -> Result<Decimal, String> {
 pub enum Ast {
     /// Empty line
     Empty,
-    /// Variable assignment: name = expr
-    Assignment { name: String, expr: Box<Expr> },
+    /// Variable assignment: name = expr, or a chain such as x = y = expr
+    /// binding every name (in written order) to the one value
+    Assignment { names: Vec<String>, expr: Box<Expr> },
+    /// User function definition: name(params) = body
+    FunctionDefinition {
+        name: String,
+        params: Vec<String>,
+        body: Box<Expr>,
+    },
     /// Expression to evaluate
     Expression(Expr),
+    /// Statement run `count` times in a row: repeat 12: balance = balance * 1.01
+    Repeat { count: usize, body: Box<Ast> },
 }
 
 /// Expression node
@@ -48,6 +95,8 @@ pub enum Expr {
         percentage: Decimal,
         value: Box<Expr>,
     },
+    /// Share of a whole as a percentage: $200 as % of $1000
+    PercentageOfWhole { part: Box<Expr>, whole: Box<Expr> },
     /// Unit/currency conversion: 100$ in EUR
     Conversion {
         value: Box<Expr>,
@@ -55,6 +104,45 @@ pub enum Expr {
     },
     /// Function call: sum(), avg()
     FunctionCall { name: String, args: Vec<Expr> },
+    /// Time from today until a date, in days or weeks: days until 2025-01-01
+    TimeUntil { unit: CompoundUnit, date: Date },
+    /// A date counted from today, negative for the past: 3 days ago
+    RelativeDate { days: i64, months: i64 },
+}
+
+impl Ast {
+    /// Depth of the deepest expression in the statement; 0 for an empty line
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        match self {
+            Ast::Empty => 0,
+            Ast::Assignment { expr, .. } => expr.depth(),
+            Ast::FunctionDefinition { body, .. } => body.depth(),
+            Ast::Expression(expr) => expr.depth(),
+            Ast::Repeat { body, .. } => body.depth(),
+        }
+    }
+}
+
+impl Expr {
+    /// Levels of nesting: 1 for a literal, `2 + 3` is 2, `(2 + 3) * 4` is 3
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        let inner = match self {
+            Expr::BinaryOp { left, right, .. } => left.depth().max(right.depth()),
+            Expr::PercentageOfWhole { part, whole } => part.depth().max(whole.depth()),
+            Expr::PercentageOf { value, .. } | Expr::Conversion { value, .. } => value.depth(),
+            Expr::FunctionCall { args, .. } => args.iter().map(Expr::depth).max().unwrap_or(0),
+            Expr::Number(_)
+            | Expr::Percentage(_)
+            | Expr::Currency { .. }
+            | Expr::WithCompoundUnit { .. }
+            | Expr::Variable(_)
+            | Expr::TimeUntil { .. }
+            | Expr::RelativeDate { .. } => 0,
+        };
+        inner + 1
+    }
 }
 
 /// Binary operators
@@ -66,10 +154,27 @@ pub enum BinaryOp {
     Divide,
     Power,
     Conversion,
+    AsPercentOf,
+    ShiftLeft,
+    ShiftRight,
+    BitAnd,
+    BitXor,
+    BitOr,
+}
+
+impl BinaryOp {
+    /// Operators that work on the bits of non-negative integers
+    #[must_use]
+    pub fn is_bitwise(self) -> bool {
+        matches!(
+            self,
+            Self::ShiftLeft | Self::ShiftRight | Self::BitAnd | Self::BitXor | Self::BitOr
+        )
+    }
 }
 
 /// Build AST from parsed pairs
-pub fn build_ast(pairs: Pairs<'_, Rule>) -> Result<Ast, String> {
+pub fn build_ast(pairs: Pairs<'_, Rule>, numbers: &NumberSyntax<'_>) -> Result<Ast, String> {
     for pair in pairs {
         if pair.as_rule() == Rule::line {
             let inner = pair.into_inner();
@@ -80,10 +185,17 @@ pub fn build_ast(pairs: Pairs<'_, Rule>) -> Result<Ast, String> {
             for inner_pair in inner {
                 match inner_pair.as_rule() {
                     Rule::assignment => {
-                        assignment = Some(build_assignment(inner_pair.into_inner())?);
+                        assignment = Some(build_assignment(inner_pair.into_inner(), numbers)?);
+                    }
+                    Rule::function_definition => {
+                        assignment =
+                            Some(build_function_definition(inner_pair.into_inner(), numbers)?);
+                    }
+                    Rule::repeat_statement => {
+                        assignment = Some(build_repeat(inner_pair.into_inner(), numbers)?);
                     }
                     Rule::expression => {
-                        expression = Some(build_expression(inner_pair.into_inner())?);
+                        expression = Some(build_expression(inner_pair.into_inner(), numbers)?);
                     }
                     Rule::trailing_text => {
                         has_trailing = true;
@@ -113,47 +225,186 @@ pub fn build_ast(pairs: Pairs<'_, Rule>) -> Result<Ast, String> {
     Ok(Ast::Empty)
 }
 
-fn build_assignment(mut pairs: pest::iterators::Pairs<'_, Rule>) -> Result<Ast, String> {
-    let name = pairs
-        .next()
-        .ok_or("Expected identifier")?
-        .as_str()
-        .to_string();
-
-    let expr_pair = pairs.next().ok_or("Expected expression")?;
-    let expr = build_expression(expr_pair.into_inner())?;
+fn build_assignment(
+    pairs: pest::iterators::Pairs<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Ast, String> {
+    let mut pairs = pairs.peekable();
+    let mut names = Vec::new();
+    while let Some(pair) = pairs.next_if(|pair| pair.as_rule() == Rule::identifier) {
+        names.push(pair.as_str().to_string());
+    }
+    let name = names.last().ok_or("Expected identifier")?.clone();
+
+    let mut expr_pair = pairs.next().ok_or("Expected expression")?;
+    // "x += 5" is sugar for "x = x + 5"; an undefined x fails as an unknown variable
+    let augmented = if expr_pair.as_rule() == Rule::augmented_assign {
+        let op = match expr_pair.as_str() {
+            "+=" => BinaryOp::Add,
+            "-=" => BinaryOp::Subtract,
+            "*=" => BinaryOp::Multiply,
+            _ => BinaryOp::Divide,
+        };
+        expr_pair = pairs.next().ok_or("Expected expression")?;
+        Some(op)
+    } else {
+        None
+    };
+    let mut expr = build_expression(expr_pair.into_inner(), numbers)?;
+    if let Some(op) = augmented {
+        expr = Expr::BinaryOp {
+            op,
+            left: Box::new(Expr::Variable(name.clone())),
+            right: Box::new(expr),
+        };
+    }
 
     Ok(Ast::Assignment {
-        name,
+        names,
         expr: Box::new(expr),
     })
 }
 
-fn build_expression(pairs: pest::iterators::Pairs<'_, Rule>) -> Result<Expr, String> {
+fn build_repeat(
+    pairs: pest::iterators::Pairs<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Ast, String> {
+    let mut count = None;
+    let mut body = None;
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::number => count = Some(parse_number_str(pair.as_str(), numbers)?),
+            Rule::assignment => body = Some(build_assignment(pair.into_inner(), numbers)?),
+            Rule::expression => {
+                body = Some(Ast::Expression(build_expression(
+                    pair.into_inner(),
+                    numbers,
+                )?))
+            }
+            _ => {}
+        }
+    }
+    let count = count
+        .filter(|count| count.fract().is_zero())
+        .and_then(|count| count.to_usize())
+        .filter(|count| *count <= MAX_REPEAT_COUNT)
+        .ok_or_else(|| {
+            format!("repeat count must be a whole number from 0 to {MAX_REPEAT_COUNT}")
+        })?;
+
+    Ok(Ast::Repeat {
+        count,
+        body: Box::new(body.ok_or("Expected statement to repeat")?),
+    })
+}
+
+fn build_function_definition(
+    pairs: pest::iterators::Pairs<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Ast, String> {
+    let mut names = Vec::new();
+    let mut body = None;
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::identifier => names.push(pair.as_str().to_string()),
+            Rule::expression => body = Some(build_expression(pair.into_inner(), numbers)?),
+            _ => {}
+        }
+    }
+    if names.is_empty() {
+        return Err("Expected function name".to_string());
+    }
+    let name = names.remove(0);
+
+    Ok(Ast::FunctionDefinition {
+        name,
+        params: names,
+        body: Box::new(body.ok_or("Expected function body")?),
+    })
+}
+
+fn build_expression(
+    pairs: pest::iterators::Pairs<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
     let mut calculation_expr = None;
 
     for pair in pairs {
         if pair.as_rule() == Rule::calculation {
-            calculation_expr = Some(build_calculation(pair.into_inner())?);
+            calculation_expr = Some(build_calculation(pair.into_inner(), numbers)?);
         }
     }
 
     calculation_expr.ok_or("Expected calculation".to_string())
 }
 
-fn build_calculation(pairs: pest::iterators::Pairs<'_, Rule>) -> Result<Expr, String> {
+fn build_calculation(
+    pairs: pest::iterators::Pairs<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
     let mut terms: Vec<Expr> = Vec::new();
     let mut ops: Vec<BinaryOp> = Vec::new();
+    // Set after "per", whose bare unit word means one of that unit
+    let mut per_unit = false;
+    // Indices of bare unit words after "/" or "per" ("$30/h", "$20 per kg")
+    let mut unit_divisors: Vec<usize> = Vec::new();
+    // Unit readings of suffixed numbers such as "1T", by term index
+    let mut unit_readings: Vec<(usize, Expr)> = Vec::new();
 
     for pair in pairs {
         match pair.as_rule() {
             Rule::add => ops.push(BinaryOp::Add),
             Rule::subtract => ops.push(BinaryOp::Subtract),
             Rule::multiply => ops.push(BinaryOp::Multiply),
-            Rule::divide => ops.push(BinaryOp::Divide),
+            Rule::divide => {
+                per_unit = pair.into_inner().any(|inner| inner.as_rule() == Rule::per);
+                ops.push(BinaryOp::Divide);
+            }
             Rule::power => ops.push(BinaryOp::Power),
             Rule::conversion_op => ops.push(BinaryOp::Conversion),
-            _ => terms.push(build_term(pair)?),
+            Rule::conversion_target => terms.push(Expr::Variable(pair.as_str().to_string())),
+            Rule::as_percent_of => ops.push(BinaryOp::AsPercentOf),
+            Rule::shift_left => ops.push(BinaryOp::ShiftLeft),
+            Rule::shift_right => ops.push(BinaryOp::ShiftRight),
+            Rule::bit_and => ops.push(BinaryOp::BitAnd),
+            Rule::bit_xor => ops.push(BinaryOp::BitXor),
+            Rule::bit_or => ops.push(BinaryOp::BitOr),
+            _ => {
+                if let Some(reading) = suffix_unit_reading(&pair, numbers) {
+                    unit_readings.push((terms.len(), reading));
+                }
+                let term = build_term(pair, numbers)?;
+                let after_divide =
+                    ops.len() == terms.len() && ops.last() == Some(&BinaryOp::Divide);
+                let (term, bare_unit) = match term {
+                    Expr::Variable(name) if std::mem::take(&mut per_unit) => {
+                        match unit::parse_unit(&name) {
+                            Some(unit) => (
+                                Expr::WithCompoundUnit {
+                                    amount: Decimal::ONE,
+                                    unit,
+                                },
+                                true,
+                            ),
+                            None => (Expr::Variable(name), false),
+                        }
+                    }
+                    // After "/" the word stays a variable reference; the evaluator
+                    // reads it as a unit only when no such variable exists
+                    Expr::Variable(name) => {
+                        let bare_unit = after_divide && unit::parse_unit(&name).is_some();
+                        (Expr::Variable(name), bare_unit)
+                    }
+                    term => {
+                        per_unit = false;
+                        (term, false)
+                    }
+                };
+                if bare_unit {
+                    unit_divisors.push(terms.len());
+                }
+                terms.push(term);
+            }
         }
     }
 
@@ -162,6 +413,22 @@ fn build_calculation(pairs: pest::iterators::Pairs<'_, Rule>) -> Result<Expr, St
         return Err("Empty expression".to_string());
     }
 
+    // "1T in kg" converts a tonne, not a trillion
+    for (index, reading) in unit_readings {
+        let converts_to_unit = ops.get(index) == Some(&BinaryOp::Conversion)
+            && matches!(
+                terms.get(index + 1),
+                Some(Expr::Variable(target)) if unit::parse_unit(target).is_some()
+            );
+        if converts_to_unit {
+            terms[index] = reading;
+        }
+    }
+
+    // Pass 0: a bare unit divisor binds like a unit suffix, so "8 h * $30/h"
+    // applies the rate instead of dividing the product by one hour
+    bind_unit_divisors(&mut terms, &mut ops, &unit_divisors);
+
     // Pass 1: Power (right-associative: 2^3^2 = 2^(3^2) = 512)
     process_ops_right_assoc(&mut terms, &mut ops, &[BinaryOp::Power]);
 
@@ -172,9 +439,20 @@ fn build_calculation(pairs: pest::iterators::Pairs<'_, Rule>) -> Result<Expr, St
         &[BinaryOp::Multiply, BinaryOp::Divide],
     );
 
-    // Pass 3: Add, Subtract, Conversion (same precedence, left-to-right)
+    // Pass 3: Add, Subtract, Conversion, AsPercentOf (same precedence, left-to-right)
     process_ops_with_conversions(&mut terms, &mut ops)?;
 
+    // Passes 4-7: bitwise operators bind more loosely than arithmetic, as in
+    // Python: shifts, then and, xor, or ("1 + 1 << 2" is 8)
+    process_ops(
+        &mut terms,
+        &mut ops,
+        &[BinaryOp::ShiftLeft, BinaryOp::ShiftRight],
+    );
+    process_ops(&mut terms, &mut ops, &[BinaryOp::BitAnd]);
+    process_ops(&mut terms, &mut ops, &[BinaryOp::BitXor]);
+    process_ops(&mut terms, &mut ops, &[BinaryOp::BitOr]);
+
     if terms.len() != 1 {
         return Err("Failed to reduce expression".to_string());
     }
@@ -208,6 +486,18 @@ fn process_ops_with_conversions(
                     },
                 );
             }
+            BinaryOp::AsPercentOf => {
+                ops.remove(i);
+                let part = terms.remove(i);
+                let whole = terms.remove(i);
+                terms.insert(
+                    i,
+                    Expr::PercentageOfWhole {
+                        part: Box::new(part),
+                        whole: Box::new(whole),
+                    },
+                );
+            }
             BinaryOp::Add | BinaryOp::Subtract => {
                 let op = ops.remove(i);
                 let left = terms.remove(i);
@@ -250,6 +540,33 @@ fn process_ops(terms: &mut Vec<Expr>, ops: &mut Vec<BinaryOp>, target_ops: &[Bin
     }
 }
 
+/// Fold each bare unit divisor into the term before it, unless a power
+/// binds either operand more tightly ("$5 / m^2") or that term is itself a
+/// divisor: "12 / 3 / h" stays (12 / 3) / h whether `h` is a unit or a variable.
+fn bind_unit_divisors(terms: &mut Vec<Expr>, ops: &mut Vec<BinaryOp>, divisors: &[usize]) {
+    let mut folded = 0;
+    for &index in divisors {
+        let i = index - folded;
+        let power_after = ops.get(i) == Some(&BinaryOp::Power);
+        let tighter_before = i >= 2 && matches!(ops[i - 2], BinaryOp::Power | BinaryOp::Divide);
+        if i == 0 || power_after || tighter_before {
+            continue;
+        }
+        ops.remove(i - 1);
+        let right = terms.remove(i);
+        let left = terms.remove(i - 1);
+        terms.insert(
+            i - 1,
+            Expr::BinaryOp {
+                op: BinaryOp::Divide,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        );
+        folded += 1;
+    }
+}
+
 /// Process operators right-to-left for right-associative operators like power
 /// e.g., 2^3^2 should be 2^(3^2) = 2^9 = 512, not (2^3)^2 = 64
 fn process_ops_right_assoc(
@@ -280,6 +597,7 @@ fn process_ops_right_assoc(
 
 fn parse_currency_value(
     pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
 ) -> Result<(Decimal, Currency), String> {
     let mut amount = Decimal::ZERO;
     let mut currency = Currency::USD;
@@ -287,7 +605,10 @@ fn parse_currency_value(
     for inner in pair.into_inner() {
         match inner.as_rule() {
             Rule::number => {
-                amount = parse_number_str(inner.as_str())?;
+                amount = parse_number_str(inner.as_str(), numbers)?;
+            }
+            Rule::money_amount => {
+                amount = parse_money_amount(inner, numbers)?;
             }
             Rule::currency_symbol => {
                 currency = Currency::parse(inner.as_str()).ok_or("Unknown currency")?;
@@ -299,16 +620,121 @@ fn parse_currency_value(
     Ok((amount, currency))
 }
 
-fn parse_suffixed_number(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
+/// Parse "1.5k" in "$1.5k" into 1500
+fn parse_money_amount(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Decimal, String> {
+    let text = pair.as_str();
+    let mut inner = pair.into_inner();
+    let amount = parse_number_str(inner.next().ok_or("Expected number")?.as_str(), numbers)?;
+    let suffix = inner.next().ok_or("Expected multiplier")?.as_str();
+    let exponent = catalog::money_suffix_exponent(suffix).ok_or("Unknown multiplier")?;
+    10i64
+        .checked_pow(exponent)
+        .and_then(|scale| amount.checked_mul(Decimal::from(scale)))
+        .ok_or_else(|| format!("Number too large: {text}"))
+}
+
+/// Parse "2k usd" into a currency amount. When the word is not a currency,
+/// the shorthand reads as it would alone ("500m walk" is 500 m) and the
+/// word is ignored like other trailing text.
+fn parse_money_amount_code(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let text = pair.as_str();
     let mut inner = pair.into_inner();
     let num_pair = inner.next().ok_or("Expected number")?;
-    let amount = parse_number_str(num_pair.as_str())?;
+    let suffix_pair = inner.next().ok_or("Expected multiplier")?;
+    let word = inner.next().ok_or("Expected currency")?.as_str();
+    if Currency::parse(word).is_some() || Currency::parse_subunit(word).is_some() {
+        let amount = parse_number_str(num_pair.as_str(), numbers)?;
+        let exponent =
+            catalog::money_suffix_exponent(suffix_pair.as_str()).ok_or("Unknown multiplier")?;
+        let amount = 10i64
+            .checked_pow(exponent)
+            .and_then(|scale| amount.checked_mul(Decimal::from(scale)))
+            .ok_or_else(|| format!("Number too large: {text}"))?;
+        return suffixed_expr(amount, word);
+    }
+    let unit_follows = unit::parse_unit(word).is_some();
+    suffixed_number_expr(num_pair, suffix_pair, text, unit_follows, numbers)
+}
 
+fn parse_suffixed_number(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let text = pair.as_str();
+    let mut inner = pair.into_inner();
+    let num_pair = inner.next().ok_or("Expected number")?;
     let suffix_pair = inner.next().ok_or("Expected identifier")?;
+    suffixed_number_expr(num_pair, suffix_pair, text, false, numbers)
+}
+
+/// The unit reading of a number whose attached SI suffix also spells a unit
+/// ("1T" as one tonne), used when the number is converted to a unit
+fn suffix_unit_reading(
+    pair: &pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Option<Expr> {
+    if pair.as_rule() != Rule::suffixed_number {
+        return None;
+    }
+    let mut inner = pair.clone().into_inner();
+    let num_pair = inner.next()?;
+    let suffix_pair = inner.next()?;
+    let attached = num_pair.as_span().end() == suffix_pair.as_span().start();
+    if !attached || !catalog::UNIT_SI_SUFFIXES.contains(&suffix_pair.as_str()) {
+        return None;
+    }
+    suffixed_number_expr(num_pair, suffix_pair, pair.as_str(), true, numbers).ok()
+}
+
+fn suffixed_number_expr(
+    num_pair: pest::iterators::Pair<'_, Rule>,
+    suffix_pair: pest::iterators::Pair<'_, Rule>,
+    text: &str,
+    unit_follows: bool,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let amount = parse_number_str(num_pair.as_str(), numbers)?;
+    // "3k" is 3000, while "3 k" and "300K" keep reading the word as a unit.
+    // "2.5M" is 2500000 unless a unit follows, which makes it metres again.
+    let attached = num_pair.as_span().end() == suffix_pair.as_span().start();
     let suffix = suffix_pair.as_str();
+    let spells_unit = unit_follows && catalog::UNIT_SI_SUFFIXES.contains(&suffix);
+    if let Some(exponent) = catalog::si_suffix_exponent(suffix).filter(|_| attached && !spells_unit)
+    {
+        let scale = if exponent >= 0 {
+            10i64
+                .checked_pow(exponent.unsigned_abs())
+                .map(Decimal::from)
+        } else {
+            Some(Decimal::new(1, exponent.unsigned_abs()))
+        };
+        return scale
+            .and_then(|scale| amount.checked_mul(scale))
+            .map(Expr::Number)
+            .ok_or_else(|| format!("Number too large: {text}"));
+    }
+    suffixed_expr(amount, suffix_pair.as_str())
+}
 
+/// Interpret the word after a number as a currency, unit, or multiplied variable
+fn suffixed_expr(amount: Decimal, suffix: &str) -> Result<Expr, String> {
     if let Some(currency) = Currency::parse(suffix) {
         Ok(Expr::Currency { amount, currency })
+    } else if let Some(subunit) = Currency::parse_subunit(suffix) {
+        // Subunits are stored in their parent currency (100000 sats -> ₿0.001)
+        let amount = amount
+            .checked_div(subunit.per_unit())
+            .ok_or("Invalid subunit amount")?;
+        Ok(Expr::Currency {
+            amount,
+            currency: subunit.currency,
+        })
     } else if let Some(compound_unit) = unit::parse_unit(suffix) {
         // All physical quantities use the canonical dimensional model.
         Ok(Expr::WithCompoundUnit {
@@ -325,14 +751,129 @@ fn parse_suffixed_number(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr,
     }
 }
 
-fn parse_percentage_of(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
+/// Parse "20°C" into a temperature reading, or "5 C°" / "Δ5°C" into a difference.
+fn parse_temperature(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let is_delta = pair.as_rule() == Rule::temperature_delta;
+    let mut inner = pair.into_inner();
+    let amount = parse_number_str(
+        inner.next().ok_or("Expected temperature value")?.as_str(),
+        numbers,
+    )?;
+    let scale = inner.next().ok_or("Expected temperature scale")?.as_str();
+    let unit = unit::parse_unit(scale).ok_or("Temperature scale is not registered")?;
+    Ok(Expr::WithCompoundUnit {
+        amount,
+        unit: if is_delta { unit.to_delta() } else { unit },
+    })
+}
+
+/// Parse "10 kg*m/s^2" into a value with a compound unit. When any factor is not
+/// a unit, the expression is ordinary arithmetic on variables ("2 x^2" = 2 × x²).
+fn parse_unit_expression_number(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let mut inner = pair.into_inner();
+    let num_pair = inner.next().ok_or("Expected number")?;
+    let amount = parse_number_str(num_pair.as_str(), numbers)?;
+    let expression = inner.next().ok_or("Expected unit expression")?;
+
+    // (operator, identifier, exponent); the first factor has no operator
+    let mut factors = Vec::new();
+    let mut op = None;
+    for part in expression.into_inner() {
+        match part.as_rule() {
+            Rule::unit_multiply => op = Some(BinaryOp::Multiply),
+            Rule::unit_divide => op = Some(BinaryOp::Divide),
+            Rule::unit_factor => {
+                let mut factor = part.into_inner();
+                let name = factor.next().ok_or("Expected unit")?.as_str();
+                let exponent = factor
+                    .next()
+                    .map(|exp| parse_unit_exponent(exp.as_str()))
+                    .transpose()?;
+                factors.push((op.take(), name, exponent));
+            }
+            _ => {}
+        }
+    }
+
+    if factors
+        .iter()
+        .all(|(_, name, _)| unit::parse_unit(name).is_some())
+    {
+        let mut combined: Option<CompoundUnit> = None;
+        for (op, name, exponent) in &factors {
+            let mut factor = unit::parse_unit(name).ok_or("Expected unit")?;
+            if let Some(exponent) = exponent {
+                factor = factor.try_power(*exponent).map_err(|e| e.to_string())?;
+            }
+            combined = Some(match (combined, op) {
+                (None, _) => factor,
+                (Some(left), Some(BinaryOp::Divide)) => {
+                    left.try_divide(&factor).map_err(|e| e.to_string())?
+                }
+                (Some(left), _) => left.try_multiply(&factor).map_err(|e| e.to_string())?,
+            });
+        }
+        return Ok(Expr::WithCompoundUnit {
+            amount,
+            unit: combined.ok_or("Expected unit")?,
+        });
+    }
+
+    let mut expr: Option<Expr> = None;
+    for (op, name, exponent) in factors {
+        let term = match exponent {
+            Some(exponent) => Expr::BinaryOp {
+                op: BinaryOp::Power,
+                left: Box::new(Expr::Variable(name.to_string())),
+                right: Box::new(Expr::Number(Decimal::from(exponent))),
+            },
+            None => Expr::Variable(name.to_string()),
+        };
+        expr = Some(match expr {
+            None if exponent.is_none() => suffixed_expr(amount, name)?,
+            None => Expr::BinaryOp {
+                op: BinaryOp::Multiply,
+                left: Box::new(Expr::Number(amount)),
+                right: Box::new(term),
+            },
+            Some(left) => Expr::BinaryOp {
+                op: op.unwrap_or(BinaryOp::Multiply),
+                left: Box::new(left),
+                right: Box::new(term),
+            },
+        });
+    }
+    expr.ok_or_else(|| "Expected unit expression".to_string())
+}
+
+fn parse_unit_exponent(s: &str) -> Result<i8, String> {
+    match s {
+        "²" => Ok(2),
+        "³" => Ok(3),
+        _ => s
+            .trim_start_matches('^')
+            .parse()
+            .map_err(|_| format!("Unit exponent out of range: {s}")),
+    }
+}
+
+fn parse_percentage_of(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
     let mut inner = pair.into_inner();
     let pct_pair = inner.next().ok_or("Expected percentage")?;
     let pct_num = pct_pair.into_inner().next().ok_or("Expected number")?;
-    let percentage = parse_number_str(pct_num.as_str())?;
+    let percentage = parse_number_str(pct_num.as_str(), numbers)?;
 
     let value_pair = inner.next().ok_or("Expected value")?;
-    let value = build_term(value_pair)?;
+    let value = build_term(value_pair, numbers)?;
 
     Ok(Expr::PercentageOf {
         percentage: percentage / Decimal::from(100),
@@ -340,7 +881,104 @@ fn parse_percentage_of(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, St
     })
 }
 
-fn parse_function_call(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
+/// "x% more than y" scales y by (1 + x%), "x% less than y" by (1 - x%)
+fn parse_percentage_change(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let mut inner = pair.into_inner();
+    let pct_pair = inner.next().ok_or("Expected percentage")?;
+    let pct_num = pct_pair.into_inner().next().ok_or("Expected number")?;
+    let percentage = parse_number_str(pct_num.as_str(), numbers)? / Decimal::from(100);
+
+    let direction = inner.next().ok_or("Expected 'more than' or 'less than'")?;
+    let factor = if direction.as_str()[..4].eq_ignore_ascii_case("less") {
+        Decimal::ONE - percentage
+    } else {
+        Decimal::ONE + percentage
+    };
+
+    let value_pair = inner.next().ok_or("Expected value")?;
+    let value = build_term(value_pair, numbers)?;
+
+    Ok(Expr::PercentageOf {
+        percentage: factor,
+        value: Box::new(value),
+    })
+}
+
+/// Value of "half", "a dozen", or "a thousand"
+fn quantity_value(text: &str) -> Result<Decimal, String> {
+    let word = text.split_whitespace().last().unwrap_or(text);
+    catalog::quantity_word_value(word).ok_or_else(|| format!("Unknown quantity: {text}"))
+}
+
+fn parse_time_until(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
+    let mut inner = pair.into_inner();
+    let unit_word = inner.next().ok_or("Expected 'days' or 'weeks'")?.as_str();
+    let unit = unit::parse_unit(&unit_word.to_lowercase()).ok_or("Time unit is not registered")?;
+    let date_text = inner
+        .find(|p| p.as_rule() == Rule::date_literal)
+        .ok_or("Expected date")?
+        .as_str();
+    let date = Date::parse_iso(date_text).ok_or_else(|| format!("Invalid date: {date_text}"))?;
+    Ok(Expr::TimeUntil { unit, date })
+}
+
+fn parse_relative_date(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
+    let mut inner = pair.into_inner();
+    let count_text = inner.next().ok_or("Expected a count")?.as_str();
+    let count: i64 = count_text
+        .parse()
+        .map_err(|_| format!("Number too large: {count_text}"))?;
+    let unit = inner
+        .next()
+        .ok_or("Expected a time unit")?
+        .as_str()
+        .to_lowercase();
+    let past = inner
+        .next()
+        .is_some_and(|p| p.as_rule() == Rule::ago_keyword);
+    let count = if past { -count } else { count };
+    let (days, months) = match unit.trim_end_matches('s') {
+        "day" => (count, 0),
+        "week" => (count.checked_mul(7).ok_or("Number too large")?, 0),
+        "month" => (0, count),
+        _ => (0, count.checked_mul(12).ok_or("Number too large")?),
+    };
+    Ok(Expr::RelativeDate { days, months })
+}
+
+fn parse_each_price(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    let mut count = None;
+    let mut price = None;
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::number if count.is_none() => {
+                count = Some(parse_number_str(inner.as_str(), numbers)?)
+            }
+            Rule::money_amount if count.is_none() => {
+                count = Some(parse_money_amount(inner, numbers)?)
+            }
+            Rule::quantity_word if count.is_none() => count = Some(quantity_value(inner.as_str())?),
+            Rule::identifier | Rule::at_keyword | Rule::each_keyword => {}
+            _ => price = Some(build_term(inner, numbers)?),
+        }
+    }
+    Ok(Expr::BinaryOp {
+        op: BinaryOp::Multiply,
+        left: Box::new(Expr::Number(count.ok_or("Expected a count")?)),
+        right: Box::new(price.ok_or("Expected a price")?),
+    })
+}
+
+fn parse_function_call(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
     let mut inner = pair.into_inner();
     let name = inner
         .next()
@@ -351,26 +989,37 @@ fn parse_function_call(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, St
     let mut args = Vec::new();
     for arg_pair in inner {
         if arg_pair.as_rule() == Rule::expression {
-            args.push(build_expression(arg_pair.into_inner())?);
+            args.push(build_expression(arg_pair.into_inner(), numbers)?);
         }
     }
 
     Ok(Expr::FunctionCall { name, args })
 }
 
-fn build_term(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
+fn build_term(
+    pair: pest::iterators::Pair<'_, Rule>,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Expr, String> {
+    // A custom number format may also read a number with a word attached
+    // ("100CR") or in parentheses ("(100)") as one literal
+    if matches!(pair.as_rule(), Rule::suffixed_number | Rule::parenthesized) {
+        if let Some(value) = numbers.custom_value(pair.as_str()) {
+            return Ok(Expr::Number(value));
+        }
+    }
     match pair.as_rule() {
         Rule::number => {
-            let n = parse_number_str(pair.as_str())?;
+            let n = parse_number_str(pair.as_str(), numbers)?;
             Ok(Expr::Number(n))
         }
+        Rule::base_literal => parse_base_literal(pair.as_str()).map(Expr::Number),
         Rule::percentage => {
             let inner = pair.into_inner().next().ok_or("Expected number")?;
-            let n = parse_number_str(inner.as_str())?;
+            let n = parse_number_str(inner.as_str(), numbers)?;
             Ok(Expr::Percentage(n / Decimal::from(100)))
         }
         Rule::currency_value => {
-            let (amount, currency) = parse_currency_value(pair)?;
+            let (amount, currency) = parse_currency_value(pair, numbers)?;
             Ok(Expr::Currency { amount, currency })
         }
         Rule::angle_value => {
@@ -379,23 +1028,48 @@ fn build_term(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
                     .next()
                     .ok_or("Expected angle value")?
                     .as_str(),
+                numbers,
             )?;
             Ok(Expr::WithCompoundUnit {
                 amount,
                 unit: unit::parse_unit("deg").ok_or("Degree unit is not registered")?,
             })
         }
-        Rule::suffixed_number => parse_suffixed_number(pair),
+        Rule::temperature_value | Rule::temperature_delta => parse_temperature(pair, numbers),
+        Rule::unit_expression_number => parse_unit_expression_number(pair, numbers),
+        Rule::suffixed_number => parse_suffixed_number(pair, numbers),
+        Rule::money_amount_code => parse_money_amount_code(pair, numbers),
         Rule::variable_ref => {
             let name = pair.as_str().to_string();
             Ok(Expr::Variable(name))
         }
         Rule::parenthesized => {
             let inner = pair.into_inner().next().ok_or("Expected expression")?;
-            build_expression(inner.into_inner())
+            build_expression(inner.into_inner(), numbers)
+        }
+        Rule::absolute_value => {
+            let inner = pair.into_inner().next().ok_or("Expected expression")?;
+            Ok(Expr::FunctionCall {
+                name: "abs".to_string(),
+                args: vec![build_expression(inner.into_inner(), numbers)?],
+            })
+        }
+        Rule::percentage_of => parse_percentage_of(pair, numbers),
+        Rule::percentage_change => parse_percentage_change(pair, numbers),
+        Rule::each_price => parse_each_price(pair, numbers),
+        Rule::time_until => parse_time_until(pair),
+        Rule::relative_date => parse_relative_date(pair),
+        Rule::quantity_of => {
+            let mut inner = pair.into_inner();
+            let quantity = quantity_value(inner.next().ok_or("Expected quantity word")?.as_str())?;
+            let value = build_term(inner.next().ok_or("Expected value")?, numbers)?;
+            Ok(Expr::PercentageOf {
+                percentage: quantity,
+                value: Box::new(value),
+            })
         }
-        Rule::percentage_of => parse_percentage_of(pair),
-        Rule::function_call => parse_function_call(pair),
+        Rule::article_quantity => quantity_value(pair.as_str()).map(Expr::Number),
+        Rule::function_call => parse_function_call(pair, numbers),
         _ => Err(format!("Unexpected rule: {:?}", pair.as_rule())),
     }
 }
@@ -403,13 +1077,15 @@ fn build_term(pair: pest::iterators::Pair<'_, Rule>) -> Result<Expr, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::parse_line;
+    use crate::parser::{parse_line, try_parse_exact};
 
     /// Helper to extract the expression from an AST
     fn get_expr(ast: &Ast) -> Option<&Expr> {
         match ast {
             Ast::Expression(e) => Some(e),
             Ast::Assignment { expr, .. } => Some(expr),
+            Ast::FunctionDefinition { body, .. } => Some(body),
+            Ast::Repeat { body, .. } => get_expr(body),
             Ast::Empty => None,
         }
     }
@@ -578,6 +1254,49 @@ mod tests {
         assert!(is_binary_op(left, BinaryOp::Add), "Left should be Add");
     }
 
+    #[test]
+    fn test_expression_depth() {
+        let depth = |input| parse_line(input).unwrap().depth();
+        assert_eq!(depth("42"), 1);
+        assert_eq!(depth("2 + 3"), 2);
+        assert_eq!(depth("(2 + 3) * 4"), 3);
+        assert_eq!(depth("x = max(1, (2 + 3) * 4)"), 4);
+        assert_eq!(depth(""), 0);
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        // Arithmetic binds first, then shifts, and, xor, or:
+        // 1 | 2 + 1 << 2 & 0xF xor 4 is 1 | (((2 + 1) << 2) & 15 xor 4)
+        let ast = parse_line("1 | 2 + 1 << 2 & 0xF xor 4").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        assert!(is_binary_op(expr, BinaryOp::BitOr), "Root should be BitOr");
+
+        let xor = binary_right(expr).unwrap();
+        assert!(
+            is_binary_op(xor, BinaryOp::BitXor),
+            "Right should be BitXor"
+        );
+        let and = binary_left(xor).unwrap();
+        assert!(is_binary_op(and, BinaryOp::BitAnd), "Then BitAnd");
+        assert_eq!(binary_right(and), Some(&Expr::Number(Decimal::from(15))));
+        let shift = binary_left(and).unwrap();
+        assert!(is_binary_op(shift, BinaryOp::ShiftLeft), "Then ShiftLeft");
+        assert!(is_binary_op(binary_left(shift).unwrap(), BinaryOp::Add));
+    }
+
+    #[test]
+    fn test_base_literals() {
+        for (input, expected) in [("0xFF", 255), ("0X1f", 31), ("0b1010", 10), ("-0x10", -16)] {
+            let ast = parse_line(input).unwrap();
+            assert_eq!(
+                get_expr(&ast),
+                Some(&Expr::Number(Decimal::from(expected))),
+                "{input}"
+            );
+        }
+    }
+
     #[test]
     fn test_nested_parentheses() {
         // ((1 + 2) * 3) + 4 should have Add at root
@@ -598,23 +1317,101 @@ mod tests {
     #[test]
     fn test_assignment_parsing() {
         let ast = parse_line("x = 10").unwrap();
-        let Ast::Assignment { name, expr } = ast else {
+        let Ast::Assignment { names, expr } = ast else {
             panic!("Expected Assignment, got {:?}", ast);
         };
-        assert_eq!(name, "x");
+        assert_eq!(names, ["x"]);
         assert!(matches!(*expr, Expr::Number(n) if n == Decimal::from(10)));
     }
 
     #[test]
     fn test_assignment_with_expression() {
         let ast = parse_line("total = 5 + 3").unwrap();
-        let Ast::Assignment { name, expr } = ast else {
+        let Ast::Assignment { names, expr } = ast else {
             panic!("Expected Assignment, got {:?}", ast);
         };
-        assert_eq!(name, "total");
+        assert_eq!(names, ["total"]);
         assert!(is_binary_op(&expr, BinaryOp::Add));
     }
 
+    #[test]
+    fn test_augmented_assignment_parsing() {
+        for (input, expected) in [
+            ("subtotal += 100", BinaryOp::Add),
+            ("subtotal -= 100", BinaryOp::Subtract),
+            ("subtotal *= 2", BinaryOp::Multiply),
+            ("subtotal /= 4", BinaryOp::Divide),
+        ] {
+            let ast = parse_line(input).unwrap();
+            let Ast::Assignment { names, expr } = ast else {
+                panic!("Expected Assignment for {input}, got {:?}", ast);
+            };
+            assert_eq!(names, ["subtotal"]);
+            assert!(is_binary_op(&expr, expected), "{input}: {expr:?}");
+            assert!(matches!(binary_left(&expr), Some(Expr::Variable(v)) if v == "subtotal"));
+        }
+    }
+
+    #[test]
+    fn test_chained_assignment_parsing() {
+        let ast = parse_line("x = y = 100").unwrap();
+        let Ast::Assignment { names, expr } = ast else {
+            panic!("Expected Assignment, got {:?}", ast);
+        };
+        assert_eq!(names, ["x", "y"]);
+        assert!(matches!(*expr, Expr::Number(n) if n == Decimal::from(100)));
+
+        // An augmented operator applies to the name right before it
+        let ast = parse_line("total = subtotal += 5").unwrap();
+        let Ast::Assignment { names, expr } = ast else {
+            panic!("Expected Assignment, got {:?}", ast);
+        };
+        assert_eq!(names, ["total", "subtotal"]);
+        assert!(matches!(binary_left(&expr), Some(Expr::Variable(v)) if v == "subtotal"));
+
+        // A variable on the right-hand side is not another target
+        let ast = parse_line("x = y + 1").unwrap();
+        assert!(matches!(ast, Ast::Assignment { names, .. } if names == ["x"]));
+    }
+
+    #[test]
+    fn test_absolute_value_bars_parse_as_abs() {
+        let ast = parse_line("|3 - 7|").unwrap();
+        let Some(Expr::FunctionCall { name, args }) = get_expr(&ast) else {
+            panic!("Expected FunctionCall, got {:?}", ast);
+        };
+        assert_eq!(name, "abs");
+        assert!(is_binary_op(&args[0], BinaryOp::Subtract));
+
+        let ast = parse_line("|a| + |b|").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        assert!(is_binary_op(expr, BinaryOp::Add));
+        assert!(
+            matches!(binary_left(expr), Some(Expr::FunctionCall { name, .. }) if name == "abs")
+        );
+        assert!(
+            matches!(binary_right(expr), Some(Expr::FunctionCall { name, .. }) if name == "abs")
+        );
+    }
+
+    #[test]
+    fn test_function_definition_parsing() {
+        let ast = parse_line("area(w, h) = w * h").unwrap();
+        let Ast::FunctionDefinition { name, params, body } = ast else {
+            panic!("Expected FunctionDefinition, got {:?}", ast);
+        };
+        assert_eq!(name, "area");
+        assert_eq!(params, ["w", "h"]);
+        assert!(is_binary_op(&body, BinaryOp::Multiply));
+
+        let ast = parse_line("answer() = 42").unwrap();
+        assert!(matches!(ast, Ast::FunctionDefinition { params, .. } if params.is_empty()));
+
+        // A call is still an expression
+        let ast = parse_line("area(2, 3)").unwrap();
+        assert!(matches!(ast, Ast::Expression(Expr::FunctionCall { .. })));
+    }
+
     // ========================================
     // Number Parsing
     // ========================================
@@ -637,6 +1434,54 @@ mod tests {
         assert_eq!(*n, expected);
     }
 
+    #[test]
+    fn test_number_with_scale_word() {
+        let ast = parse_line("1.5 billion").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        assert!(matches!(expr, Expr::Number(n) if *n == Decimal::from(1_500_000_000)));
+
+        let ast = parse_line("2 million usd").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        let Expr::Currency { amount, currency } = expr else {
+            panic!("Expected Currency, got {:?}", expr);
+        };
+        assert_eq!(*amount, Decimal::from(2_000_000));
+        assert_eq!(*currency, Currency::USD);
+    }
+
+    #[test]
+    fn test_number_with_si_suffix() {
+        let number = |input: &str| match parse_line(input).unwrap() {
+            Ast::Expression(Expr::Number(n)) => Some(n),
+            _ => None,
+        };
+        assert_eq!(number("3k"), Some(Decimal::from(3000)));
+        assert_eq!(number("2.5M"), Some(Decimal::from(2_500_000)));
+        assert_eq!(number("1.2G"), Some(Decimal::from(1_200_000_000)));
+        assert_eq!(number("40µ"), Some(Decimal::from_str("0.00004").unwrap()));
+
+        // "500m" is 500 meters: lowercase m is never milli
+        let is_unit = |input: &str| {
+            matches!(
+                parse_line(input).unwrap(),
+                Ast::Expression(Expr::WithCompoundUnit { .. })
+            )
+        };
+        assert!(is_unit("500m"));
+        // Suffixes are case-sensitive and must touch the number
+        assert!(is_unit("300K"));
+        assert!(is_unit("5 M"));
+        assert!(is_unit("5g"));
+        assert!(is_unit("3km"));
+        // Prefixes that spell a unit stay that unit when a unit follows
+        assert!(is_unit("5M km"));
+        assert!(matches!(
+            parse_line("1T in kg").unwrap(),
+            Ast::Expression(Expr::Conversion { value, .. })
+                if matches!(*value, Expr::WithCompoundUnit { .. })
+        ));
+    }
+
     // ========================================
     // Percentage Parsing
     // ========================================
@@ -664,6 +1509,200 @@ mod tests {
         assert!(matches!(**value, Expr::Number(n) if n == Decimal::from(100)));
     }
 
+    #[test]
+    fn test_percentage_change_expression() {
+        let ast = parse_line("20% more than 100").unwrap();
+        let Some(Expr::PercentageOf { percentage, .. }) = get_expr(&ast) else {
+            panic!("Expected PercentageOf, got {:?}", ast);
+        };
+        assert_eq!(*percentage, Decimal::from_str("1.2").unwrap());
+
+        let ast = parse_line("10% less than 50 kg").unwrap();
+        let Some(Expr::PercentageOf { percentage, value }) = get_expr(&ast) else {
+            panic!("Expected PercentageOf, got {:?}", ast);
+        };
+        assert_eq!(*percentage, Decimal::from_str("0.9").unwrap());
+        assert!(matches!(**value, Expr::WithCompoundUnit { .. }));
+    }
+
+    #[test]
+    fn test_repeat_statement() {
+        let ast = parse_line("repeat 12: balance = balance * 1.01 - 50").unwrap();
+        let Ast::Repeat { count, body } = ast else {
+            panic!("Expected Repeat, got {:?}", ast);
+        };
+        assert_eq!(count, 12);
+        assert!(matches!(*body, Ast::Assignment { ref names, .. } if names == &["balance"]));
+
+        let ast = parse_line("repeat 3: x += 1").unwrap();
+        assert!(matches!(ast, Ast::Repeat { count: 3, .. }));
+
+        for input in [
+            "repeat 1.5: x += 1",
+            "repeat 10001: x += 1",
+            "repeat -1: x += 1",
+        ] {
+            assert!(try_parse_exact(input).is_err(), "{input}");
+        }
+        // "repeat" stays usable as a variable name
+        assert!(matches!(
+            parse_line("repeat = 3").unwrap(),
+            Ast::Assignment { .. }
+        ));
+    }
+
+    #[test]
+    fn test_quantity_words() {
+        let ast = parse_line("half of 200").unwrap();
+        let Some(Expr::PercentageOf { percentage, .. }) = get_expr(&ast) else {
+            panic!("Expected PercentageOf, got {:?}", ast);
+        };
+        assert_eq!(*percentage, Decimal::new(5, 1));
+
+        let ast = parse_line("a dozen").unwrap();
+        assert_eq!(get_expr(&ast), Some(&Expr::Number(Decimal::from(12))));
+
+        // Bare words stay plain variables
+        let ast = parse_line("quarter").unwrap();
+        assert_eq!(get_expr(&ast), Some(&Expr::Variable("quarter".to_string())));
+        // "halfway" is not "half"
+        assert!(!matches!(
+            get_expr(&parse_line("halfway of 200").unwrap()),
+            Some(Expr::PercentageOf { .. })
+        ));
+    }
+
+    #[test]
+    fn test_time_until_expression() {
+        let ast = parse_line("days until 2025-01-01").unwrap();
+        let Some(Expr::TimeUntil { unit, date }) = get_expr(&ast) else {
+            panic!("Expected TimeUntil, got {:?}", ast);
+        };
+        assert_eq!(unit.symbol, "d");
+        assert_eq!(*date, Date::from_ymd(2025, 1, 1).unwrap());
+
+        let ast = parse_line("Weeks until 2025-06-30").unwrap();
+        assert!(
+            matches!(get_expr(&ast), Some(Expr::TimeUntil { unit, .. }) if unit.symbol == "wk")
+        );
+
+        // Days that do not exist are rejected
+        assert!(try_parse_exact("days until 2025-02-30").is_err());
+    }
+
+    #[test]
+    fn test_unit_expression_literal() {
+        let ast = parse_line("10 kg*m/s^2").unwrap();
+        let Some(Expr::WithCompoundUnit { amount, unit }) = get_expr(&ast) else {
+            panic!("Expected WithCompoundUnit, got {:?}", ast);
+        };
+        assert_eq!(*amount, Decimal::from(10));
+        assert_eq!(unit.symbol, "N");
+
+        // Unknown factors fall back to arithmetic on variables
+        let ast = parse_line("2 x^2").unwrap();
+        assert!(matches!(
+            get_expr(&ast),
+            Some(Expr::BinaryOp {
+                op: BinaryOp::Multiply,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_per_keyword() {
+        let ast = parse_line("60 miles per hour").unwrap();
+        let Some(Expr::BinaryOp {
+            op: BinaryOp::Divide,
+            left,
+            right,
+        }) = get_expr(&ast)
+        else {
+            panic!("Expected division, got {:?}", ast);
+        };
+        assert!(matches!(**left, Expr::WithCompoundUnit { .. }));
+        // The bare unit word after "per" is one of that unit
+        let Expr::WithCompoundUnit { amount, unit } = &**right else {
+            panic!("Expected a unit value, got {:?}", right);
+        };
+        assert_eq!(*amount, Decimal::ONE);
+        assert_eq!(unit.symbol, "h");
+
+        // "per" is not read as a suffix, and other words after it stay variables
+        let ast = parse_line("10 per person").unwrap();
+        assert!(matches!(
+            get_expr(&ast),
+            Some(Expr::BinaryOp { right, .. }) if **right == Expr::Variable("person".into())
+        ));
+    }
+
+    #[test]
+    fn test_unit_divisor_binds_to_its_price() {
+        // "8 h * $30/h" is 8 h * ($30/h), not (8 h * $30) / h
+        let ast = parse_line("8 h * $30/h").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        assert!(is_binary_op(expr, BinaryOp::Multiply));
+        let right = binary_right(expr).unwrap();
+        assert!(is_binary_op(right, BinaryOp::Divide));
+        assert_eq!(binary_right(right), Some(&Expr::Variable("h".into())));
+
+        // A power still binds tighter than the unit divisor
+        let ast = parse_line("$5 / m^2").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        assert!(is_binary_op(expr, BinaryOp::Divide));
+        assert!(is_binary_op(binary_right(expr).unwrap(), BinaryOp::Power));
+
+        // Division chains stay left-associative: "12 / 3 / h" is (12 / 3) / h
+        let ast = parse_line("12 / 3 / h").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        assert!(is_binary_op(binary_left(expr).unwrap(), BinaryOp::Divide));
+        assert_eq!(binary_right(expr), Some(&Expr::Variable("h".into())));
+    }
+
+    #[test]
+    fn test_temperature_readings_and_differences() {
+        for (input, symbol, is_delta) in [
+            ("20°C", "°C", false),
+            ("20 °F", "°F", false),
+            ("5 C°", "C°", true),
+            ("Δ5°C", "C°", true),
+            ("Δ9 F", "F°", true),
+        ] {
+            let ast = parse_line(input).unwrap();
+            let Some(Expr::WithCompoundUnit { unit, .. }) = get_expr(&ast) else {
+                panic!("Expected a temperature for {input}, got {:?}", ast);
+            };
+            assert_eq!(unit.symbol, symbol, "{input}");
+            assert_eq!(unit.is_delta, is_delta, "{input}");
+        }
+
+        // A bare degree sign is still an angle
+        let ast = parse_line("90°").unwrap();
+        assert!(matches!(
+            get_expr(&ast),
+            Some(Expr::WithCompoundUnit { unit, .. }) if unit.symbol == "°"
+        ));
+    }
+
+    #[test]
+    fn test_as_percent_of_expression() {
+        let ast = parse_line("$200 as % of $1000").unwrap();
+        let expr = get_expr(&ast).unwrap();
+        let Expr::PercentageOfWhole { part, whole } = expr else {
+            panic!("Expected PercentageOfWhole, got {:?}", expr);
+        };
+        assert!(matches!(**part, Expr::Currency { .. }));
+        assert!(matches!(**whole, Expr::Currency { .. }));
+
+        // "as" is not taken as a unit suffix
+        let ast = parse_line("200 as % of 1000").unwrap();
+        assert!(matches!(
+            get_expr(&ast),
+            Some(Expr::PercentageOfWhole { .. })
+        ));
+    }
+
     // ========================================
     // Currency Parsing
     // ========================================
@@ -690,6 +1729,22 @@ mod tests {
         assert_eq!(*currency, Currency::USD);
     }
 
+    #[test]
+    fn test_currency_suffix_without_space() {
+        for (input, expected) in [
+            ("100usd", Currency::USD),
+            ("100chf", Currency::CHF),
+            ("0.5btc", Currency::BTC),
+            ("50usdc", Currency::USDC),
+        ] {
+            let ast = parse_line(input).unwrap();
+            let Some(Expr::Currency { currency, .. }) = get_expr(&ast) else {
+                panic!("Expected Currency for {input}, got {:?}", ast);
+            };
+            assert_eq!(*currency, expected, "{input}");
+        }
+    }
+
     // ========================================
     // Unit Parsing
     // ========================================
@@ -4,10 +4,14 @@ mod ast;
 
 pub use ast::{Ast, BinaryOp, Expr};
 
+use std::borrow::Cow;
+
+use pest::error::InputLocation;
 use pest::Parser;
 use pest_derive::Parser;
+use rust_decimal::Decimal;
 
-use crate::ParseError;
+use crate::{NumberLocale, ParseError};
 
 #[derive(Parser)]
 #[grammar = "parser/grammar.pest"]
@@ -16,6 +20,20 @@ pub struct NumrParser;
 const MAX_INPUT_BYTES: usize = 16 * 1024;
 const MAX_OPERATIONS: usize = 256;
 const MAX_NESTING: usize = 128;
+/// Most iterations a `repeat N:` line may run
+pub const MAX_REPEAT_COUNT: usize = 10_000;
+
+/// The part of a line before its `#` or `//` comment, if any.
+///
+/// `100 + 50 # groceries` gives `100 + 50 `; a line without a comment is returned whole.
+pub fn strip_comment(input: &str) -> &str {
+    let comment_start = [input.find('#'), input.find("//")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(input.len());
+    &input[..comment_start]
+}
 
 fn validate_limits(input: &str) -> Result<(), ParseError> {
     if input.len() > MAX_INPUT_BYTES {
@@ -25,27 +43,34 @@ fn validate_limits(input: &str) -> Result<(), ParseError> {
         });
     }
 
-    let trimmed = input.trim_start();
-    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("//") {
+    // Operators and parentheses in comment text are not evaluated
+    let code = strip_comment(input);
+    if code.trim().is_empty() {
         return Ok(());
     }
 
     let mut nesting = 0usize;
     let mut max_nesting = 0usize;
     let mut operations = 0usize;
-    for ch in input.chars() {
+    let mut bars = 0usize;
+    for ch in code.chars() {
         match ch {
+            '|' => bars = bars.saturating_add(1),
             '(' => {
                 nesting = nesting.saturating_add(1);
                 max_nesting = max_nesting.max(nesting);
             }
             ')' => nesting = nesting.saturating_sub(1),
-            '+' | '-' | '*' | '/' | '÷' | '×' | '^' | ',' | '=' => {
+            '+' | '-' | '−' | '*' | '/' | '÷' | '×' | '@' | '^' | ',' | '=' | '&' | '<' | '>' =>
+            {
                 operations = operations.saturating_add(1);
             }
             _ => {}
         }
     }
+    // Absolute value bars do not say which side they open, so count every
+    // pair as one more level of nesting
+    let max_nesting = max_nesting.saturating_add(bars / 2);
     if max_nesting > MAX_NESTING {
         return Err(ParseError::TooDeep {
             actual: max_nesting,
@@ -61,51 +86,203 @@ fn validate_limits(input: &str) -> Result<(), ParseError> {
     Ok(())
 }
 
+/// A custom number format: the value of a literal it recognizes
+type CustomNumber = dyn Fn(&str) -> Option<Decimal> + Send + Sync;
+
+/// Number formats read beyond the built-in ones
+#[derive(Clone, Copy, Default)]
+pub(crate) struct NumberSyntax<'a> {
+    /// How digits are grouped and which mark starts the fraction
+    pub locale: NumberLocale,
+    /// Domain-specific literals such as `(100)`, tried before the built-in
+    /// formats; see [`crate::Engine::set_number_parser`]
+    pub custom: Option<&'a CustomNumber>,
+}
+
+impl NumberSyntax<'_> {
+    /// The value of a literal in the custom format, if it is one
+    fn custom_value(&self, literal: &str) -> Option<Decimal> {
+        self.custom.and_then(|custom| custom(literal))
+    }
+
+    /// Spell European-style numbers the way the grammar reads them, one
+    /// character for one so error offsets still point into the line as
+    /// written: `1.234,56` reads as `1 234.56`. Dots only group digits in
+    /// threes after at most three leading digits, so `1.5` is left as written.
+    /// Inside a function call's parentheses a comma separates arguments, so
+    /// `max(1,5)` stays two arguments. Comments are left untouched.
+    fn delocalize<'b>(&self, input: &'b str) -> Cow<'b, str> {
+        if self.locale != NumberLocale::European {
+            return Cow::Borrowed(input);
+        }
+        let code = strip_comment(input);
+        let bytes = code.as_bytes();
+        let digits_at = |from: usize| {
+            bytes.get(from..).map_or(0, |rest| {
+                rest.iter().take_while(|b| b.is_ascii_digit()).count()
+            })
+        };
+
+        // Whether each open parenthesis starts a function call's arguments
+        let mut calls: Vec<bool> = Vec::new();
+        let opens_call = |paren: usize| {
+            let name = code[..paren].trim_end();
+            let word_start = name
+                .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map_or(0, |at| at + 1);
+            name[word_start..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        };
+
+        // Only ASCII marks are swapped for ASCII marks, so the text stays UTF-8
+        let mut output = input.as_bytes().to_vec();
+        let mut index = 0;
+        while index < bytes.len() {
+            match bytes[index] {
+                b'(' => calls.push(opens_call(index)),
+                b')' => {
+                    calls.pop();
+                }
+                _ => {}
+            }
+            let starts_number = bytes[index].is_ascii_digit()
+                && (index == 0
+                    || !(bytes[index - 1].is_ascii_alphanumeric()
+                        || matches!(bytes[index - 1], b'_' | b'.' | b',')));
+            if !starts_number {
+                index += 1;
+                continue;
+            }
+            let leading = digits_at(index);
+            let mut end = index + leading;
+            while leading <= 3 && bytes.get(end) == Some(&b'.') && digits_at(end + 1) == 3 {
+                output[end] = b' ';
+                end += 4;
+            }
+            if bytes.get(end) == Some(&b',')
+                && calls.last() != Some(&true)
+                && digits_at(end + 1) > 0
+            {
+                output[end] = b'.';
+                end += 1 + digits_at(end + 1);
+            }
+            index = end;
+        }
+        if output == input.as_bytes() {
+            return Cow::Borrowed(input);
+        }
+        Cow::Owned(String::from_utf8(output).expect("only ASCII bytes were replaced"))
+    }
+}
+
+/// A parsed line and how much of it the parse used
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedLine {
+    pub ast: Ast,
+    /// Leading text was skipped because the full line did not parse
+    pub used_fuzzy: bool,
+    /// Character offset where the parsed text starts; 0 unless `used_fuzzy`
+    pub matched_suffix_start: usize,
+}
+
 /// Parse a single line of input (with fuzzy fallback for user input)
 pub fn parse_line(input: &str) -> Result<Ast, ParseError> {
+    parse_line_with(input, &NumberSyntax::default())
+}
+
+/// [`parse_line`], reading numbers in the given syntax
+pub(crate) fn parse_line_with(input: &str, numbers: &NumberSyntax<'_>) -> Result<Ast, ParseError> {
+    parse_line_detailed_with(input, numbers).map(|parsed| parsed.ast)
+}
+
+/// Parse like [`parse_line`], also reporting whether the fuzzy fallback skipped
+/// leading text (`blah blah 2 + 2` reads only `2 + 2`), so editors can flag it
+pub fn parse_line_detailed(input: &str) -> Result<ParsedLine, ParseError> {
+    parse_line_detailed_with(input, &NumberSyntax::default())
+}
+
+fn parse_line_detailed_with(
+    input: &str,
+    numbers: &NumberSyntax<'_>,
+) -> Result<ParsedLine, ParseError> {
+    let input = &*numbers.delocalize(input);
     validate_limits(input)?;
     // Try parsing the full line first
-    if let Ok(pairs) = NumrParser::parse(Rule::line, input) {
-        if let Ok(ast) = ast::build_ast(pairs) {
-            return Ok(ast);
-        }
-    }
+    let full_line_error = match NumrParser::parse(Rule::line, input) {
+        Ok(pairs) => match ast::build_ast(pairs, numbers) {
+            Ok(ast) => {
+                return Ok(ParsedLine {
+                    ast,
+                    used_fuzzy: false,
+                    matched_suffix_start: 0,
+                })
+            }
+            Err(message) => ParseError::InvalidExpression(message),
+        },
+        Err(error) => syntax_error(input, &error),
+    };
 
     // Fuzzy parsing: try suffixes starting at word/token boundaries only.
     // This strips leading prose (e.g., "pay rate = $85/hr" → "$85/hr") while
-    // avoiding O(n) parse attempts on every byte offset.
+    // avoiding O(n) parse attempts on every byte offset. Boundaries inside a
+    // trailing comment are never tried, so comment text cannot become the result.
+    let code = strip_comment(input);
     let bytes = input.as_bytes();
-    for (i, _) in input.char_indices().skip(1).take(128) {
+    for (i, _) in code.char_indices().skip(1).take(128) {
         // Only try boundaries after whitespace or punctuation
         if i > 0 && bytes[i - 1].is_ascii_alphanumeric() {
             continue;
         }
         let suffix = &input[i..];
-        if suffix.trim().is_empty() {
+        if strip_comment(suffix).trim().is_empty() {
             continue;
         }
 
         if let Ok(pairs) = NumrParser::parse(Rule::line, suffix) {
-            if let Ok(ast) = ast::build_ast(pairs) {
-                return Ok(ast);
+            if let Ok(ast) = ast::build_ast(pairs, numbers) {
+                return Ok(ParsedLine {
+                    ast,
+                    used_fuzzy: true,
+                    matched_suffix_start: input[..i].chars().count()
+                        + suffix.chars().take_while(|c| c.is_whitespace()).count(),
+                });
             }
         }
     }
 
     // If all else fails, return the original error from the full line parse
-    // or a generic error
-    Err(ParseError::InvalidSyntax)
+    Err(full_line_error)
 }
 
 /// Parse a line exactly (no fuzzy fallback) - used for continuation detection
 pub fn try_parse_exact(input: &str) -> Result<Ast, ParseError> {
+    try_parse_exact_with(input, &NumberSyntax::default())
+}
+
+/// [`try_parse_exact`], reading numbers in the given syntax
+pub(crate) fn try_parse_exact_with(
+    input: &str,
+    numbers: &NumberSyntax<'_>,
+) -> Result<Ast, ParseError> {
+    let input = &*numbers.delocalize(input);
     validate_limits(input)?;
     match NumrParser::parse(Rule::line, input) {
-        Ok(pairs) => ast::build_ast(pairs).map_err(ParseError::InvalidExpression),
-        Err(_) => Err(ParseError::InvalidSyntax),
+        Ok(pairs) => ast::build_ast(pairs, numbers).map_err(ParseError::InvalidExpression),
+        Err(error) => Err(syntax_error(input, &error)),
     }
 }
 
+/// Convert a pest failure into a syntax error at the character where matching stopped.
+fn syntax_error(input: &str, error: &pest::error::Error<Rule>) -> ParseError {
+    let byte_offset = match error.location {
+        InputLocation::Pos(position) => position,
+        InputLocation::Span((start, _)) => start,
+    };
+    let offset = input
+        .get(..byte_offset)
+        .map_or_else(|| input.chars().count(), |prefix| prefix.chars().count());
+    ParseError::InvalidSyntax { offset }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,18 +305,69 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_error_offsets() {
+        for (input, expected) in [("10 + ", 5), ("€5 * )", 5), ("5 ? 3", 2), ("sqrt(4", 6)] {
+            let error = try_parse_exact(input).unwrap_err();
+            assert_eq!(error.offset(), Some(expected), "{input}");
+        }
+        // When fuzzy fallback also fails, the full-line position is reported
+        assert_eq!(parse_line("€5 * )").unwrap_err().offset(), Some(5));
+        assert_eq!(ParseError::TooDeep { actual: 2, max: 1 }.offset(), None);
+    }
+
+    #[test]
+    fn detailed_parse_reports_the_fuzzy_fallback() {
+        let exact = parse_line_detailed("2 + 2").unwrap();
+        assert!(!exact.used_fuzzy);
+        assert_eq!(exact.matched_suffix_start, 0);
+
+        let prose = parse_line_detailed("blah blah 2 + 2").unwrap();
+        assert!(prose.used_fuzzy);
+        assert_eq!(prose.matched_suffix_start, 10);
+        assert_eq!(prose.ast, parse_line("2 + 2").unwrap());
+
+        // Offsets count characters, not bytes
+        let accented = parse_line_detailed("café total: 5 + 5").unwrap();
+        assert!(accented.used_fuzzy);
+        assert_eq!(accented.matched_suffix_start, 12);
+
+        assert!(!parse_line_detailed("# just a note").unwrap().used_fuzzy);
+        assert!(parse_line_detailed("€5 * )").is_err());
+    }
+
     #[test]
     fn comments_only_consume_the_input_size_budget() {
         let comment = format!("# {}", "+".repeat(MAX_OPERATIONS + 1));
         assert!(parse_line(&comment).is_ok());
         assert!(try_parse_exact(&comment).is_ok());
+
+        let trailing = format!("1 + 2 # {}", "+".repeat(MAX_OPERATIONS + 1));
+        assert!(try_parse_exact(&trailing).is_ok());
+    }
+
+    #[test]
+    fn trailing_comments_are_ignored() {
+        for input in [
+            "100 + 50 # groceries",
+            "100 + 50 // groceries",
+            "100 + 50 # groceries and 5 more",
+            "rent = 1000 # monthly",
+        ] {
+            assert!(try_parse_exact(input).is_ok(), "{input}");
+        }
+        assert_eq!(strip_comment("100 + 50 // food # misc"), "100 + 50 ");
+        assert_eq!(strip_comment("100"), "100");
+
+        // Comment text is never tried as a fuzzy suffix
+        assert!(parse_line("pay: # 5").is_err());
     }
 
     /// Verify grammar.pest currency_symbol rule matches CURRENCIES registry.
     /// If this test fails, you need to sync grammar.pest with types/currency.rs
     #[test]
     fn test_grammar_currency_symbols_match_registry() {
-        use crate::types::CURRENCIES;
+        use crate::types::{Currency, CURRENCIES};
         use std::collections::HashSet;
 
         // Read grammar.pest
@@ -165,7 +393,11 @@ mod tests {
                 // Single Unicode symbol OR "zł" (Polish złoty is 2-char but in grammar)
                 chars.len() == 1 || *s == "zł"
             })
+            // JP¥ and CN¥ tell apart the two currencies that share ¥
+            .chain(["JP¥", "CN¥"])
             .collect();
+        assert_eq!(Currency::parse("JP¥"), Some(Currency::JPY));
+        assert_eq!(Currency::parse("CN¥"), Some(Currency::CNY));
 
         // Check for symbols in grammar but not in registry
         let extra_in_grammar: Vec<_> = grammar_symbols.difference(&registry_symbols).collect();
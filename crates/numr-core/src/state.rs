@@ -0,0 +1,146 @@
+//! JSON export and import of the full engine state.
+//!
+//! A snapshot stores the line inputs together with everything that shapes their
+//! evaluation: exchange rates, evaluation policies, and output formatting.
+//! Values and variables are recomputed on import by replaying the lines, so a
+//! restored engine cannot disagree with its own inputs.
+
+use crate::cache::RateCache;
+use crate::error::StateError;
+use crate::eval::EvalOptions;
+use crate::types::{Currency, FormatOptions};
+use crate::Engine;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Snapshot format version, bumped on incompatible changes
+const STATE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct EngineState {
+    version: u32,
+    lines: Vec<String>,
+    rates: Vec<RateEntry>,
+    #[serde(default)]
+    options: EvalOptions,
+    #[serde(default)]
+    format_options: FormatOptions,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RateEntry {
+    from: Currency,
+    to: Currency,
+    /// Kept as a string: the workspace serializes `Decimal` as a float, which
+    /// would perturb rates on the way back in
+    rate: String,
+}
+
+impl Engine {
+    /// Export lines, exchange rates, and configuration as JSON.
+    pub fn to_json(&self) -> Result<String, StateError> {
+        let rates = self
+            .rates_snapshot()
+            .into_iter()
+            .map(|(from, to, rate)| RateEntry {
+                from,
+                to,
+                rate: rate.to_string(),
+            })
+            .collect();
+
+        let state = EngineState {
+            version: STATE_VERSION,
+            lines: self.lines.iter().map(|line| line.input.clone()).collect(),
+            rates,
+            options: self.context.options.clone(),
+            format_options: self.format_options,
+        };
+        serde_json::to_string(&state).map_err(StateError::Serialize)
+    }
+
+    /// Restore an engine exported with [`Engine::to_json`].
+    ///
+    /// Rates and configuration are applied first, then the lines are replayed.
+    /// Rates are set as if quoted again, so their reciprocals are derived the
+    /// same way as before the export.
+    pub fn from_json(json: &str) -> Result<Self, StateError> {
+        let state: EngineState = serde_json::from_str(json).map_err(StateError::Deserialize)?;
+        if state.version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(state.version));
+        }
+
+        let mut engine = Engine::new();
+        engine.context.rate_cache = RateCache::new();
+        for RateEntry { from, to, rate } in state.rates {
+            let invalid = || StateError::InvalidRate(format!("{from}/{to}: {rate}"));
+            let parsed = Decimal::from_str(&rate).map_err(|_| invalid())?;
+            engine
+                .try_set_exchange_rate(from, to, parsed)
+                .map_err(|_| invalid())?;
+        }
+        engine.context.options = state.options;
+        engine.format_options = state.format_options;
+        engine.append_lines(state.lines.iter().map(String::as_str));
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decimal, GroupSeparator, GroupSize, RoundingMode};
+
+    #[test]
+    fn test_round_trip_preserves_configuration() {
+        let mut engine = Engine::new();
+        engine.set_exchange_rate(Currency::USD, Currency::EUR, decimal("0.5"));
+        // GBP/EUR is quoted on its own rather than derived from EUR/GBP
+        engine.set_exchange_rate(Currency::EUR, Currency::GBP, decimal("0.8"));
+        engine.set_exchange_rate(Currency::GBP, Currency::EUR, decimal("1.3"));
+        engine.set_options(EvalOptions {
+            lenient_units: true,
+            ..EvalOptions::default()
+        });
+        engine.set_format_options(FormatOptions {
+            grouping: Some(GroupSeparator::Space),
+            group_size: GroupSize::Indian,
+            rounding: RoundingMode::HalfUp,
+            normalize_units: true,
+            accounting_negatives: true,
+            mark_rounded: true,
+        });
+        engine.eval("$10 in eur");
+
+        let mut restored = Engine::from_json(&engine.to_json().unwrap()).unwrap();
+        assert_eq!(restored.lines(), engine.lines());
+        assert_eq!(restored.options(), engine.options());
+        assert_eq!(restored.format_options(), engine.format_options());
+        assert_eq!(restored.lines()[0].value.to_string(), "€5.00");
+        assert_eq!(restored.rates_snapshot(), engine.rates_snapshot());
+
+        // A later reverse quote keeps the restored quote instead of replacing it
+        restored.set_exchange_rate(Currency::EUR, Currency::USD, decimal("2.1"));
+        engine.set_exchange_rate(Currency::EUR, Currency::USD, decimal("2.1"));
+        assert_eq!(restored.rates_snapshot(), engine.rates_snapshot());
+    }
+
+    #[test]
+    fn test_rejects_invalid_snapshots() {
+        assert!(matches!(
+            Engine::from_json("not json"),
+            Err(StateError::Deserialize(_))
+        ));
+        assert!(matches!(
+            Engine::from_json(r#"{"version": 99, "lines": [], "rates": []}"#),
+            Err(StateError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            Engine::from_json(
+                r#"{"version": 1, "lines": [], "rates": [{"from": "USD", "to": "EUR", "rate": "0"}]}"#
+            ),
+            Err(StateError::InvalidRate(_))
+        ));
+    }
+}
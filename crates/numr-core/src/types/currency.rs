@@ -9,6 +9,7 @@
 //! That's it! Parsing, display, highlighting, and exchange rate fetching
 //! will automatically pick up the new currency from the registry.
 
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -74,7 +75,7 @@ pub static CURRENCIES: &[CurrencyDef] = &[
         currency: Currency::JPY,
         symbol: "¥",
         code: "JPY",
-        aliases: &["¥", "jpy", "yen"],
+        aliases: &["¥", "jp¥", "jpy", "yen"],
         symbol_after: false,
         display_precision: FIAT_DISPLAY_PRECISION,
         is_crypto: false,
@@ -94,7 +95,7 @@ pub static CURRENCIES: &[CurrencyDef] = &[
         currency: Currency::CNY,
         symbol: "¥",
         code: "CNY",
-        aliases: &["cny", "rmb", "yuan"],
+        aliases: &["cn¥", "cny", "rmb", "yuan"],
         symbol_after: false,
         display_precision: FIAT_DISPLAY_PRECISION,
         is_crypto: false,
@@ -333,6 +334,66 @@ pub static CURRENCIES: &[CurrencyDef] = &[
     },
 ];
 
+/// A fixed fraction of a currency, such as satoshis for BTC.
+///
+/// Subunits are not currencies of their own: amounts written in a subunit are
+/// parsed into the parent currency, and converting into a subunit yields a
+/// plain count.
+pub struct CurrencySubunit {
+    /// The parent currency
+    pub currency: Currency,
+    /// All accepted aliases for parsing (lowercase)
+    pub aliases: &'static [&'static str],
+    /// Number of subunits in one parent unit, as a power of ten
+    pub exponent: u32,
+}
+
+impl CurrencySubunit {
+    /// Number of subunits in one parent unit
+    pub fn per_unit(&self) -> Decimal {
+        Decimal::from_i128_with_scale(10i128.pow(self.exponent), 0)
+    }
+}
+
+/// Registry of currency subunits.
+pub static CURRENCY_SUBUNITS: &[CurrencySubunit] = &[
+    CurrencySubunit {
+        currency: Currency::BTC,
+        aliases: &["sat", "sats", "satoshi", "satoshis"],
+        exponent: 8,
+    },
+    CurrencySubunit {
+        currency: Currency::ETH,
+        aliases: &["gwei"],
+        exponent: 9,
+    },
+    CurrencySubunit {
+        currency: Currency::ETH,
+        aliases: &["wei"],
+        exponent: 18,
+    },
+];
+
+/// Textual currency indicators found in pasted financial data, read as the
+/// currency they stand for when `EvalOptions::normalize_currency_symbols` is set.
+///
+/// Ambiguous indicators such as `kr` (SEK, NOK, or DKK) are left out; callers
+/// can map them with `Engine::add_currency_alias`.
+pub static CURRENCY_TEXT_ALIASES: &[(&str, Currency)] = &[
+    ("US$", Currency::USD),
+    ("C$", Currency::CAD),
+    ("CA$", Currency::CAD),
+    ("A$", Currency::AUD),
+    ("AU$", Currency::AUD),
+    ("Rs.", Currency::INR),
+    ("Rs", Currency::INR),
+    ("CN¥", Currency::CNY),
+    ("RMB", Currency::CNY),
+    ("JP¥", Currency::JPY),
+    ("SFr.", Currency::CHF),
+    ("NIS", Currency::ILS),
+];
+
 /// Supported currencies
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Currency {
@@ -436,10 +497,41 @@ impl Currency {
             .map(|d| d.currency)
     }
 
+    /// Parse a currency subunit (e.g., "sats", "gwei")
+    pub fn parse_subunit(s: &str) -> Option<&'static CurrencySubunit> {
+        let lower = s.to_lowercase();
+        CURRENCY_SUBUNITS
+            .iter()
+            .find(|subunit| subunit.aliases.contains(&lower.as_str()))
+    }
+
+    /// Get all currency subunit aliases (for UI highlighting)
+    pub fn all_subunit_aliases() -> impl Iterator<Item = &'static str> {
+        CURRENCY_SUBUNITS
+            .iter()
+            .flat_map(|subunit| subunit.aliases.iter().copied())
+    }
+
     /// Iterator over all currencies
     pub fn all() -> impl Iterator<Item = Currency> {
         CURRENCIES.iter().map(|d| d.currency)
     }
+
+    /// Iterator over fiat currencies, in catalog order
+    pub fn fiat() -> impl Iterator<Item = Currency> {
+        CURRENCIES
+            .iter()
+            .filter(|d| !d.is_crypto)
+            .map(|d| d.currency)
+    }
+
+    /// Iterator over cryptocurrencies, in catalog order
+    pub fn crypto() -> impl Iterator<Item = Currency> {
+        CURRENCIES
+            .iter()
+            .filter(|d| d.is_crypto)
+            .map(|d| d.currency)
+    }
 }
 
 impl fmt::Display for Currency {
@@ -471,6 +563,20 @@ mod tests {
         assert_eq!(Currency::parse("bitcoin"), Some(Currency::BTC));
     }
 
+    #[test]
+    fn test_parse_subunits() {
+        let sats = Currency::parse_subunit("Sats").unwrap();
+        assert_eq!(sats.currency, Currency::BTC);
+        assert_eq!(sats.per_unit(), Decimal::from(100_000_000));
+        assert_eq!(
+            Currency::parse_subunit("gwei").unwrap().currency,
+            Currency::ETH
+        );
+        assert!(Currency::parse_subunit("btc").is_none());
+        // Subunit aliases must not shadow currencies
+        assert!(Currency::all_subunit_aliases().all(|alias| Currency::parse(alias).is_none()));
+    }
+
     #[test]
     fn test_all_currencies_have_defs() {
         for currency in Currency::all() {
@@ -480,4 +586,16 @@ mod tests {
             assert!(!def.aliases.is_empty());
         }
     }
+
+    #[test]
+    fn test_fiat_and_crypto_split_the_catalog() {
+        assert!(Currency::crypto().any(|c| c == Currency::BTC));
+        assert!(Currency::fiat().any(|c| c == Currency::USD));
+        assert!(!Currency::fiat().any(|c| c == Currency::BTC));
+        assert!(!Currency::crypto().any(|c| c == Currency::USD));
+        assert_eq!(
+            Currency::fiat().count() + Currency::crypto().count(),
+            Currency::all().count()
+        );
+    }
 }
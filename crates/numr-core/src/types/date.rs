@@ -0,0 +1,208 @@
+//! Calendar dates for relative date expressions ("days until 2025-01-01")
+
+use std::fmt;
+
+/// Years a date may fall in. Keeps day counts far from overflow and dates
+/// printable as four digits.
+const YEARS: std::ops::RangeInclusive<i64> = -9999..=9999;
+
+/// A proleptic Gregorian calendar date, stored as days since 1970-01-01
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    days: i64,
+}
+
+impl Date {
+    /// Build a date from its year, month (1-12), and day of month.
+    /// Returns `None` for days that do not exist, such as February 30, and for
+    /// years beyond ±9999.
+    #[must_use]
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Option<Self> {
+        if !YEARS.contains(&year)
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+        {
+            return None;
+        }
+        Some(Self {
+            days: days_from_civil(year, month, day),
+        })
+    }
+
+    /// Parse an ISO 8601 calendar date: `2025-01-01`
+    #[must_use]
+    pub fn parse_iso(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        Self::from_ymd(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+    }
+
+    /// Today's date in UTC according to the system clock.
+    /// Returns `None` where no system clock is available (WebAssembly).
+    #[must_use]
+    pub fn today() -> Option<Self> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let seconds = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()?
+                .as_secs();
+            Some(Self {
+                days: i64::try_from(seconds / 86_400).ok()?,
+            })
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+    }
+
+    /// Days from `self` to `other`, negative when `other` is earlier
+    #[must_use]
+    pub fn days_until(self, other: Self) -> i64 {
+        other.days - self.days
+    }
+
+    /// Year, month, and day of month
+    #[must_use]
+    pub fn ymd(self) -> (i64, u32, u32) {
+        civil_from_days(self.days)
+    }
+
+    /// The date `days` later, or earlier when negative. `None` past year ±9999.
+    #[must_use]
+    pub fn add_days(self, days: i64) -> Option<Self> {
+        let days = self.days.checked_add(days)?;
+        let first = days_from_civil(*YEARS.start(), 1, 1);
+        let last = days_from_civil(*YEARS.end(), 12, 31);
+        (first..=last).contains(&days).then_some(Self { days })
+    }
+
+    /// The same day `months` later, or earlier when negative. Days past the end
+    /// of the target month move back to its last day: Jan 31 + 1 month = Feb 28.
+    #[must_use]
+    pub fn add_months(self, months: i64) -> Option<Self> {
+        let (year, month, day) = self.ymd();
+        let index = year
+            .checked_mul(12)?
+            .checked_add(i64::from(month) - 1)?
+            .checked_add(months)?;
+        let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+        Self::from_ymd(year, month, day.min(days_in_month(year, month)))
+    }
+}
+
+impl serde::Serialize for Date {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Day counting over 400-year eras, after Howard Hinnant's `days_from_civil`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display_round_trip() {
+        for text in ["1970-01-01", "2024-02-29", "2025-01-01", "1999-12-31"] {
+            assert_eq!(Date::parse_iso(text).unwrap().to_string(), text);
+        }
+        assert_eq!(Date::parse_iso("1970-01-01").unwrap().days, 0);
+        assert!(Date::parse_iso("2025-02-29").is_none());
+        assert!(Date::parse_iso("2025-13-01").is_none());
+        assert!(Date::parse_iso("25-01-01").is_none());
+    }
+
+    #[test]
+    fn test_far_dates_are_rejected() {
+        assert!(Date::from_ymd(9999, 12, 31).is_some());
+        assert!(Date::from_ymd(10_000, 1, 1).is_none());
+        assert!(Date::from_ymd(700_000_000_000_000_000, 1, 1).is_none());
+        let today = Date::from_ymd(2025, 6, 1).unwrap();
+        assert!(today.add_months(700_000_000_000_000_000 * 12).is_none());
+        assert!(today.add_months(-12 * 20_000).is_none());
+        assert!(today.add_days(i64::MAX).is_none());
+        assert!(Date::from_ymd(9999, 12, 31).unwrap().add_days(1).is_none());
+    }
+
+    #[test]
+    fn test_days_until() {
+        let start = Date::from_ymd(2024, 12, 25).unwrap();
+        let new_year = Date::from_ymd(2025, 1, 1).unwrap();
+        assert_eq!(start.days_until(new_year), 7);
+        assert_eq!(new_year.days_until(start), -7);
+        // 2024 is a leap year
+        let next = Date::from_ymd(2025, 12, 25).unwrap();
+        assert_eq!(start.days_until(next), 365);
+        assert_eq!(
+            Date::from_ymd(2024, 1, 1).unwrap().days_until(new_year),
+            366
+        );
+    }
+
+    #[test]
+    fn test_add_days_and_months() {
+        let date = |text| Date::parse_iso(text).unwrap();
+        assert_eq!(date("2024-12-25").add_days(7), Some(date("2025-01-01")));
+        assert_eq!(date("2025-01-01").add_days(-1), Some(date("2024-12-31")));
+        assert_eq!(date("2025-01-31").add_months(1), Some(date("2025-02-28")));
+        assert_eq!(date("2024-01-31").add_months(1), Some(date("2024-02-29")));
+        assert_eq!(date("2025-03-15").add_months(-3), Some(date("2024-12-15")));
+        assert_eq!(date("2024-02-29").add_months(12), Some(date("2025-02-28")));
+        assert_eq!(date("2025-01-01").add_days(i64::MAX), None);
+    }
+}
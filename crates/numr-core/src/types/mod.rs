@@ -1,9 +1,16 @@
 //! Value types for numr calculations
 
 pub mod currency;
+mod date;
 pub mod unit;
 mod value;
 
-pub use currency::{Currency, CurrencyDef, CURRENCIES};
+pub use currency::{
+    Currency, CurrencyDef, CurrencySubunit, CURRENCIES, CURRENCY_SUBUNITS, CURRENCY_TEXT_ALIASES,
+};
+pub use date::Date;
 pub use unit::{CompoundUnit, Dimensions, RuntimeUnitDef, UNITS};
-pub use value::{format_currency_value, format_number, NumberBase, Value};
+pub use value::{
+    format_currency_value, format_currency_value_with, format_number, format_number_with,
+    FormatOptions, GroupSeparator, GroupSize, NumberBase, RoundingMode, Value,
+};
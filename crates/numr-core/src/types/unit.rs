@@ -4,6 +4,7 @@
 //! Each unit has a scale factor and dimension exponents.
 
 use rust_decimal::Decimal;
+use rust_decimal::MathematicalOps;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
@@ -144,6 +145,103 @@ impl Dimensions {
     }
 }
 
+/// Human-readable names for dimensions with a common physical quantity name.
+const NAMED_DIMENSIONS: &[(Dimensions, &str)] = &[
+    (Dimensions::ZERO, "dimensionless"),
+    (Dimensions::length(1), "length"),
+    (Dimensions::length(2), "area"),
+    (Dimensions::length(3), "volume"),
+    (Dimensions::mass(1), "mass"),
+    (Dimensions::time(1), "time"),
+    (Dimensions::time(-1), "frequency"),
+    (Dimensions::temperature(1), "temperature"),
+    (Dimensions::data(1), "data"),
+    (Dimensions::angle(1), "angle"),
+    (
+        Dimensions {
+            length: 1,
+            time: -1,
+            ..Dimensions::ZERO
+        },
+        "speed",
+    ),
+    (
+        Dimensions {
+            length: 1,
+            time: -2,
+            ..Dimensions::ZERO
+        },
+        "acceleration",
+    ),
+    (
+        Dimensions {
+            length: 1,
+            mass: 1,
+            time: -2,
+            ..Dimensions::ZERO
+        },
+        "force",
+    ),
+    (
+        Dimensions {
+            length: 2,
+            mass: 1,
+            time: -2,
+            ..Dimensions::ZERO
+        },
+        "energy",
+    ),
+    (
+        Dimensions {
+            length: 2,
+            mass: 1,
+            time: -3,
+            ..Dimensions::ZERO
+        },
+        "power",
+    ),
+    (
+        Dimensions {
+            length: -1,
+            mass: 1,
+            time: -2,
+            ..Dimensions::ZERO
+        },
+        "pressure",
+    ),
+    (
+        Dimensions {
+            data: 1,
+            time: -1,
+            ..Dimensions::ZERO
+        },
+        "data rate",
+    ),
+];
+
+/// Names the physical quantity ("length", "speed"), falling back to base
+/// dimension exponents ("length·mass⁻¹") for unnamed combinations.
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((_, name)) = NAMED_DIMENSIONS.iter().find(|(dims, _)| dims == self) {
+            return f.write_str(name);
+        }
+        let parts: Vec<String> = [
+            ("length", self.length),
+            ("mass", self.mass),
+            ("time", self.time),
+            ("temperature", self.temperature),
+            ("data", self.data),
+            ("angle", self.angle),
+        ]
+        .into_iter()
+        .filter(|(_, exp)| *exp != 0)
+        .map(|(name, exp)| format!("{name}{}", format_exponent(exp)))
+        .collect();
+        f.write_str(&parts.join("·"))
+    }
+}
+
 // ============================================================================
 // COMPOUND UNIT
 // ============================================================================
@@ -160,6 +258,10 @@ pub struct CompoundUnit {
     pub dimensions: Dimensions,
     /// Display name (e.g., "km", "m/s", "m²")
     pub symbol: String,
+    /// A difference between two readings ("5 C°") rather than a reading on an
+    /// offset scale ("20 °C"). Deltas convert by factor only.
+    #[serde(default)]
+    pub is_delta: bool,
 }
 
 impl CompoundUnit {
@@ -170,6 +272,7 @@ impl CompoundUnit {
             offset: Decimal::ZERO,
             dimensions,
             symbol: symbol.into(),
+            is_delta: false,
         }
     }
 
@@ -185,6 +288,7 @@ impl CompoundUnit {
             offset,
             dimensions,
             symbol: symbol.into(),
+            is_delta: false,
         }
     }
 
@@ -199,7 +303,10 @@ impl CompoundUnit {
     }
 
     /// Multiply two units (for operations like 5m * 10m = 50m²).
+    /// Temperature readings (`20 °C`) are refused, since the product would
+    /// silently drop the scale's offset; differences (`20 C°`) multiply normally.
     pub fn try_multiply(&self, other: &Self) -> Result<Self, EvalError> {
+        self.reject_temperature_reading("multiply", other)?;
         let new_dims =
             self.dimensions
                 .checked_multiply(other.dimensions)
@@ -217,11 +324,14 @@ impl CompoundUnit {
             offset: Decimal::ZERO,
             dimensions: new_dims,
             symbol: smart_symbol(&self.symbol, &other.symbol, &new_dims, new_factor, true),
+            is_delta: false,
         })
     }
 
     /// Divide two units (for operations like 100km / 2h = 50km/h).
+    /// Temperature readings are refused, as in [`CompoundUnit::try_multiply`].
     pub fn try_divide(&self, other: &Self) -> Result<Self, EvalError> {
+        self.reject_temperature_reading("divide", other)?;
         let new_dims =
             self.dimensions
                 .checked_divide(other.dimensions)
@@ -239,12 +349,105 @@ impl CompoundUnit {
             offset: Decimal::ZERO,
             dimensions: new_dims,
             symbol: smart_symbol(&self.symbol, &other.symbol, &new_dims, new_factor, false),
+            is_delta: false,
         })
     }
 
+    fn reject_temperature_reading(&self, operation: &str, other: &Self) -> Result<(), EvalError> {
+        match [self, other]
+            .into_iter()
+            .find(|unit| unit.is_absolute_temperature())
+        {
+            Some(reading) => Err(EvalError::InvalidOperands(format!(
+                "Cannot {operation} {} readings; use a difference such as 5 {}",
+                reading.symbol,
+                reading.to_delta().symbol
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// True when a registered unit measures this unit's dimensions, as
+    /// `N` does for `kg·m/s²`. Products such as `kg·°C` have none.
+    #[must_use]
+    pub fn has_named_dimensions(&self) -> bool {
+        self.dimensions.is_dimensionless()
+            || UNITS.iter().any(|def| def.dimensions == self.dimensions)
+    }
+
+    /// Raise a unit to an integer power (for input like `5 m^2` or `s^-1`).
+    pub fn try_power(&self, exp: i8) -> Result<Self, EvalError> {
+        if exp == 0 {
+            return Err(EvalError::InvalidOperands(
+                "unit exponent must be non-zero".to_string(),
+            ));
+        }
+        let new_dims = self
+            .dimensions
+            .checked_power(exp)
+            .ok_or(EvalError::Overflow {
+                operation: "combining unit dimensions",
+            })?;
+        let new_factor = self
+            .factor
+            .checked_powi(i64::from(exp))
+            .ok_or(EvalError::Overflow {
+                operation: "combining unit scales",
+            })?;
+        let symbol = match find_unit_by_dimensions_and_factor(&new_dims, new_factor) {
+            Some(matching) => matching.to_string(),
+            None if self.symbol.contains(['/', '·']) => {
+                format!("({}){}", self.symbol, format_exponent(exp))
+            }
+            None => format!("{}{}", self.symbol, format_exponent(exp)),
+        };
+        Ok(Self {
+            factor: new_factor,
+            offset: Decimal::ZERO,
+            dimensions: new_dims,
+            symbol,
+            is_delta: false,
+        })
+    }
+
+    /// The difference unit for this scale: "°C" becomes "C°", and a delta
+    /// converts without the scale's offset.
+    pub fn to_delta(&self) -> Self {
+        if self.is_delta {
+            return self.clone();
+        }
+        let symbol = match self.symbol.strip_prefix('°') {
+            Some(scale) => format!("{scale}°"),
+            None => format!("Δ{}", self.symbol),
+        };
+        Self {
+            factor: self.factor,
+            offset: Decimal::ZERO,
+            dimensions: self.dimensions,
+            symbol,
+            is_delta: true,
+        }
+    }
+
+    /// Check if this is a temperature reading rather than a temperature difference
+    pub fn is_absolute_temperature(&self) -> bool {
+        self.dimensions == Dimensions::temperature(1) && !self.is_delta
+    }
+
     /// Check if this unit can be converted to another
     pub fn can_convert_to(&self, other: &Self) -> bool {
+        self.dimensions == other.dimensions && self.is_delta == other.is_delta
+    }
+
+    /// Check if two units denote the same scale, so conversion is an identity.
+    ///
+    /// Derived units (e.g., `km / h`) can carry a factor that differs from the
+    /// registered unit only by decimal rounding, so a matching symbol also counts.
+    pub fn is_same_unit(&self, other: &Self) -> bool {
         self.dimensions == other.dimensions
+            && self.offset == other.offset
+            && self.is_delta == other.is_delta
+            && (self.factor == other.factor || self.symbol == other.symbol)
     }
 
     /// Convert a value from this unit to another unit.
@@ -256,6 +459,9 @@ impl CompoundUnit {
         if !self.can_convert_to(target) {
             return Ok(None);
         }
+        if self.is_same_unit(target) {
+            return Ok(Some(value));
+        }
         let si_value = self.checked_to_si(value).ok_or(EvalError::Overflow {
             operation: "converting a unit to its base scale",
         })?;
@@ -280,6 +486,7 @@ impl std::hash::Hash for CompoundUnit {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.symbol.hash(state);
         self.dimensions.hash(state);
+        self.is_delta.hash(state);
     }
 }
 
@@ -869,6 +1076,7 @@ impl RuntimeUnitDef {
             offset: self.offset,
             dimensions: self.dimensions,
             symbol: self.symbol.to_string(),
+            is_delta: false,
         }
     }
 }
@@ -889,6 +1097,45 @@ pub fn parse_unit(s: &str) -> Option<CompoundUnit> {
         .map(|def| def.to_compound_unit())
 }
 
+/// Parse a unit written as an expression, as in a conversion target:
+/// "km", "m²", "ft^3", "km/h", "kg*m/s^2"
+pub fn parse_unit_expression(s: &str) -> Option<CompoundUnit> {
+    if let Some(unit) = parse_unit(s) {
+        return Some(unit);
+    }
+    let mut combined: Option<CompoundUnit> = None;
+    let mut divide = false;
+    let mut rest = s;
+    loop {
+        let end = rest.find(['*', '·', '/']).unwrap_or(rest.len());
+        let factor = parse_unit_factor(&rest[..end])?;
+        combined = Some(match combined {
+            None => factor,
+            Some(left) if divide => left.try_divide(&factor).ok()?,
+            Some(left) => left.try_multiply(&factor).ok()?,
+        });
+        let Some(op) = rest[end..].chars().next() else {
+            return combined;
+        };
+        divide = op == '/';
+        rest = &rest[end + op.len_utf8()..];
+    }
+}
+
+/// Parse one unit with an optional exponent: "m", "m²", "s^-1"
+fn parse_unit_factor(s: &str) -> Option<CompoundUnit> {
+    let (name, exponent) = if let Some(name) = s.strip_suffix('²') {
+        (name, 2)
+    } else if let Some(name) = s.strip_suffix('³') {
+        (name, 3)
+    } else if let Some((name, exponent)) = s.split_once('^') {
+        (name, exponent.parse::<i8>().ok()?)
+    } else {
+        return parse_unit(s);
+    };
+    parse_unit(name)?.try_power(exponent).ok()
+}
+
 /// Get all unit aliases (for syntax highlighting)
 pub fn all_aliases() -> impl Iterator<Item = &'static str> {
     UNITS.iter().flat_map(|d| d.aliases.iter().copied())
@@ -899,6 +1146,63 @@ pub fn all_symbols() -> impl Iterator<Item = &'static str> {
     UNITS.iter().map(|d| d.symbol)
 }
 
+/// The unit of `unit`'s own scale family (metric prefixes, or binary
+/// multiples for data) that shows `amount` most readably: the largest one in
+/// which the amount is at least 1, or the smallest when none is.
+/// Returns `None` when `unit` is already that unit, or is an offset scale,
+/// a temperature difference, or outside the registry's families.
+#[must_use]
+pub fn readable_unit(amount: Decimal, unit: &CompoundUnit) -> Option<(Decimal, CompoundUnit)> {
+    if amount.is_zero() || unit.is_delta || !unit.offset.is_zero() {
+        return None;
+    }
+    let mut family: Vec<&RuntimeUnitDef> = UNITS
+        .iter()
+        .filter(|def| def.dimensions == unit.dimensions && def.offset.is_zero())
+        .filter(|def| {
+            def.factor
+                .checked_div(unit.factor)
+                .is_some_and(is_scale_step)
+        })
+        .collect();
+    family.sort_by_key(|def| std::cmp::Reverse(def.factor));
+
+    let in_unit = |def: &RuntimeUnitDef| {
+        amount
+            .checked_mul(unit.factor)
+            .and_then(|si| si.checked_div(def.factor))
+    };
+    let chosen = family
+        .iter()
+        .copied()
+        .find(|def| in_unit(def).is_some_and(|value| value.abs() >= Decimal::ONE))
+        .or_else(|| family.last().copied())?;
+    if chosen.symbol == unit.symbol {
+        return None;
+    }
+    Some((in_unit(chosen)?.normalize(), chosen.to_compound_unit()))
+}
+
+/// Whether two units' factors differ by a power of 10 or of 1024
+fn is_scale_step(ratio: Decimal) -> bool {
+    let Some(mut step) = (if ratio >= Decimal::ONE {
+        Some(ratio)
+    } else {
+        Decimal::ONE.checked_div(ratio)
+    }) else {
+        return false;
+    };
+    if !step.fract().is_zero() {
+        return false;
+    }
+    for base in [Decimal::TEN, Decimal::from(1024)] {
+        while step > Decimal::ONE && (step % base).is_zero() {
+            step /= base;
+        }
+    }
+    step == Decimal::ONE
+}
+
 pub fn try_convert(
     value: Decimal,
     from: &CompoundUnit,
@@ -1007,6 +1311,31 @@ fn format_compound_symbol(left: &str, right: &str, multiply: bool) -> String {
 mod tests {
     use super::*;
 
+    fn readable(amount: &str, symbol: &str) -> Option<String> {
+        readable_unit(d(amount), &parse_unit(symbol).unwrap())
+            .map(|(amount, unit)| format!("{amount} {}", unit.symbol))
+    }
+
+    #[test]
+    fn test_readable_unit_across_scales() {
+        // Length
+        assert_eq!(readable("9500000", "m").as_deref(), Some("9500 km"));
+        assert_eq!(readable("0.0005", "m").as_deref(), Some("0.5 mm"));
+        assert_eq!(readable("150", "cm").as_deref(), Some("1.5 m"));
+        assert_eq!(readable("-2500", "m").as_deref(), Some("-2.5 km"));
+        assert_eq!(readable("500", "m"), None);
+        // Mass
+        assert_eq!(readable("3000", "g").as_deref(), Some("3 kg"));
+        assert_eq!(readable("0.25", "g").as_deref(), Some("250 mg"));
+        // Data steps by 1024, and never turns bytes into bits
+        assert_eq!(readable("1536", "MB").as_deref(), Some("1.5 GB"));
+        assert_eq!(readable("0.5", "KB").as_deref(), Some("512 B"));
+        assert_eq!(readable("0.5", "B"), None);
+        // Imperial units and offset scales are left alone
+        assert_eq!(readable("10000", "ft"), None);
+        assert_eq!(readable("5000", "C"), None);
+    }
+
     #[test]
     fn test_dimensions_multiply() {
         let length = Dimensions::length(1);
@@ -1057,6 +1386,88 @@ mod tests {
         assert_eq!(kmh.dimensions.time, -1);
     }
 
+    #[test]
+    fn test_same_unit_conversion_is_identity() {
+        let kph = parse_unit("kph").unwrap();
+        let derived = parse_unit("km")
+            .unwrap()
+            .try_divide(&parse_unit("h").unwrap())
+            .unwrap();
+        assert!(derived.is_same_unit(&kph));
+        let value = d("10") / d("3");
+        assert_eq!(derived.try_convert_to(value, &kph).unwrap(), Some(value));
+
+        let fahrenheit = parse_unit("F").unwrap();
+        assert_eq!(
+            fahrenheit.try_convert_to(d("98.6"), &fahrenheit).unwrap(),
+            Some(d("98.6"))
+        );
+        assert!(!fahrenheit.is_same_unit(&parse_unit("C").unwrap()));
+    }
+
+    #[test]
+    fn test_unit_power() {
+        let metre = parse_unit("m").unwrap();
+        let square = metre.try_power(2).unwrap();
+        assert_eq!(square.dimensions, Dimensions::length(2));
+        assert_eq!(square.symbol, "m²");
+
+        let per_second = parse_unit("s").unwrap().try_power(-1).unwrap();
+        assert_eq!(per_second.dimensions, Dimensions::time(-1));
+
+        let per_km = parse_unit("km").unwrap().try_power(-2).unwrap();
+        assert_eq!(per_km.factor, d("0.000001"));
+
+        assert!(metre.try_power(0).is_err());
+    }
+
+    #[test]
+    fn test_parse_unit_expression() {
+        let square_metre = parse_unit_expression("m²").unwrap();
+        assert_eq!(square_metre, parse_unit_expression("m^2").unwrap());
+        assert_eq!(square_metre.dimensions.length, 2);
+
+        let speed = parse_unit_expression("km/h").unwrap();
+        assert_eq!(speed.symbol, "km/h");
+        let force = parse_unit_expression("kg*m/s^2").unwrap();
+        assert_eq!(force.dimensions, parse_unit("N").unwrap().dimensions);
+
+        assert!(parse_unit_expression("m²x").is_none());
+        assert!(parse_unit_expression("km/").is_none());
+        assert!(parse_unit_expression("EUR").is_none());
+    }
+
+    #[test]
+    fn test_temperature_delta() {
+        let celsius = parse_unit("C").unwrap();
+        let delta = celsius.to_delta();
+        assert_eq!(delta.symbol, "C°");
+        assert!(delta.is_delta && !delta.is_absolute_temperature());
+        assert!(celsius.is_absolute_temperature());
+        assert!(!celsius.can_convert_to(&delta));
+
+        // Differences convert by factor only
+        let fahrenheit = parse_unit("F").unwrap().to_delta();
+        assert_eq!(fahrenheit.offset, Decimal::ZERO);
+        let converted = fahrenheit.try_convert_to(d("9"), &delta).unwrap().unwrap();
+        assert_eq!(converted.round_dp(10), d("5"));
+        assert_eq!(parse_unit("K").unwrap().to_delta().symbol, "ΔK");
+    }
+
+    #[test]
+    fn test_dimension_names() {
+        assert_eq!(Dimensions::length(1).to_string(), "length");
+        assert_eq!(Dimensions::mass(1).to_string(), "mass");
+        assert_eq!(parse_unit("kph").unwrap().dimensions.to_string(), "speed");
+        assert_eq!(parse_unit("kWh").unwrap().dimensions.to_string(), "energy");
+        let odd = Dimensions {
+            length: 1,
+            mass: -1,
+            ..Dimensions::ZERO
+        };
+        assert_eq!(odd.to_string(), "length·mass⁻¹");
+    }
+
     #[test]
     fn test_format_exponent() {
         assert_eq!(format_exponent(2), "²");
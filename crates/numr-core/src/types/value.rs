@@ -1,13 +1,19 @@
 //! Core value representation
 
-use super::{CompoundUnit, Currency};
+use super::{CompoundUnit, Currency, Date};
+use crate::cache::RateCache;
 use crate::EvalError;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 
 /// Number of decimal places for display formatting
 const DISPLAY_PRECISION: u32 = 2;
 
+/// Decimal places kept in a converted amount before comparing, so a derived
+/// reciprocal rate (1 / 3) does not leave a remainder in the last digit
+const COMPARE_PRECISION: u32 = 12;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NumberBase {
     Binary,
@@ -24,6 +30,128 @@ impl NumberBase {
     }
 }
 
+/// Separator inserted between digit groups of the integer part
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupSeparator {
+    /// `1,234,567.89`
+    Comma,
+    /// `1 234 567.89`
+    Space,
+    /// `1.234.567,89` (the decimal mark becomes a comma)
+    Dot,
+}
+
+impl GroupSeparator {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "comma" | "," => Some(Self::Comma),
+            "space" | " " => Some(Self::Space),
+            "dot" | "." => Some(Self::Dot),
+            _ => None,
+        }
+    }
+
+    fn separator(self) -> char {
+        match self {
+            Self::Comma => ',',
+            Self::Space => ' ',
+            Self::Dot => '.',
+        }
+    }
+
+    fn decimal_mark(self) -> char {
+        match self {
+            Self::Comma | Self::Space => '.',
+            Self::Dot => ',',
+        }
+    }
+}
+
+/// Sizes of the digit groups that a [`GroupSeparator`] separates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupSize {
+    /// Groups of three: `12,345,678`
+    #[default]
+    Thousands,
+    /// Three digits, then groups of two (lakh and crore): `1,23,45,678`
+    Indian,
+}
+
+impl GroupSize {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "thousands" | "3" => Some(Self::Thousands),
+            "indian" | "lakh" => Some(Self::Indian),
+            _ => None,
+        }
+    }
+
+    /// Whether a separator goes before the last `digits_after` integer digits
+    fn is_boundary(self, digits_after: usize) -> bool {
+        match self {
+            Self::Thousands => digits_after.is_multiple_of(3),
+            Self::Indian => digits_after >= 3 && (digits_after - 3).is_multiple_of(2),
+        }
+    }
+}
+
+/// How digits beyond the kept precision are dropped, by `round()` and for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Midpoints go to the even neighbour (banker's rounding): `2.5` → `2`, `3.5` → `4`
+    #[default]
+    HalfEven,
+    /// Midpoints go away from zero: `2.5` → `3`, `-2.5` → `-3`
+    HalfUp,
+    /// Extra digits are cut off: `2.7` → `2`, `-2.7` → `-2`
+    Truncate,
+}
+
+impl RoundingMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "half-even" | "bankers" => Some(Self::HalfEven),
+            "half-up" => Some(Self::HalfUp),
+            "truncate" => Some(Self::Truncate),
+            _ => None,
+        }
+    }
+
+    /// The matching `rust_decimal` strategy
+    #[must_use]
+    pub fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Self::Truncate => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+/// Output formatting preferences.
+///
+/// The default matches `Display`, which keeps results parseable as input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Group integer digits in thousands (off by default)
+    pub grouping: Option<GroupSeparator>,
+    /// Where `grouping` separators go
+    pub group_size: GroupSize,
+    /// How amounts are rounded to the displayed precision
+    pub rounding: RoundingMode,
+    /// Show quantities in the most readable unit of their scale family
+    /// (`9500000 m` as `9500 km`); see [`Value::normalized`]
+    pub normalize_units: bool,
+    /// Show negative money in parentheses, as in accounting: `($100.00)`
+    pub accounting_negatives: bool,
+    /// Append `…` to amounts whose displayed digits were rounded (`1/3` as `0.33…`)
+    pub mark_rounded: bool,
+}
+
 /// A computed value with optional unit/currency
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Value {
@@ -37,6 +165,14 @@ pub enum Value {
     Currency { amount: Decimal, currency: Currency },
     /// Value with a physical unit (simple or compound, e.g., km, m², km/h)
     WithCompoundUnit { amount: Decimal, unit: CompoundUnit },
+    /// Price per unit of a physical quantity (e.g., $20/kg)
+    CurrencyRate {
+        amount: Decimal,
+        currency: Currency,
+        per: CompoundUnit,
+    },
+    /// Calendar date ("3 days ago")
+    Date(Date),
     /// No value (empty line or comment)
     Empty,
     /// Error during evaluation
@@ -59,6 +195,15 @@ impl Value {
         Value::WithCompoundUnit { amount, unit }
     }
 
+    /// Create a price per unit (e.g., $20/kg)
+    pub fn currency_rate(amount: Decimal, currency: Currency, per: CompoundUnit) -> Self {
+        Value::CurrencyRate {
+            amount,
+            currency,
+            per,
+        }
+    }
+
     /// Create a typed evaluation error from a compatibility message.
     pub fn error(error: impl Into<EvalError>) -> Self {
         Value::Error(error.into())
@@ -72,10 +217,74 @@ impl Value {
             Value::Percentage(p) => Some(*p),
             Value::Currency { amount, .. } => Some(*amount),
             Value::WithCompoundUnit { amount, .. } => Some(*amount),
-            Value::Empty | Value::Error(_) => None,
+            Value::CurrencyRate { amount, .. } => Some(*amount),
+            Value::Date(_) | Value::Empty | Value::Error(_) => None,
         }
     }
 
+    /// Amount and currency of a currency value
+    #[must_use]
+    pub fn as_currency(&self) -> Option<(Decimal, Currency)> {
+        match self {
+            Value::Currency { amount, currency } => Some((*amount, *currency)),
+            _ => None,
+        }
+    }
+
+    /// Amount and unit of a physical quantity
+    #[must_use]
+    pub fn as_unit(&self) -> Option<(Decimal, &CompoundUnit)> {
+        match self {
+            Value::WithCompoundUnit { amount, unit } => Some((*amount, unit)),
+            _ => None,
+        }
+    }
+
+    /// Order two values after converting `other` into this value's currency or
+    /// unit, so `$100` is greater than `€50` when a euro buys less than two
+    /// dollars. Plain numbers and percentages compare by their amounts and dates
+    /// by day. Returns `None` for values of different kinds, for currencies
+    /// without a rate between them, and for errors, empty lines, and prices per unit.
+    #[must_use]
+    pub fn compare_with(&self, other: &Value, rates: &RateCache) -> Option<Ordering> {
+        match (self, other) {
+            (
+                Value::Number(_) | Value::BaseNumber { .. } | Value::Percentage(_),
+                Value::Number(_) | Value::BaseNumber { .. } | Value::Percentage(_),
+            ) => self.as_decimal()?.partial_cmp(&other.as_decimal()?),
+            (
+                Value::Currency { amount, currency },
+                Value::Currency {
+                    amount: other_amount,
+                    currency: other_currency,
+                },
+            ) => {
+                let rate = rates.try_get_rate(*other_currency, *currency).ok()??;
+                let converted = other_amount.checked_mul(rate)?;
+                amount.partial_cmp(&converted.round_dp(COMPARE_PRECISION))
+            }
+            (
+                Value::WithCompoundUnit { amount, unit },
+                Value::WithCompoundUnit {
+                    amount: other_amount,
+                    unit: other_unit,
+                },
+            ) => {
+                let converted = other_unit.try_convert_to(*other_amount, unit).ok()??;
+                amount.partial_cmp(&converted.round_dp(COMPARE_PRECISION))
+            }
+            (Value::Date(date), Value::Date(other_date)) => date.partial_cmp(other_date),
+            _ => None,
+        }
+    }
+
+    /// Number of digits kept after the decimal point in the stored amount.
+    /// Display rounds to a few places; this shows the precision underneath.
+    #[must_use]
+    pub fn scale(&self) -> Option<u32> {
+        self.as_decimal().map(|amount| amount.scale())
+    }
+
     /// Get the numeric value as f64 (for backwards compatibility)
     pub fn as_f64(&self) -> Option<f64> {
         use rust_decimal::prelude::ToPrimitive;
@@ -92,6 +301,46 @@ impl Value {
         matches!(self, Value::Error(_))
     }
 
+    /// The same quantity in the unit of its scale family that reads best:
+    /// `9500000 m` becomes `9500 km`, `0.0005 m` becomes `0.5 mm`, and
+    /// `1536 MB` becomes `1.5 GB`. Other values are returned unchanged.
+    #[must_use]
+    pub fn normalized(&self) -> Value {
+        match self {
+            Value::WithCompoundUnit { amount, unit } => {
+                match super::unit::readable_unit(*amount, unit) {
+                    Some((amount, unit)) => Value::WithCompoundUnit { amount, unit },
+                    None => self.clone(),
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Check if value is a plain number, in any display base
+    #[must_use]
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Number(_) | Value::BaseNumber { .. })
+    }
+
+    /// Check if value is an amount of money (not a price per unit)
+    #[must_use]
+    pub fn is_currency(&self) -> bool {
+        matches!(self, Value::Currency { .. })
+    }
+
+    /// Check if value is a quantity with a physical unit
+    #[must_use]
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Value::WithCompoundUnit { .. })
+    }
+
+    /// Check if value is a percentage
+    #[must_use]
+    pub fn is_percentage(&self) -> bool {
+        matches!(self, Value::Percentage(_))
+    }
+
     /// Return the structured error, if this value represents one.
     #[must_use]
     pub fn as_error(&self) -> Option<&EvalError> {
@@ -109,48 +358,104 @@ impl Value {
             Value::WithCompoundUnit { unit, .. } => {
                 Value::with_compound_unit(new_amount, unit.clone())
             }
+            Value::CurrencyRate { currency, per, .. } => {
+                Value::currency_rate(new_amount, *currency, per.clone())
+            }
             _ => Value::Number(new_amount),
         }
     }
-}
 
-impl std::fmt::Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Format a conversion result after the value it was converted from
+    /// ("5 km → 3.11 mi"). Units are never normalized here: the result is
+    /// already in the unit that was asked for.
+    #[must_use]
+    pub fn format_with_source(&self, source: &Value, options: &FormatOptions) -> String {
+        let as_converted = FormatOptions {
+            normalize_units: false,
+            ..*options
+        };
+        format!(
+            "{} → {}",
+            source.format(&as_converted),
+            self.format(&as_converted)
+        )
+    }
+
+    /// Format this value for display using the given options.
+    pub fn format(&self, options: &FormatOptions) -> String {
         match self {
-            Value::Number(n) => write!(f, "{}", format_number(*n)),
-            Value::BaseNumber { amount, base } => {
-                write!(f, "{}", format_number_base(*amount, *base))
-            }
+            Value::Number(n) => format_number_with(*n, options),
+            Value::BaseNumber { amount, base } => format_number_base(*amount, *base),
             Value::Percentage(p) => match p.checked_mul(Decimal::from(100)) {
-                Some(percent) => write!(f, "{}%", format_number(percent)),
+                Some(percent) => format!("{}%", format_number_with(percent, options)),
                 // Programmatic callers can construct a percentage outside the
                 // representable display range. Formatting must remain total.
-                None => write!(f, "{}×100%", format_number(*p)),
+                None => format!("{}×100%", format_number_with(*p, options)),
             },
             Value::Currency { amount, currency } => {
-                let formatted = format_currency_value(*amount, *currency);
-                if currency.symbol_after() {
-                    write!(f, "{}{}", formatted, currency.symbol())
-                } else {
-                    write!(f, "{}{}", currency.symbol(), formatted)
+                let formatted = format_currency_value_with(*amount, *currency, options);
+                let negative = options
+                    .accounting_negatives
+                    .then(|| formatted.strip_prefix('-'))
+                    .flatten();
+                let with_symbol = |digits: &str| {
+                    if currency.symbol_after() {
+                        format!("{}{}", digits, currency.symbol())
+                    } else {
+                        format!("{}{}", currency.symbol(), digits)
+                    }
+                };
+                match negative {
+                    Some(digits) => format!("({})", with_symbol(digits)),
+                    None => with_symbol(&formatted),
                 }
             }
+            Value::WithCompoundUnit { .. } if options.normalize_units => {
+                let as_written = FormatOptions {
+                    normalize_units: false,
+                    ..*options
+                };
+                self.normalized().format(&as_written)
+            }
             Value::WithCompoundUnit { amount, unit } => {
                 if unit.symbol == "°" {
-                    write!(f, "{}°", format_number(*amount))
+                    format!("{}°", format_number_with(*amount, options))
                 } else {
-                    write!(f, "{} {}", format_number(*amount), unit)
+                    format!("{} {}", format_number_with(*amount, options), unit)
                 }
             }
-            Value::Empty => Ok(()),
-            Value::Error(msg) => write!(f, "Error: {msg}"),
+            Value::CurrencyRate {
+                amount,
+                currency,
+                per,
+            } => {
+                let price = Value::currency(*amount, *currency).format(options);
+                if per.symbol.contains(['/', '·']) {
+                    format!("{price}/({per})")
+                } else {
+                    format!("{price}/{per}")
+                }
+            }
+            Value::Date(date) => date.to_string(),
+            Value::Empty => String::new(),
+            Value::Error(msg) => format!("Error: {msg}"),
         }
     }
 }
 
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format(&FormatOptions::default()))
+    }
+}
+
 /// Format a number nicely (max DISPLAY_PRECISION decimal places, unless that would hide a small non-zero value)
 pub fn format_number(n: Decimal) -> String {
-    let rounded = n.round_dp(DISPLAY_PRECISION);
+    format_number_rounded(n, RoundingMode::default())
+}
+
+fn format_number_rounded(n: Decimal, rounding: RoundingMode) -> String {
+    let rounded = n.round_dp_with_strategy(DISPLAY_PRECISION, rounding.strategy());
     if rounded.is_zero() && !n.is_zero() {
         return n.normalize().to_string();
     }
@@ -186,18 +491,78 @@ fn format_number_base(n: Decimal, base: NumberBase) -> String {
     }
 }
 
+/// Format a number using the given options.
+pub fn format_number_with(n: Decimal, options: &FormatOptions) -> String {
+    mark_if_rounded(format_number_rounded(n, options.rounding), n, options)
+}
+
 /// Format currency amount using display rules from the currency registry.
 pub fn format_currency_value(n: Decimal, currency: Currency) -> String {
-    format_currency_with_precision(n, currency.display_precision())
+    format_currency_with_precision(n, currency.display_precision(), RoundingMode::default())
+}
+
+/// Format currency amount using the registry precision and the given options.
+pub fn format_currency_value_with(
+    n: Decimal,
+    currency: Currency,
+    options: &FormatOptions,
+) -> String {
+    mark_if_rounded(
+        format_currency_with_precision(n, currency.display_precision(), options.rounding),
+        n,
+        options,
+    )
+}
+
+/// Group the digits of a formatted amount, appending `…` under
+/// `FormatOptions::mark_rounded` when the shown digits no longer equal `n`.
+fn mark_if_rounded(formatted: String, n: Decimal, options: &FormatOptions) -> String {
+    let rounded =
+        options.mark_rounded && Decimal::from_str_exact(&formatted).is_ok_and(|shown| shown != n);
+    let mut formatted = apply_grouping(formatted, options);
+    if rounded {
+        formatted.push('…');
+    }
+    formatted
+}
+
+/// Insert digit-group separators into a plain formatted decimal.
+fn apply_grouping(formatted: String, options: &FormatOptions) -> String {
+    let Some(grouping) = options.grouping else {
+        return formatted;
+    };
+
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", formatted.as_str()),
+    };
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+
+    let mut result = String::with_capacity(formatted.len() + integer.len() / 3);
+    result.push_str(sign);
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && options.group_size.is_boundary(integer.len() - index) {
+            result.push(grouping.separator());
+        }
+        result.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        result.push(grouping.decimal_mark());
+        result.push_str(fraction);
+    }
+    result
 }
 
 /// Format currency amount using the requested precision.
 /// Extra trailing zeros are trimmed for higher-precision currencies, but at least
 /// two decimal places are preserved for readability.
-fn format_currency_with_precision(n: Decimal, precision: u32) -> String {
+fn format_currency_with_precision(n: Decimal, precision: u32, rounding: RoundingMode) -> String {
     let mut formatted = format!(
         "{:.prec$}",
-        n.round_dp(precision),
+        n.round_dp_with_strategy(precision, rounding.strategy()),
         prec = precision as usize
     );
 
@@ -218,6 +583,23 @@ mod tests {
     use super::*;
     use std::str::FromStr;
 
+    #[test]
+    fn test_type_predicates() {
+        let money = Value::currency(Decimal::from(5), Currency::USD);
+        let distance = Value::with_compound_unit(
+            Decimal::from(5),
+            crate::types::unit::parse_unit("km").unwrap(),
+        );
+        let hex = Value::with_base(Decimal::from(255), NumberBase::Hexadecimal);
+        let share = Value::Percentage(Decimal::new(2, 1));
+
+        assert!(money.is_currency() && !money.is_number() && !money.is_unit());
+        assert!(distance.is_unit() && !distance.is_currency());
+        assert!(Value::Number(Decimal::ONE).is_number() && hex.is_number());
+        assert!(share.is_percentage() && !share.is_number());
+        assert!(!Value::Empty.is_number() && !Value::error("x").is_currency());
+    }
+
     #[test]
     fn test_format_number() {
         assert_eq!(format_number(Decimal::from(42)), "42");
@@ -229,6 +611,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_normalized_units_are_display_only() {
+        let distance = Value::with_compound_unit(
+            Decimal::from(9_500_000),
+            crate::types::unit::parse_unit("m").unwrap(),
+        );
+        assert_eq!(distance.normalized().to_string(), "9500 km");
+        let options = FormatOptions {
+            normalize_units: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(distance.format(&options), "9500 km");
+        assert_eq!(distance.to_string(), "9500000 m");
+        assert_eq!(
+            Value::Number(Decimal::TEN).normalized(),
+            Value::Number(Decimal::TEN)
+        );
+    }
+
     #[test]
     fn test_format_number_base() {
         assert_eq!(
@@ -247,21 +648,215 @@ mod tests {
 
     #[test]
     fn test_format_currency_with_precision() {
-        assert_eq!(format_currency_with_precision(Decimal::from(1), 8), "1.00");
         assert_eq!(
-            format_currency_with_precision(Decimal::from_str("0.1").unwrap(), 8),
+            format_currency_with_precision(Decimal::from(1), 8, RoundingMode::default()),
+            "1.00"
+        );
+        assert_eq!(
+            format_currency_with_precision(
+                Decimal::from_str("0.1").unwrap(),
+                8,
+                RoundingMode::default()
+            ),
             "0.10"
         );
         assert_eq!(
-            format_currency_with_precision(Decimal::from_str("0.04").unwrap(), 8),
+            format_currency_with_precision(
+                Decimal::from_str("0.04").unwrap(),
+                8,
+                RoundingMode::default()
+            ),
             "0.04"
         );
         assert_eq!(
-            format_currency_with_precision(Decimal::from_str("0.0042105263").unwrap(), 8),
+            format_currency_with_precision(
+                Decimal::from_str("0.0042105263").unwrap(),
+                8,
+                RoundingMode::default()
+            ),
             "0.00421053"
         );
     }
 
+    #[test]
+    fn test_rounding_modes() {
+        let options = |rounding| FormatOptions {
+            rounding,
+            ..FormatOptions::default()
+        };
+        let n = Decimal::from_str("2.125").unwrap();
+        assert_eq!(
+            format_number_with(n, &options(RoundingMode::HalfEven)),
+            "2.12"
+        );
+        assert_eq!(
+            format_number_with(n, &options(RoundingMode::HalfUp)),
+            "2.13"
+        );
+        assert_eq!(
+            format_number_with(
+                Decimal::from_str("2.129").unwrap(),
+                &options(RoundingMode::Truncate)
+            ),
+            "2.12"
+        );
+        assert_eq!(
+            format_currency_value_with(-n, Currency::USD, &options(RoundingMode::HalfUp)),
+            "-2.13"
+        );
+        assert_eq!(RoundingMode::parse("half_up"), Some(RoundingMode::HalfUp));
+        assert_eq!(RoundingMode::parse("nearest"), None);
+    }
+
+    #[test]
+    fn test_indian_grouping() {
+        let n = Decimal::from_str("123456789.5").unwrap();
+        let thousands = FormatOptions {
+            grouping: Some(GroupSeparator::Comma),
+            ..FormatOptions::default()
+        };
+        let indian = FormatOptions {
+            group_size: GroupSize::Indian,
+            ..thousands
+        };
+
+        assert_eq!(format_number_with(n, &thousands), "123,456,789.50");
+        assert_eq!(format_number_with(n, &indian), "12,34,56,789.50");
+        assert_eq!(
+            format_number_with(Decimal::from(1234567), &indian),
+            "12,34,567"
+        );
+        assert_eq!(
+            format_number_with(Decimal::from(-12345), &indian),
+            "-12,345"
+        );
+        assert_eq!(format_number_with(Decimal::from(999), &indian), "999");
+        assert_eq!(
+            format_currency_value_with(Decimal::from(10_000_000), Currency::INR, &indian),
+            "1,00,00,000.00"
+        );
+        // Group sizes do nothing without a separator
+        let ungrouped = FormatOptions {
+            group_size: GroupSize::Indian,
+            ..FormatOptions::default()
+        };
+        assert_eq!(format_number_with(n, &ungrouped), "123456789.50");
+    }
+
+    #[test]
+    fn test_grouped_formatting() {
+        let comma = FormatOptions {
+            grouping: Some(GroupSeparator::Comma),
+            ..FormatOptions::default()
+        };
+        let space = FormatOptions {
+            grouping: Some(GroupSeparator::Space),
+            ..FormatOptions::default()
+        };
+        let dot = FormatOptions {
+            grouping: Some(GroupSeparator::Dot),
+            ..FormatOptions::default()
+        };
+        let n = Decimal::from_str("-1234567.891").unwrap();
+
+        assert_eq!(
+            format_number_with(n, &FormatOptions::default()),
+            "-1234567.89"
+        );
+        assert_eq!(format_number_with(n, &comma), "-1,234,567.89");
+        assert_eq!(format_number_with(n, &space), "-1 234 567.89");
+        assert_eq!(format_number_with(n, &dot), "-1.234.567,89");
+        assert_eq!(format_number_with(Decimal::from(123), &comma), "123");
+        assert_eq!(format_number_with(Decimal::from(123456), &comma), "123,456");
+
+        let price = Value::currency(Decimal::from(2_000_000), Currency::USD);
+        assert_eq!(price.format(&comma), "$2,000,000.00");
+        assert_eq!(price.to_string(), "$2000000.00");
+        assert_eq!(
+            Value::currency(Decimal::from(1500), Currency::RUB).format(&space),
+            "1 500.00₽"
+        );
+        assert_eq!(
+            Value::with_base(Decimal::from(1_000_000), NumberBase::Hexadecimal).format(&comma),
+            "0xf4240"
+        );
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let km = crate::types::unit::parse_unit("km").unwrap();
+        let distance = Value::with_compound_unit(Decimal::from(5), km.clone());
+        let money = Value::currency(Decimal::new(125, 1), Currency::EUR);
+
+        assert_eq!(
+            money.as_currency(),
+            Some((Decimal::new(125, 1), Currency::EUR))
+        );
+        assert_eq!(distance.as_unit(), Some((Decimal::from(5), &km)));
+        assert_eq!(money.as_unit(), None);
+        assert_eq!(distance.as_currency(), None);
+        assert_eq!(Value::Number(Decimal::ONE).as_currency(), None);
+    }
+
+    #[test]
+    fn test_mark_rounded() {
+        let marked = FormatOptions {
+            mark_rounded: true,
+            ..FormatOptions::default()
+        };
+        let third = Decimal::ONE / Decimal::from(3);
+        assert_eq!(format_number_with(third, &FormatOptions::default()), "0.33");
+        assert_eq!(format_number_with(third, &marked), "0.33…");
+        assert_eq!(format_number_with(Decimal::new(25, 1), &marked), "2.50");
+        assert_eq!(format_number_with(Decimal::from(7), &marked), "7");
+        assert_eq!(
+            Value::currency(Decimal::new(10_005, 3), Currency::USD).format(&marked),
+            "$10.00…"
+        );
+        assert_eq!(
+            Value::currency(Decimal::new(1_050, 2), Currency::RUB).format(&marked),
+            "10.50₽"
+        );
+        assert_eq!(Value::Percentage(third).format(&marked), "33.33…%");
+        let grouped = FormatOptions {
+            grouping: Some(GroupSeparator::Comma),
+            ..marked
+        };
+        assert_eq!(
+            format_number_with(Decimal::new(12_345_678, 3), &grouped),
+            "12,345.68…"
+        );
+    }
+
+    #[test]
+    fn test_accounting_negatives() {
+        let accounting = FormatOptions {
+            accounting_negatives: true,
+            ..FormatOptions::default()
+        };
+        let refund = Value::currency(Decimal::from(-100), Currency::USD);
+        assert_eq!(refund.to_string(), "$-100.00");
+        assert_eq!(refund.format(&accounting), "($100.00)");
+        assert_eq!(
+            Value::currency(Decimal::from(-100), Currency::RUB).format(&accounting),
+            "(100.00₽)"
+        );
+        assert_eq!(
+            Value::currency(Decimal::from(100), Currency::USD).format(&accounting),
+            "$100.00"
+        );
+        // Only money: plain negatives keep their sign
+        assert_eq!(Value::Number(Decimal::from(-5)).format(&accounting), "-5");
+        let grouped = FormatOptions {
+            grouping: Some(GroupSeparator::Comma),
+            ..accounting
+        };
+        assert_eq!(
+            Value::currency(Decimal::from(-1_250_000), Currency::EUR).format(&grouped),
+            "(€1,250,000.00)"
+        );
+    }
+
     #[test]
     fn percentage_formatting_never_overflows() {
         assert_eq!(
@@ -6,7 +6,7 @@ use std::collections::HashMap;
 
 use wasm_bindgen::prelude::*;
 
-use crate::{Decimal, Engine, Value};
+use crate::{Currency, Decimal, Engine, Value};
 
 /// Initialize the browser panic hook once.
 #[wasm_bindgen(start)]
@@ -30,6 +30,32 @@ impl WasmEngine {
         }
     }
 
+    /// Evaluate one line, keeping its variables and result for later lines.
+    /// Returns JSON: `{"type":"currency","value":"100","display":"$100.00"}`.
+    #[wasm_bindgen]
+    pub fn eval(&mut self, input: &str) -> String {
+        serde_json::to_string(&ValueJson::from(&self.engine.eval(input))).unwrap_or_else(|_| {
+            r#"{"type":"error","value":null,"display":"Error: serialization failed"}"#.to_string()
+        })
+    }
+
+    /// Forget all lines and variables; exchange rates are kept.
+    #[wasm_bindgen]
+    pub fn clear(&mut self) {
+        self.engine.clear();
+    }
+
+    /// Set one exchange rate: 1 `from` is worth `rate` `to` (codes such as "USD", "EUR").
+    #[wasm_bindgen]
+    pub fn set_rate(&mut self, from: &str, to: &str, rate: f64) -> Result<(), String> {
+        let parse =
+            |code: &str| Currency::parse(code).ok_or_else(|| format!("unknown currency: {code}"));
+        let rate = Decimal::try_from(rate).map_err(|_| format!("invalid exchange rate: {rate}"))?;
+        self.engine
+            .try_set_exchange_rate(parse(from)?, parse(to)?, rate)
+            .map_err(|error| error.to_string())
+    }
+
     /// Evaluate a document and return results, totals, and variable names together.
     #[wasm_bindgen]
     pub fn eval_document_full(&mut self, content: &str) -> String {
@@ -100,6 +126,38 @@ impl From<crate::LineResult> for LineResultJson {
     }
 }
 
+/// A single result: its kind, full-precision amount, and display text.
+/// Percentages carry their fraction (`0.2` for 20%).
+#[derive(serde::Serialize)]
+struct ValueJson {
+    #[serde(rename = "type")]
+    value_type: &'static str,
+    value: Option<String>,
+    display: String,
+}
+
+impl From<&Value> for ValueJson {
+    fn from(value: &Value) -> Self {
+        let value_type = match value {
+            Value::Number(_) | Value::BaseNumber { .. } => "number",
+            Value::Percentage(_) => "percentage",
+            Value::Currency { .. } => "currency",
+            Value::WithCompoundUnit { .. } => "unit",
+            Value::CurrencyRate { .. } => "currency_rate",
+            Value::Date(_) => "date",
+            Value::Empty => "empty",
+            Value::Error(_) => "error",
+        };
+        Self {
+            value_type,
+            value: value
+                .as_decimal()
+                .map(|amount| amount.normalize().to_string()),
+            display: value.to_string(),
+        }
+    }
+}
+
 #[derive(serde::Serialize)]
 struct DocumentResultJson {
     results: Vec<LineResultJson>,
@@ -122,6 +180,35 @@ mod tests {
         assert_eq!(parsed["variable_names"], "price");
     }
 
+    #[test]
+    fn evaluates_lines_one_call_at_a_time() {
+        let mut engine = WasmEngine::new();
+        let parsed = |json: String| serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+        assert_eq!(
+            parsed(engine.eval("price = $10")),
+            serde_json::json!({"type": "currency", "value": "10", "display": "$10.00"})
+        );
+        assert_eq!(parsed(engine.eval("price * 3"))["display"], "$30.00");
+        assert_eq!(parsed(engine.eval("20%"))["value"], "0.2");
+
+        engine.clear();
+        let error = parsed(engine.eval("price"));
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["value"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn sets_a_single_rate() {
+        let mut engine = WasmEngine::new();
+        engine.set_rate("USD", "EUR", 0.5).unwrap();
+        let result: serde_json::Value = serde_json::from_str(&engine.eval("$10 in EUR")).unwrap();
+        assert_eq!(result["display"], "€5.00");
+
+        assert!(engine.set_rate("USD", "XXX", 1.0).is_err());
+        assert!(engine.set_rate("USD", "EUR", f64::NAN).is_err());
+    }
+
     #[test]
     fn applies_rates_before_document_evaluation() {
         let mut engine = WasmEngine::new();
@@ -2,7 +2,7 @@
 //!
 //! This test validates compound unit operations (m², km/h, etc.)
 
-use numr_core::{Engine, Value};
+use numr_core::{Engine, EvalOptions, Value};
 
 const COMPOUND_UNITS_FILE: &str = include_str!("../../../compound_units.numr");
 
@@ -86,6 +86,44 @@ fn test_compound_unit_parsing() {
     assert_eq!(result.to_string(), "10 m/s");
 }
 
+#[test]
+fn test_unit_expression_parsing() {
+    let mut engine = create_engine();
+
+    assert_eq!(engine.eval("5 m^2").to_string(), "5 m²");
+    assert_eq!(engine.eval("100 km/h").to_string(), "100 km/h");
+    assert_eq!(engine.eval("100 m/s^2").to_string(), "100 m/s²");
+    assert_eq!(engine.eval("2 cm³").to_string(), "2 mL");
+
+    // kg·m/s² has the dimensions of force and resolves to newtons
+    let force = engine.eval("10 kg*m/s^2");
+    let Value::WithCompoundUnit { unit, .. } = &force else {
+        panic!("expected a unit value, got {force:?}");
+    };
+    assert_eq!(unit.dimensions.to_string(), "force");
+    assert_eq!(force.to_string(), "10 N");
+    assert_eq!(engine.eval("10 kg*m/s^2 in N").to_string(), "10 N");
+
+    // Unit expressions convert like registry units
+    let result = engine.eval("36 km/h in mps");
+    assert_eq!(result.to_string(), "10 m/s");
+    let result = engine.eval("1 m^2 in cm2");
+    assert_eq!(result.as_f64(), Some(10000.0));
+}
+
+#[test]
+fn test_unit_expression_with_variables() {
+    let mut engine = create_engine();
+    engine.eval("x = 3");
+    engine.eval("price = 4");
+
+    // Factors that are not units keep their arithmetic meaning
+    assert_eq!(engine.eval("2 x^2").as_f64(), Some(18.0));
+    assert_eq!(engine.eval("10 kg*price").to_string(), "40 kg");
+    // Spaced operators are ordinary arithmetic, not a unit expression
+    assert_eq!(engine.eval("6 m * 2").to_string(), "12 m");
+}
+
 #[test]
 fn test_compound_unit_conversion() {
     let mut engine = create_engine();
@@ -96,6 +134,49 @@ fn test_compound_unit_conversion() {
     assert!((val - 13.89).abs() < 0.01);
 }
 
+#[test]
+fn test_area_and_volume_conversions() {
+    let mut engine = create_engine();
+
+    // (expression, expected amount, expected unit); exact definitions where they exist
+    let cases = [
+        ("1 km² in acres", 247.105_381, "acre"),
+        ("1 acre in m²", 4_046.856_422_4, "m²"),
+        ("1 acre in ft²", 43_560.0, "ft²"),
+        ("1 hectare in acres", 2.471_053_81, "acre"),
+        ("1 mi² in km²", 2.589_988_110_336, "km²"),
+        ("1 ft² in m²", 0.092_903_04, "m²"),
+        ("1 m^2 in ft^2", 10.763_910_417, "ft²"),
+        ("1 in² in cm²", 6.451_6, "cm²"),
+        ("1 m³ in gallons", 264.172_052_358, "gal"),
+        ("1 gallon in L", 3.785_411_784, "L"),
+        ("1 gallon in in³", 231.0, "in³"),
+        ("1 ft³ in L", 28.316_846_592, "L"),
+        ("1 yd³ in m³", 0.764_554_857_984, "m³"),
+        ("1 m³ in L", 1_000.0, "L"),
+    ];
+    for (expression, expected, symbol) in cases {
+        let result = engine.eval(expression);
+        let Value::WithCompoundUnit { amount, unit } = &result else {
+            panic!("{expression} -> {result:?}");
+        };
+        let amount: f64 = amount.to_string().parse().unwrap();
+        assert!(
+            (amount - expected).abs() < expected * 1e-6,
+            "{expression} = {amount}, expected {expected}"
+        );
+        assert_eq!(unit.symbol, symbol, "{expression}");
+    }
+}
+
+#[test]
+fn test_compound_conversion_targets() {
+    let mut engine = create_engine();
+    assert_eq!(engine.eval("100 mph in km/h").to_string(), "160.93 km/h");
+    assert_eq!(engine.eval("60 mph to m/s").to_string(), "26.82 m/s");
+    assert!(engine.eval("1 acre in kg/s").is_error());
+}
+
 #[test]
 fn test_compound_unit_addition() {
     let mut engine = create_engine();
@@ -141,3 +222,122 @@ fn test_compound_unit_totals() {
     assert_eq!(totals.len(), 1);
     assert_eq!(totals[0].to_string(), "60 m²");
 }
+
+#[test]
+fn test_mismatched_dimensions_name_both_sides() {
+    let mut engine = create_engine();
+
+    assert_eq!(
+        engine.eval("5 km + 2 kg").to_string(),
+        "Error: Cannot add length and mass"
+    );
+    assert_eq!(
+        engine.eval("10 kph - 3 m2").to_string(),
+        "Error: Cannot subtract speed and area"
+    );
+}
+
+#[test]
+fn test_lenient_units_add_raw_amounts() {
+    let mut engine = create_engine();
+    engine.set_options(EvalOptions {
+        lenient_units: true,
+        ..EvalOptions::default()
+    });
+
+    let result = engine.eval("5 km + 2 kg");
+    assert_eq!(result.as_f64(), Some(7.0));
+    assert!(matches!(result, Value::Number(_)));
+    let warnings = &engine.lines().last().unwrap().warnings;
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("length (km) and mass (kg)"));
+
+    // Compatible units still convert, without warnings
+    let result = engine.eval("1 km + 500 m");
+    assert_eq!(result.to_string(), "1.50 km");
+    assert!(engine.lines().last().unwrap().warnings.is_empty());
+}
+
+#[test]
+fn test_per_keyword_builds_rates() {
+    let mut engine = create_engine();
+
+    assert_eq!(engine.eval("60 miles per hour").to_string(), "60 mph");
+    assert_eq!(engine.eval("100 km per 2 h").to_string(), "50 km/h");
+    let result = engine.eval("60 miles per hour in kph");
+    assert!((result.as_f64().unwrap() - 96.56).abs() < 0.01);
+}
+
+#[test]
+fn test_temperature_differences_add_to_readings() {
+    let mut engine = create_engine();
+
+    assert_eq!(engine.eval("20°C + 5 C°").to_string(), "25 °C");
+    assert_eq!(engine.eval("20°C + Δ5°C").to_string(), "25 °C");
+    assert_eq!(engine.eval("5 C° + 20 °C").to_string(), "25 °C");
+    // A Fahrenheit difference has no offset: 9 F° is 5 C°
+    assert_eq!(engine.eval("20°C + 9 F°").to_string(), "25 °C");
+    assert_eq!(engine.eval("70°F - Δ5°C").to_string(), "61 °F");
+    assert!(engine.lines().last().unwrap().warnings.is_empty());
+
+    // Two readings subtract to a difference
+    assert_eq!(engine.eval("30°C - 20°C").to_string(), "10 C°");
+    assert_eq!(engine.eval("9 F° in C").to_string(), "5 C°");
+    assert!(engine.eval("5 C° - 20°C").is_error());
+}
+
+#[test]
+fn test_adding_temperature_readings_warns() {
+    let mut engine = create_engine();
+
+    assert_eq!(engine.eval("20°C + 30°C").to_string(), "50 °C");
+    let warnings = &engine.lines().last().unwrap().warnings;
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("5 C°"));
+
+    // Readings and differences total separately
+    engine.eval("5 C°");
+    let totals = engine.grouped_totals();
+    assert_eq!(totals.len(), 2);
+}
+
+#[test]
+fn test_temperature_readings_do_not_multiply_by_units() {
+    let mut engine = create_engine();
+
+    // The product would drop the scale's offset, so readings are refused
+    assert_eq!(
+        engine.eval("2°C * 3m").to_string(),
+        "Error: Cannot multiply °C readings; use a difference such as 5 C°"
+    );
+    assert!(engine.eval("20°F / 2 h").is_error());
+    // Differences and plain scaling still work
+    assert_eq!(engine.eval("2 C° * 3 m").to_string(), "6 C°·m");
+    assert_eq!(engine.eval("10 C° / 2 h").to_string(), "5 C°/h");
+    assert_eq!(engine.eval("20°C * 2").to_string(), "40 °C");
+}
+
+#[test]
+fn test_unusual_unit_products_warn_when_enabled() {
+    let mut engine = create_engine();
+    assert_eq!(engine.eval("5 kg * 2 C°").to_string(), "10 kg·C°");
+    assert!(engine.lines().last().unwrap().warnings.is_empty());
+
+    engine.set_options(EvalOptions {
+        warn_unusual_units: true,
+        ..EvalOptions::default()
+    });
+    assert_eq!(engine.eval("5 kg * 2 C°").to_string(), "10 kg·C°");
+    let warnings = &engine.lines().last().unwrap().warnings;
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("kg·C°"));
+
+    // Products with a named quantity do not warn
+    for input in ["5 m * 10 m", "100 km / 2 h", "10 kg * 2 m/s^2"] {
+        engine.eval(input);
+        assert!(
+            engine.lines().last().unwrap().warnings.is_empty(),
+            "{input}"
+        );
+    }
+}
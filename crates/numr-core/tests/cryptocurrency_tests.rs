@@ -164,3 +164,57 @@ fn test_btc_variables() {
     assert_eq!(result.as_decimal(), Some(d("23750")));
     assert_eq!(result.to_string(), "$23750.00");
 }
+
+#[test]
+fn test_satoshi_subunit_conversions() {
+    let mut engine = engine_with_btc_rate(d("95000"));
+
+    // Subunit amounts are BTC values
+    let result = engine.eval("100000 sats in BTC");
+    assert_eq!(result.as_decimal(), Some(d("0.001")));
+    assert!(matches!(
+        result,
+        numr_core::Value::Currency {
+            currency: Currency::BTC,
+            ..
+        }
+    ));
+    assert_eq!(engine.eval("100000 sats").to_string(), "₿0.001");
+
+    // Converting into a subunit yields a plain count
+    let result = engine.eval("1 BTC in sats");
+    assert_eq!(result, numr_core::Value::Number(d("100000000")));
+    assert_eq!(
+        engine
+            .eval("$95 in sats")
+            .as_decimal()
+            .map(|sats| sats.round_dp(8)),
+        Some(d("100000"))
+    );
+    assert_eq!(
+        engine.eval("100000 sats in USD").as_decimal(),
+        Some(d("95"))
+    );
+}
+
+#[test]
+fn test_gwei_and_wei_subunit_conversions() {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval("1 ETH in gwei").as_decimal(),
+        Some(d("1000000000"))
+    );
+    assert_eq!(
+        engine.eval("1 gwei in ETH").as_decimal(),
+        Some(d("0.000000001"))
+    );
+    assert_eq!(
+        engine.eval("1 ETH in wei").as_decimal(),
+        Some(d("1000000000000000000"))
+    );
+    assert_eq!(
+        engine.eval("1000000000 wei in gwei").as_decimal(),
+        Some(d("1"))
+    );
+}
@@ -1,4 +1,4 @@
-use numr_core::{Engine, Value};
+use numr_core::{Currency, Engine, EvalError, Value};
 use rust_decimal::Decimal;
 use std::str::FromStr;
 
@@ -9,6 +9,45 @@ fn test_division_by_zero() {
     assert!(matches!(result, Value::Error(_)));
 }
 
+#[test]
+fn test_errors_keep_their_kind() {
+    let mut engine = Engine::new();
+
+    // An unknown operand is reported as such, not as a failed operation
+    assert_eq!(
+        engine.eval("missing * 2"),
+        Value::Error(EvalError::UnknownVariable("missing".to_string()))
+    );
+    assert_eq!(
+        engine.eval("5 / 0"),
+        Value::Error(EvalError::DivisionByZero)
+    );
+    assert!(matches!(
+        engine.eval("10 +"),
+        Value::Error(EvalError::Parse(_))
+    ));
+
+    let error = engine.eval("5 km in kg");
+    assert!(matches!(
+        error,
+        Value::Error(EvalError::IncompatibleUnits { .. })
+    ));
+    assert_eq!(error.to_string(), "Error: Cannot convert km to kg");
+
+    let Value::Error(error) = engine.eval("5 km + 2 kg") else {
+        panic!("expected an error");
+    };
+    assert_eq!(error.code(), "incompatible_units");
+    assert_eq!(error.to_string(), "Cannot add length and mass");
+
+    let error = EvalError::NoRate {
+        from: Currency::USD,
+        to: Currency::BTC,
+    };
+    assert_eq!(error.code(), "no_rate");
+    assert_eq!(error.to_string(), "no exchange rate for USD to BTC");
+}
+
 #[test]
 fn test_recursive_definition() {
     let mut engine = Engine::new();
@@ -185,3 +224,77 @@ fn test_complex_unit_conversions() {
     let res_mixed = engine.eval("(1 kg * 2) in g");
     assert_eq!(res_mixed.as_f64(), Some(2000.0));
 }
+
+#[test]
+fn test_identity_conversions() {
+    let cases = [
+        ("$100 in usd", "$100.00"),
+        ("€42.5 in EUR", "€42.50"),
+        ("5 km in km", "5 km"),
+        ("98.6 F in F", "98.60 °F"),
+        ("-40 C in celsius", "-40 °C"),
+        ("300 K to K", "300 K"),
+        ("60 kph in kph", "60 km/h"),
+        ("(100 km / 2 h) in kph", "50 km/h"),
+        ("5 m * 10 m in m2", "50 m²"),
+        ("1.5 kWh in kWh", "1.50 kWh"),
+        ("22 to hex to hex", "0x16"),
+    ];
+    for (expression, expected) in cases {
+        assert_eq!(
+            Engine::new().eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+}
+
+#[test]
+fn test_identity_conversions_are_exact() {
+    let mut engine = Engine::new();
+
+    for (expression, amount) in [
+        ("98.6 F in F", "98.6"),
+        ("1 / 3 km in km", "0.3333333333333333333333333333"),
+        ("72 mph in mph", "72"),
+        ("(10 km / 3 h) in kph", "3.3333333333333333333333333333"),
+        ("$1 / 3 in usd", "0.3333333333333333333333333333"),
+    ] {
+        assert_eq!(
+            engine.eval(expression).as_decimal(),
+            Some(Decimal::from_str(amount).unwrap()),
+            "{expression}"
+        );
+    }
+}
+
+#[test]
+fn test_variable_self_conversion() {
+    let mut engine = Engine::new();
+
+    engine.eval("distance = 12.5 km");
+    assert_eq!(engine.eval("distance in km").to_string(), "12.50 km");
+
+    engine.eval("speed = 100 km / 2 h");
+    assert_eq!(engine.eval("speed in kph").to_string(), "50 km/h");
+
+    engine.eval("budget = €250");
+    assert_eq!(engine.eval("budget in eur").to_string(), "€250.00");
+
+    // Same-unit arithmetic goes through operand coercion unchanged
+    assert_eq!(engine.eval("distance + 2.5 km").to_string(), "15 km");
+    assert_eq!(engine.eval("budget - €50").to_string(), "€200.00");
+}
+
+#[test]
+fn test_si_suffix_keeps_units() {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval("3k").to_string(), "3000");
+    assert_eq!(engine.eval("3k steps").to_string(), "3000");
+    assert_eq!(engine.eval("2.5M").to_string(), "2500000");
+    assert_eq!(engine.eval("2G").to_string(), "2000000000");
+    // Suffixes that spell a unit read as that unit when a unit follows
+    assert_eq!(engine.eval("1T in kg").to_string(), "1000 kg");
+    assert_eq!(engine.eval("5M km").to_string(), "5 m");
+}
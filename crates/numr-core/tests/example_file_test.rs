@@ -334,6 +334,25 @@ fn test_ratio_to_unit_conversion() {
     assert_eq!(result.as_decimal(), Some(d("10")));
 }
 
+/// An amount of money is not a ratio, so it has no unit to convert to
+#[test]
+fn test_currency_amount_to_unit_is_an_error() {
+    let mut engine = create_engine();
+
+    engine.eval("liquid = 1000 usd");
+    engine.eval("expenses = 100 usd");
+    assert_eq!(
+        engine.eval("liquid / expenses in months").to_string(),
+        "10 mo"
+    );
+
+    let result = engine.eval("$100 in months");
+    assert_eq!(result.to_string(), "Error: Cannot convert USD to mo");
+    // Dividing by a plain number still leaves money
+    assert!(engine.eval("(liquid / 100) in months").is_error());
+    assert!(engine.eval("liquid in kg").is_error());
+}
+
 // ============================================================================
 // MIXED UNIT ARITHMETIC TESTS
 // ============================================================================
@@ -49,6 +49,30 @@ fn currency_percentage_expressions() {
     }
 }
 
+#[test]
+fn percentage_more_and_less_than() {
+    let cases = [
+        ("20% more than $100", "$120.00"),
+        ("10% less than 50 kg", "45 kg"),
+        ("25% MORE THAN 80", "100"),
+        ("15% less than €200 + 10", "€180.00"),
+        ("100% less than 7", "0"),
+    ];
+    let mut engine = Engine::new();
+
+    for (expression, expected) in cases {
+        assert_eq!(
+            engine.eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+
+    // "more" and "less" are still usable as variable names
+    engine.eval("less = 5");
+    assert_eq!(engine.eval("10% more than less").as_f64(), Some(5.5));
+}
+
 #[test]
 fn stateful_profit_formula() {
     let mut engine = Engine::new();
@@ -58,3 +82,65 @@ fn stateful_profit_formula() {
     assert_eq!(engine.eval("price - cost").as_f64(), Some(20.0));
     assert_eq!(engine.eval("20% of cost").as_f64(), Some(16.0));
 }
+
+#[test]
+fn share_of_a_whole_as_percentage() {
+    let cases = [
+        ("$200 as % of $1000", "20%"),
+        ("200 as % of $1000", "20%"),
+        ("$200 as % of 1000", "20%"),
+        ("750 m as % of 3 km", "25%"),
+        ("20% as % of 50%", "40%"),
+    ];
+    let mut engine = Engine::new();
+
+    for (expression, expected) in cases {
+        assert_eq!(
+            engine.eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+
+    assert!(engine.eval("5 km as % of 2 kg").is_error());
+    assert!(engine.eval("5 as % of 0").is_error());
+}
+
+#[test]
+fn share_of_a_whole_continuation() {
+    let mut engine = Engine::new();
+    engine.eval("$200");
+    assert_eq!(engine.eval("as % of $1000").to_string(), "20%");
+    assert!(engine.lines()[0].is_continuation_source);
+}
+
+#[test]
+fn apr_and_apy_conversions() {
+    let mut engine = Engine::new();
+
+    // 5% compounded monthly yields (1 + 0.05/12)^12 - 1 = 5.1162%
+    let apy = engine.eval("apr_to_apy(5%, 12)");
+    assert_eq!(apy.to_string(), "5.12%");
+    assert!((apy.as_f64().unwrap() - 0.051162).abs() < 1e-6);
+    let apr = engine.eval("apy_to_apr(5.116189788%, 12)");
+    assert!((apr.as_f64().unwrap() - 0.05).abs() < 1e-9);
+    // Rates may also be written as fractions
+    assert_eq!(engine.eval("apr_to_apy(0.05, 365)").to_string(), "5.13%");
+}
+
+#[test]
+fn apr_and_apy_edge_cases() {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval("apr_to_apy(0%, 12)").to_string(), "0%");
+    assert_eq!(engine.eval("apy_to_apr(0%, 12)").to_string(), "0%");
+    // Annual compounding is the identity
+    assert_eq!(engine.eval("apr_to_apy(7%, 1)").to_string(), "7%");
+    assert_eq!(engine.eval("apy_to_apr(7%, 1)").to_string(), "7%");
+
+    assert!(engine.eval("apr_to_apy(5%, 0)").is_error());
+    assert!(engine.eval("apr_to_apy(5%, 1.5)").is_error());
+    assert!(engine.eval("apr_to_apy(-1200%, 12)").is_error());
+    assert!(engine.eval("apy_to_apr(-100%, 12)").is_error());
+    assert!(engine.eval("apr_to_apy(5%)").is_error());
+}
@@ -27,3 +27,20 @@ fn test_currency_rounding() {
     let res = engine.eval("$10 / 3"); // 3.33333... USD
     assert_eq!(res.to_string(), "$3.33");
 }
+
+#[test]
+fn test_result_scale() {
+    let mut engine = Engine::new();
+    assert_eq!(engine.eval("1.50").scale(), Some(2));
+    assert_eq!(engine.eval("5 km").scale(), Some(0));
+    assert_eq!(engine.eval("$12.5").scale(), Some(1));
+
+    // Conversions carry the precision of their factors even when shown rounded
+    let converted = engine.eval("5 km in mi");
+    assert_eq!(converted.to_string(), "3.11 mi");
+    assert!(converted.scale().unwrap() > 10, "{:?}", converted.scale());
+    assert_eq!(engine.eval("1 mi in km").scale(), Some(6));
+
+    assert_eq!(engine.eval("").scale(), None);
+    assert_eq!(engine.eval("1 / 0").scale(), None);
+}
@@ -237,3 +237,31 @@ fn test_to_conversion_keyword() {
     // Verify display has clean decimal precision
     assert_clean_display(&result.to_string());
 }
+
+#[test]
+fn test_engine_state_round_trips_through_json() {
+    let mut engine = Engine::new();
+    engine.set_exchange_rate(Currency::USD, Currency::EUR, d("0.8"));
+    engine.set_exchange_rate(Currency::BTC, Currency::USD, d("60000"));
+    for line in [
+        "rent = $1500",
+        "groceries = €420",
+        "# travel",
+        "flight = 0.01 BTC",
+        "distance = 12 km",
+        "+ 3000 m",
+        "rent + groceries in EUR",
+        "tax = 20%",
+        "total * tax",
+    ] {
+        engine.eval(line);
+    }
+
+    let json = engine.to_json().unwrap();
+    let restored = Engine::from_json(&json).unwrap();
+
+    assert_eq!(restored.grouped_totals(), engine.grouped_totals());
+    assert_eq!(restored.variables(), engine.variables());
+    assert_eq!(restored.lines(), engine.lines());
+    assert_eq!(restored.to_json().unwrap(), json);
+}
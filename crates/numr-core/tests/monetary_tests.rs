@@ -1,7 +1,7 @@
 //! Monetary calculation tests
 //! Tests for currency conversions, multi-currency arithmetic, and formatting
 
-use numr_core::{catalog::currency_catalog, decimal as d, Currency, Engine};
+use numr_core::{catalog::currency_catalog, decimal as d, Currency, Engine, EvalOptions, Value};
 
 #[test]
 fn test_currency_formats() {
@@ -31,6 +31,32 @@ fn test_currency_formats() {
     }
 }
 
+#[test]
+fn test_adjacent_lowercase_currency_codes() {
+    let mut engine = Engine::new();
+    let cases = [
+        ("100usd", "$100.00"),
+        ("100chf", "CHF100.00"),
+        ("100rub", "100.00₽"),
+        ("0.5btc", "₿0.50"),
+        ("2eth", "Ξ2.00"),
+        ("50usdc", "USDC50.00"),
+        ("100matic", "MATIC100.00"),
+        // Exponents and grouped digits still end before the code
+        ("1e2eur", "€100.00"),
+        ("1,000usd", "$1000.00"),
+        ("100usd * 2", "$200.00"),
+    ];
+
+    for (expression, expected) in cases {
+        assert_eq!(
+            engine.eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+}
+
 #[test]
 fn test_currency_arithmetic_same() {
     let mut engine = Engine::new();
@@ -95,6 +121,27 @@ fn test_multi_currency_arithmetic() {
     assert_eq!(result.to_string(), "$150.00");
 }
 
+#[test]
+fn test_sum_and_average_keep_currency_and_unit() {
+    let mut engine = Engine::new();
+    engine.set_exchange_rate(Currency::USD, Currency::EUR, d("0.5"));
+
+    assert_eq!(engine.eval("sum($100, $50)").to_string(), "$150.00");
+    assert_eq!(engine.eval("avg($100, $50)").to_string(), "$75.00");
+    // Converted to the last currency, as totals are
+    assert_eq!(engine.eval("sum($100, €50)").to_string(), "€100.00");
+    assert_eq!(engine.eval("sum(€50, $100)").to_string(), "$200.00");
+    assert_eq!(engine.eval("avg(1 km, 500 m)").to_string(), "750 m");
+    // Plain numbers add in the shared type
+    assert_eq!(engine.eval("sum($100, 20)").to_string(), "$120.00");
+
+    assert!(engine.eval("sum($100, 5 km)").is_error());
+    assert!(engine.eval("sum($100, 10%)").is_error());
+    // Without amounts the plain arithmetic is unchanged
+    assert_eq!(engine.eval("sum(1, 2, 3)").as_decimal(), Some(d("6")));
+    assert_eq!(engine.eval("sum(10%, 20%)").as_decimal(), Some(d("0.3")));
+}
+
 #[test]
 fn test_currency_with_variables() {
     let mut engine = Engine::new();
@@ -239,6 +286,38 @@ fn test_usd_to_crypto_conversion() {
 // These tests document expected behavior for mixing plain numbers with currency
 // ============================================================================
 
+#[test]
+fn test_strict_units_rejects_plain_number_mixing() {
+    let mut engine = Engine::new();
+    engine.set_options(EvalOptions {
+        strict_units: true,
+        ..EvalOptions::default()
+    });
+
+    assert_eq!(
+        engine.eval("1000 + 1000 RUB").to_string(),
+        "Error: Cannot add a plain number and RUB"
+    );
+    assert_eq!(
+        engine.eval("1000 RUB - 500").to_string(),
+        "Error: Cannot subtract RUB and a plain number"
+    );
+    assert_eq!(
+        engine.eval("5 km + 2").to_string(),
+        "Error: Cannot add km and a plain number"
+    );
+
+    // Tagged amounts, scaling, and percentages are unaffected
+    assert_eq!(engine.eval("1000 RUB + 1000 RUB").to_string(), "2000.00₽");
+    assert_eq!(engine.eval("1000 RUB * 2").to_string(), "2000.00₽");
+    assert_eq!(engine.eval("$100 + 10%").to_string(), "$110.00");
+    assert_eq!(engine.eval("2 + 3").to_string(), "5");
+
+    // The default stays lenient
+    engine.set_options(EvalOptions::default());
+    assert_eq!(engine.eval("1000 + 1000 RUB").to_string(), "2000.00₽");
+}
+
 #[test]
 fn test_plain_number_plus_currency_propagates() {
     let mut engine = Engine::new();
@@ -353,6 +432,26 @@ fn test_unit_times_currency_allowed() {
     assert_eq!(result.to_string(), "$4080.00");
 }
 
+#[test]
+fn test_at_rate_notation() {
+    let mut engine = Engine::new();
+    let cases = [
+        ("45h @ $85", "$3825.00"),
+        ("8h @ $30/h", "$240.00"),
+        ("8 hours @ $30 per hour", "$240.00"),
+        ("3 kg @ $20/kg + $5", "$65.00"),
+        ("12 @ €2", "€24.00"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(engine.eval(input).to_string(), expected, "{input}");
+    }
+
+    // A line starting with @ applies a rate to the previous result
+    engine.eval("");
+    engine.eval("40h");
+    assert_eq!(engine.eval("@ $25").to_string(), "$1000.00");
+}
+
 #[test]
 fn test_complex_rub_calculation_with_percentage() {
     let mut engine = Engine::new();
@@ -411,3 +510,237 @@ fn test_complex_rub_calculation_with_percentage() {
     assert_eq!(result.as_decimal(), Some(d("21762")));
     assert_eq!(result.to_string(), "₪21762.00");
 }
+
+#[test]
+fn test_scale_words_in_amounts() {
+    let mut engine = Engine::new();
+
+    let result = engine.eval("2 million usd");
+    assert_eq!(result, Value::currency(d("2000000"), Currency::USD));
+
+    let result = engine.eval("$1.5 billion");
+    assert_eq!(result, Value::currency(d("1500000000"), Currency::USD));
+
+    assert_eq!(
+        engine.eval("1.5 billion").as_decimal(),
+        Some(d("1500000000"))
+    );
+    assert_eq!(
+        engine.eval("3 million + 500000").as_decimal(),
+        Some(d("3500000"))
+    );
+    assert_eq!(
+        engine.eval("2 Thousand EUR"),
+        Value::currency(d("2000"), Currency::EUR)
+    );
+
+    // Scale words only apply as whole words
+    engine.eval("millionaire = 3");
+    assert_eq!(engine.eval("2 millionaire").as_decimal(), Some(d("6")));
+}
+
+#[test]
+fn test_currency_round_trips() {
+    let mut engine = Engine::new();
+    let epsilon = d("0.0000000001");
+
+    for expression in [
+        "1 usd in eur in usd",
+        "$250 in gbp in usd",
+        "€100 in jpy in eur",
+        "1 btc in uah in btc",
+    ] {
+        let result = engine.eval(expression).as_decimal().unwrap();
+        let original = engine
+            .eval(expression.split(" in ").next().unwrap())
+            .as_decimal()
+            .unwrap();
+        assert!(
+            (result - original).abs() < epsilon,
+            "{expression} = {result}"
+        );
+    }
+}
+
+#[test]
+fn test_rate_variable_applied_to_time() {
+    let mut engine = Engine::new();
+    assert_eq!(engine.eval("rate = $85 / 1h").to_string(), "$85.00/h");
+    let stored = engine
+        .variables()
+        .into_iter()
+        .find_map(|(name, value)| (name == "rate").then_some(value));
+    assert!(matches!(
+        stored,
+        Some(Value::CurrencyRate {
+            currency: Currency::USD,
+            ..
+        })
+    ));
+    assert_eq!(engine.eval("rate * 40h").to_string(), "$3400.00");
+    assert_eq!(engine.eval("40 h * rate").to_string(), "$3400.00");
+    assert_eq!(engine.eval("rate * 90 min").to_string(), "$127.50");
+    // A rate saved per day converts its time unit on the way in
+    engine.eval("daily = $680 per day");
+    assert_eq!(engine.eval("daily * 36 h").to_string(), "$1020.00");
+}
+
+#[test]
+fn test_price_per_unit() {
+    let mut engine = Engine::new();
+    engine.set_exchange_rate(Currency::USD, Currency::EUR, d("0.5"));
+
+    let rate = engine.eval("$20 per kg");
+    assert!(matches!(
+        rate,
+        Value::CurrencyRate {
+            currency: Currency::USD,
+            ..
+        }
+    ));
+    assert_eq!(rate.to_string(), "$20.00/kg");
+
+    let cases = [
+        // Applying the rate cancels the unit
+        ("$20 per kg * 3 kg", "$60.00"),
+        ("$20 per kg * 500 g", "$10.00"),
+        ("2 kg * ($4 per kg)", "$8.00"),
+        // Dividing a price by a quantity builds a rate, and back again
+        ("$240 / 8 h", "$30.00/h"),
+        ("$60 / ($20 per kg)", "3 kg"),
+        // Scaling and percentages keep the rate
+        ("$20 per kg * 2", "$40.00/kg"),
+        ("$20 per kg + 10%", "$22.00/kg"),
+        // Conversions re-quote the currency or the unit
+        ("$20 per kg in EUR", "€10.00/kg"),
+        ("$20 per kg in g", "$0.02/g"),
+        ("$20 per kg + $1 per g", "$1020.00/kg"),
+    ];
+    for (expression, expected) in cases {
+        assert_eq!(
+            engine.eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+}
+
+#[test]
+fn test_money_shorthand_multipliers() {
+    let mut engine = Engine::new();
+    let cases = [
+        ("$1.5k", "$1500.00"),
+        ("€2m", "€2000000.00"),
+        ("$3bn", "$3000000000.00"),
+        ("$2B", "$2000000000.00"),
+        ("1.5k$", "$1500.00"),
+        ("2k usd", "$2000.00"),
+        ("1.5m EUR", "€1500000.00"),
+        ("$100k / 4", "$25000.00"),
+    ];
+    for (expression, expected) in cases {
+        assert_eq!(
+            engine.eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+
+    // The multiplier must touch the amount, and other words keep their meaning
+    assert_eq!(engine.eval("$5 m").to_string(), "$5.00");
+    assert_eq!(engine.eval("2k x 3").as_decimal(), Some(d("6000")));
+
+    // Only a currency takes the shorthand; after any other word the number
+    // reads as it would alone
+    assert_eq!(engine.eval("500m walk").to_string(), "500 m");
+    assert_eq!(engine.eval("5m km").to_string(), "5 m");
+    assert_eq!(engine.eval("2m apples").to_string(), "2 m");
+}
+
+#[test]
+fn test_price_per_unit_with_slash() {
+    let mut engine = Engine::new();
+
+    let rate = engine.eval("$30/h");
+    assert!(matches!(
+        rate,
+        Value::CurrencyRate {
+            currency: Currency::USD,
+            ..
+        }
+    ));
+    assert_eq!(rate.to_string(), "$30.00/h");
+
+    let cases = [
+        ("$30/h * 8 h", "$240.00"),
+        ("8 h * $30/h", "$240.00"),
+        ("8 h * $30 per h", "$240.00"),
+        ("$240 / 8h", "$30.00/h"),
+        ("$240 / 8h * 2 h", "$60.00"),
+        ("$30/h in day", "$720.00/d"),
+        ("€50 / hour * 90 min", "€75.00"),
+    ];
+    for (expression, expected) in cases {
+        assert_eq!(
+            engine.eval(expression).to_string(),
+            expected,
+            "{expression}"
+        );
+    }
+
+    // A variable with a unit's name still divides as a variable
+    engine.eval("h = 4");
+    assert_eq!(engine.eval("$20/h").to_string(), "$5.00");
+    // ...and a division chain stays left-associative
+    engine.eval("h = 2");
+    assert_eq!(engine.eval("12 / 3 / h").to_string(), "2");
+    engine.eval("m = 3");
+    assert_eq!(engine.eval("60 / 2 / m").to_string(), "10");
+}
+
+#[test]
+fn test_grocery_prices_with_spaced_slash() {
+    let mut engine = Engine::new();
+
+    let rate = engine.eval("$20 / kg");
+    assert!(matches!(
+        rate,
+        Value::CurrencyRate {
+            currency: Currency::USD,
+            ..
+        }
+    ));
+    assert_eq!(rate.to_string(), "$20.00/kg");
+    assert_eq!(engine.eval("$20 / kg * 3 kg").to_string(), "$60.00");
+
+    engine.clear();
+    assert_eq!(engine.eval("apples = $3 / kg * 2 kg").to_string(), "$6.00");
+    assert_eq!(
+        engine.eval("cheese = $24 / kg * 250 g").to_string(),
+        "$6.00"
+    );
+    assert_eq!(
+        engine.eval("rice = 1.5 kg * $2.40 / kg").to_string(),
+        "$3.60"
+    );
+    assert_eq!(engine.eval("sum(above)").to_string(), "$15.60");
+}
+
+#[test]
+fn test_price_per_unit_errors_and_totals() {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval("$20 per kg * 3 m").to_string(),
+        "Error: Cannot apply a price per mass to length"
+    );
+    assert!(engine.eval("$20 per kg + 5").is_error());
+
+    // Rates are not amounts, so they stay out of grouped totals
+    engine.clear();
+    engine.eval("$20 per kg");
+    engine.eval("$5");
+    let totals = engine.grouped_totals();
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].to_string(), "$5.00");
+}
@@ -20,6 +20,29 @@ fn test_tip_calculation() {
     assert_eq!(result.as_decimal(), Some(d("12.75")));
 }
 
+#[test]
+fn test_quantity_words() {
+    let mut engine = Engine::new();
+    assert_eq!(engine.eval("half of 200").as_decimal(), Some(d("100")));
+    assert_eq!(engine.eval("a quarter of $80").to_string(), "$20.00");
+    assert_eq!(engine.eval("a dozen @ $3").to_string(), "$36.00");
+    assert_eq!(engine.eval("a dozen eggs at $3").to_string(), "$36.00");
+    assert_eq!(engine.eval("a thousand + 1").as_decimal(), Some(d("1001")));
+    assert_eq!(engine.eval("2 thousand").as_decimal(), Some(d("2000")));
+
+    // Outside those forms the words are plain variables
+    assert_eq!(
+        engine.eval("two thousand").to_string(),
+        "Error: unknown variable: thousand"
+    );
+    assert_eq!(
+        engine.eval("dozen").to_string(),
+        "Error: unknown variable: dozen"
+    );
+    engine.eval("dozen = 13");
+    assert_eq!(engine.eval("dozen * 2").as_decimal(), Some(d("26")));
+}
+
 #[test]
 fn test_split_bill() {
     let mut engine = Engine::new();
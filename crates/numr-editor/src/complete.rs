@@ -0,0 +1,187 @@
+//! Prefix completion for numr expressions.
+//!
+//! Candidates come from the same registries the highlighter uses: currency
+//! codes and aliases, unit names, built-in functions, keywords, constants,
+//! and the caller's variable names.
+//!
+//! # Example
+//!
+//! ```
+//! use numr_editor::{complete, CompletionKind};
+//!
+//! let completions = complete("sq", 2, &[]);
+//! assert_eq!(completions[0].text, "sqrt");
+//! assert_eq!(completions[0].kind, CompletionKind::Function);
+//! assert_eq!(completions[0].range, 0..2);
+//! ```
+
+use numr_core::{
+    catalog::{BUILTIN_FUNCTIONS, KEYWORDS, MATH_CONSTANTS},
+    types::unit::{all_aliases, all_symbols},
+    Currency,
+};
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Category of a completion candidate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "wasm", derive(serde::Serialize))]
+#[cfg_attr(feature = "wasm", serde(rename_all = "snake_case"))]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Currency,
+    Unit,
+    Keyword,
+    Constant,
+}
+
+/// A completion candidate and the character range it replaces
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(serde::Serialize))]
+pub struct Completion {
+    pub text: String,
+    pub kind: CompletionKind,
+    /// Character (not byte) range of the word under the cursor
+    pub range: Range<usize>,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Suggest completions for the word ending at `cursor` (a character index).
+///
+/// Matching is a case-insensitive prefix match. Results are ordered by kind
+/// (variables first), then by length, then alphabetically. Candidates equal
+/// to the typed word are omitted.
+pub fn complete(input: &str, cursor: usize, variables: &[String]) -> Vec<Completion> {
+    let chars: Vec<char> = input.chars().collect();
+    let cursor = cursor.min(chars.len());
+
+    let mut start = cursor;
+    while start > 0 && is_word_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while end < chars.len() && is_word_char(chars[end]) {
+        end += 1;
+    }
+    // Words never start with a digit ("5km" is a number followed by a unit)
+    while start < cursor && chars[start].is_ascii_digit() {
+        start += 1;
+    }
+    if start == cursor {
+        return Vec::new();
+    }
+
+    let prefix: String = chars[start..cursor]
+        .iter()
+        .collect::<String>()
+        .to_lowercase();
+    let word: String = chars[start..end].iter().collect();
+
+    let candidates = variables
+        .iter()
+        .map(|name| (name.as_str(), CompletionKind::Variable))
+        .chain(
+            BUILTIN_FUNCTIONS
+                .iter()
+                .map(|name| (*name, CompletionKind::Function)),
+        )
+        .chain(Currency::all_codes().map(|code| (code, CompletionKind::Currency)))
+        .chain(Currency::all_aliases().map(|alias| (alias, CompletionKind::Currency)))
+        .chain(Currency::all_subunit_aliases().map(|alias| (alias, CompletionKind::Currency)))
+        .chain(all_symbols().map(|symbol| (symbol, CompletionKind::Unit)))
+        .chain(all_aliases().map(|alias| (alias, CompletionKind::Unit)))
+        .chain(
+            KEYWORDS
+                .iter()
+                .map(|keyword| (*keyword, CompletionKind::Keyword)),
+        )
+        .chain(
+            MATH_CONSTANTS
+                .iter()
+                .map(|constant| (*constant, CompletionKind::Constant)),
+        );
+
+    // Candidates arrive in priority order, so the first spelling of a word wins
+    // ("AVAX" over its alias "avax"). Multi-word aliases cannot be typed as one word.
+    let mut seen = HashSet::new();
+    let mut completions: Vec<Completion> = candidates
+        .filter(|(text, _)| text.chars().all(is_word_char))
+        .filter(|(text, _)| text.to_lowercase().starts_with(&prefix) && *text != word)
+        .filter(|(text, _)| seen.insert(text.to_lowercase()))
+        .map(|(text, kind)| Completion {
+            text: text.to_string(),
+            kind,
+            range: start..end,
+        })
+        .collect();
+
+    completions.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then(a.text.chars().count().cmp(&b.text.chars().count()))
+            .then(a.text.cmp(&b.text))
+    });
+    completions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(completions: &[Completion]) -> Vec<&str> {
+        completions.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn completes_functions_currencies_and_units() {
+        let completions = complete("sq", 2, &[]);
+        assert_eq!(texts(&completions), ["sqrt"]);
+
+        let completions = complete("100 eu", 6, &[]);
+        assert!(completions
+            .iter()
+            .any(|c| c.text == "EUR" && c.kind == CompletionKind::Currency));
+        assert!(completions.iter().all(|c| c.range == (4..6)));
+
+        let completions = complete("5 kilo", 6, &[]);
+        assert!(completions
+            .iter()
+            .any(|c| c.kind == CompletionKind::Unit && c.text.starts_with("kilo")));
+    }
+
+    #[test]
+    fn variables_rank_first() {
+        let variables = vec!["salary".to_string(), "savings".to_string()];
+        let completions = complete("sa", 2, &variables);
+        assert_eq!(texts(&completions)[..2], ["salary", "savings"]);
+        assert!(completions
+            .iter()
+            .any(|c| c.text == "satoshi" && c.kind == CompletionKind::Currency));
+    }
+
+    #[test]
+    fn replaces_the_whole_word_under_the_cursor() {
+        let completions = complete("sqr(16)", 2, &[]);
+        assert_eq!(completions[0].text, "sqrt");
+        assert_eq!(completions[0].range, 0..3);
+    }
+
+    #[test]
+    fn uses_character_offsets() {
+        let completions = complete("€5 + av", 7, &[]);
+        assert_eq!(texts(&completions), ["avg", "average", "AVAX", "avalanche"]);
+        assert_eq!(completions[0].range, 5..7);
+    }
+
+    #[test]
+    fn no_completions_without_a_word_prefix() {
+        assert!(complete("", 0, &[]).is_empty());
+        assert!(complete("10 + ", 5, &[]).is_empty());
+        assert!(complete("42", 2, &[]).is_empty());
+        assert!(complete("sqrt", 4, &[]).is_empty());
+    }
+}
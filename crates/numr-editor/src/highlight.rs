@@ -7,14 +7,15 @@
 //!
 //! The tokenizer recognizes several semantic categories:
 //!
-//! - **Numbers**: Numeric literals including decimals and percentages
-//! - **Operators**: Arithmetic operators (+, -, *, /, ^, =)
+//! - **Numbers**: Numeric literals including decimals, percentages, and scale words
+//! - **Operators**: Arithmetic and bitwise operators (+, -, *, /, ^, =, &, <<, >>)
 //! - **Variables**: User-defined variable names (in assignment context)
 //! - **Units**: Physical units (km, kg, m, etc.)
 //! - **Currencies**: Currency symbols ($, €) and codes (USD, EUR)
 //! - **Keywords**: Conversion keywords (in, to, of)
 //! - **Functions**: Built-in functions (sum, avg, sqrt, etc.)
 //! - **Comments**: Lines or inline text starting with # or //
+//! - **Unmatched brackets**: Parentheses without a partner, such as the `(` in `(1 + 2`
 //!
 //! # Example
 //!
@@ -27,7 +28,11 @@
 //! ```
 
 use numr_core::{
-    catalog::{is_builtin_function, ANSWER_ALIASES, KEYWORDS, MATH_CONSTANTS},
+    catalog::{
+        is_builtin_function, money_suffix_exponent, quantity_word_value, si_suffix_exponent,
+        ANSWER_ALIASES, KEYWORDS, LINE_REFERENCES, MATH_CONSTANTS,
+    },
+    strip_comment,
     types::unit::{all_aliases, all_symbols},
     Currency,
 };
@@ -50,6 +55,7 @@ pub enum TokenType {
     Text, // Unrecognized prose
     Whitespace,
     Punctuation,
+    UnmatchedBracket, // "(" or ")" without a partner
 }
 
 /// A token with its text and semantic type
@@ -73,6 +79,7 @@ static CURRENCY_WORDS: LazyLock<HashSet<String>> = LazyLock::new(|| {
     Currency::all_aliases()
         .map(|s| s.to_lowercase())
         .chain(Currency::all_codes().map(|s| s.to_lowercase()))
+        .chain(Currency::all_subunit_aliases().map(str::to_string))
         .collect()
 });
 
@@ -110,7 +117,7 @@ pub fn tokenize(input: &str) -> Vec<Token> {
     let mut i = 0;
 
     // Check if line has assignment (word = ...) to identify variable definition
-    let assignment_var = find_assignment_variable(input);
+    let assignment_vars = find_assignment_variables(input);
 
     while i < chars.len() {
         let c = chars[i];
@@ -135,6 +142,31 @@ pub fn tokenize(input: &str) -> Vec<Token> {
             while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
                 i += 1;
             }
+            // Hexadecimal and binary literals ("0xFF", "0b1010")
+            let digits: String = chars[start..i].iter().collect();
+            if digits.trim_start_matches('-') == "0"
+                && matches!(chars.get(i), Some('x' | 'X' | 'b' | 'B'))
+                && chars.get(i + 1).is_some_and(char::is_ascii_hexdigit)
+            {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+            }
+            // SI suffixes written without a space ("3k") are part of the number literal
+            let word_end = (i..chars.len())
+                .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'))
+                .unwrap_or(chars.len());
+            let suffix: String = chars[i..word_end].iter().collect();
+            // So are money multipliers right after a currency symbol ("$1.5k", "€2m")
+            let after_symbol = tokens
+                .last()
+                .is_some_and(|token: &Token| token.token_type == TokenType::Currency);
+            if si_suffix_exponent(&suffix).is_some()
+                || (after_symbol && money_suffix_exponent(&suffix).is_some())
+            {
+                i = word_end;
+            }
             if i < chars.len() && chars[i] == '%' {
                 i += 1;
             }
@@ -157,7 +189,10 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 token_type: TokenType::Unit,
             });
             i += 1;
-        } else if matches!(c, '+' | '*' | '/' | '^' | '×' | '÷' | '-' | '=') {
+        } else if matches!(
+            c,
+            '+' | '*' | '/' | '^' | '×' | '÷' | '@' | '-' | '−' | '=' | '&' | '<' | '>'
+        ) {
             tokens.push(Token {
                 text: c.to_string(),
                 token_type: TokenType::Operator,
@@ -188,18 +223,23 @@ pub fn tokenize(input: &str) -> Vec<Token> {
             } else if is_builtin_function(&lower) && is_function_call {
                 TokenType::Function
             } else if MATH_CONSTANTS.contains(&lower.as_str())
+                || LINE_REFERENCES.contains(&word.as_str())
                 || ANSWER_ALIASES
                     .iter()
                     .any(|alias| alias.eq_ignore_ascii_case(&word))
             {
                 TokenType::Variable
+            } else if quantity_word_value(&lower).is_some() {
+                // Scale words ("2 million") are part of the number literal, and
+                // quantity words ("a dozen") stand for one
+                TokenType::Number
+            } else if assignment_vars.contains(&word.as_str()) {
+                // Variable being defined, even when it shadows a unit ("h = 5")
+                TokenType::Variable
             } else if UNIT_WORDS.contains(&lower) {
                 TokenType::Unit
             } else if CURRENCY_WORDS.contains(&lower) {
                 TokenType::Currency
-            } else if assignment_var == Some(word.as_str()) {
-                // Variable being defined
-                TokenType::Variable
             } else {
                 // Unknown word - plain text
                 TokenType::Text
@@ -209,7 +249,7 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 text: word,
                 token_type,
             });
-        } else if c == '(' || c == ')' || c == ',' {
+        } else if c == '(' || c == ')' || c == ',' || c == '|' {
             tokens.push(Token {
                 text: c.to_string(),
                 token_type: TokenType::Punctuation,
@@ -235,51 +275,119 @@ pub fn tokenize(input: &str) -> Vec<Token> {
         }
     }
 
+    mark_unmatched_brackets(&mut tokens);
     tokens
 }
 
+/// Retag parentheses that have no partner so frontends can flag them
+fn mark_unmatched_brackets(tokens: &mut [Token]) {
+    let mut open = Vec::new();
+    for (index, token) in tokens.iter_mut().enumerate() {
+        if token.token_type != TokenType::Punctuation {
+            continue;
+        }
+        if token.text == "(" {
+            open.push(index);
+        } else if token.text == ")" && open.pop().is_none() {
+            token.token_type = TokenType::UnmatchedBracket;
+        }
+    }
+    for index in open {
+        tokens[index].token_type = TokenType::UnmatchedBracket;
+    }
+}
+
+/// Find the parenthesis matching the one at character index `pos`.
+///
+/// Returns the character index of the partner, or `None` when `pos` is not
+/// on a parenthesis, the parenthesis is unbalanced, or it sits in a comment.
+///
+/// ```
+/// use numr_editor::match_bracket;
+///
+/// assert_eq!(match_bracket("(1 + (2 * 3))", 0), Some(12));
+/// assert_eq!(match_bracket("(1 + (2 * 3))", 11), Some(5));
+/// assert_eq!(match_bracket("(1 + 2", 0), None);
+/// ```
+pub fn match_bracket(input: &str, pos: usize) -> Option<usize> {
+    let chars: Vec<char> = expression_prefix(input).chars().collect();
+    let forward = match chars.get(pos)? {
+        '(' => true,
+        ')' => false,
+        _ => return None,
+    };
+
+    let mut depth = 0usize;
+    let mut step = |index: usize| {
+        match chars[index] {
+            '(' if forward => depth += 1,
+            ')' if !forward => depth += 1,
+            '(' | ')' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    };
+    if forward {
+        (pos..chars.len()).find(|&index| step(index))
+    } else {
+        (0..=pos).rev().find(|&index| step(index))
+    }
+}
+
 /// Tokenize input and promote known variable references.
+///
+/// Variables shadow unit and currency words (`h + 1` reads the variable `h`),
+/// except directly after a number, where the word is still a suffix (`2 h`).
 pub fn tokenize_with_variables(input: &str, variables: &HashSet<String>) -> Vec<Token> {
-    tokenize(input)
-        .into_iter()
-        .map(|mut token| {
-            if token.token_type == TokenType::Text && variables.contains(&token.text) {
-                token.token_type = TokenType::Variable;
-            }
-            token
-        })
-        .collect()
+    let mut tokens = tokenize(input);
+    let mut after_number = false;
+    for token in &mut tokens {
+        let shadowable = match token.token_type {
+            TokenType::Text => true,
+            TokenType::Unit | TokenType::Currency => !after_number,
+            _ => false,
+        };
+        if shadowable && variables.contains(&token.text) {
+            token.token_type = TokenType::Variable;
+        }
+        if token.token_type != TokenType::Whitespace {
+            after_number = token.token_type == TokenType::Number;
+        }
+    }
+    tokens
 }
 
 /// Return the executable prefix of a line, excluding trailing whitespace and comments.
 ///
 /// UI surfaces use this as the semantic anchor for results when a logical line wraps.
 pub fn expression_prefix(input: &str) -> &str {
-    let comment_start = [input.find('#'), input.find("//")]
-        .into_iter()
-        .flatten()
-        .min()
-        .unwrap_or(input.len());
-    input[..comment_start].trim_end()
+    strip_comment(input).trim_end()
 }
 
-/// Find variable name if line is an assignment (e.g., "tax = 20%" returns Some("tax"))
-fn find_assignment_variable(input: &str) -> Option<&str> {
-    if let Some((var_part, _)) = input.split_once('=') {
-        let var_part = var_part.trim();
+/// Find the variable names a line assigns (e.g., "tax = 20%" or "tax += 1%" returns ["tax"],
+/// and the chain "x = y = 100" returns ["x", "y"])
+fn find_assignment_variables(input: &str) -> Vec<&str> {
+    let segments: Vec<&str> = input.split('=').collect();
+    let mut names = Vec::new();
+    for var_part in &segments[..segments.len() - 1] {
+        let var_part = var_part
+            .strip_suffix(['+', '-', '*', '/'])
+            .unwrap_or(var_part)
+            .trim();
         // Check it's a valid identifier
-        if !var_part.is_empty()
-            && var_part
+        if var_part.is_empty()
+            || !var_part
                 .chars()
                 .next()
                 .map(|c| c.is_alphabetic() || c == '_')
                 .unwrap_or(false)
-            && var_part.chars().all(|c| c.is_alphanumeric() || c == '_')
+            || !var_part.chars().all(|c| c.is_alphanumeric() || c == '_')
         {
-            return Some(var_part);
+            break;
         }
+        names.push(var_part);
     }
-    None
+    names
 }
 
 /// Check if 'x' at position i is likely a multiplication operator.
@@ -330,6 +438,42 @@ mod tests {
     fn test_percentage() {
         let tokens = tokenize("20%");
         assert!(has_token(&tokens, "20%", TokenType::Number));
+
+        let tokens = tokenize("tax += 1%");
+        assert!(has_token(&tokens, "tax", TokenType::Variable));
+
+        let tokens = tokenize("low = high = 5");
+        assert!(has_token(&tokens, "low", TokenType::Variable));
+        assert!(has_token(&tokens, "high", TokenType::Variable));
+    }
+
+    #[test]
+    fn test_scale_word() {
+        let tokens = tokenize("2 million usd");
+        assert!(has_token(&tokens, "million", TokenType::Number));
+        assert!(has_token(&tokens, "usd", TokenType::Currency));
+
+        let tokens = tokenize("half of a dozen");
+        assert!(has_token(&tokens, "half", TokenType::Number));
+        assert!(has_token(&tokens, "dozen", TokenType::Number));
+    }
+
+    #[test]
+    fn test_si_suffix() {
+        let tokens = tokenize("3k + 500m");
+        assert!(has_token(&tokens, "3k", TokenType::Number));
+        assert!(has_token(&tokens, "500", TokenType::Number));
+        assert!(has_token(&tokens, "m", TokenType::Unit));
+    }
+
+    #[test]
+    fn test_money_shorthand() {
+        let tokens = tokenize("$1.5k + €2m");
+        assert!(has_token(&tokens, "1.5k", TokenType::Number));
+        assert!(has_token(&tokens, "2m", TokenType::Number));
+        // Without a currency, "m" is still meters
+        let tokens = tokenize("2m");
+        assert!(has_token(&tokens, "m", TokenType::Unit));
     }
 
     #[test]
@@ -341,6 +485,24 @@ mod tests {
         assert!(has_token(&tokens, "/", TokenType::Operator));
     }
 
+    #[test]
+    fn test_bitwise_operators() {
+        let tokens = tokenize("0xFF & 0b1010 << 2 xor 3");
+        assert!(has_token(&tokens, "0xFF", TokenType::Number));
+        assert!(has_token(&tokens, "0b1010", TokenType::Number));
+        assert!(has_token(&tokens, "&", TokenType::Operator));
+        assert!(has_token(&tokens, "<", TokenType::Operator));
+        assert!(has_token(&tokens, "xor", TokenType::Keyword));
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        let tokens = tokenize("6 × 7 ÷ 2 − 1");
+        assert!(has_token(&tokens, "×", TokenType::Operator));
+        assert!(has_token(&tokens, "÷", TokenType::Operator));
+        assert!(has_token(&tokens, "−", TokenType::Operator));
+    }
+
     #[test]
     fn test_multiply_x_between_numbers() {
         let tokens = tokenize("2x3");
@@ -375,9 +537,25 @@ mod tests {
         assert!(has_token(&tokens, "tax", TokenType::Variable));
     }
 
+    #[test]
+    fn test_variables_shadowing_units() {
+        let tokens = tokenize("h = 5");
+        assert!(has_token(&tokens, "h", TokenType::Variable));
+
+        let variables = HashSet::from(["h".to_string(), "btc".to_string()]);
+        let tokens = tokenize_with_variables("h * btc", &variables);
+        assert!(has_token(&tokens, "h", TokenType::Variable));
+        assert!(has_token(&tokens, "btc", TokenType::Variable));
+
+        // A word right after a number is still a unit suffix
+        let tokens = tokenize_with_variables("2 h + 3btc", &variables);
+        assert!(has_token(&tokens, "h", TokenType::Unit));
+        assert!(has_token(&tokens, "btc", TokenType::Currency));
+    }
+
     #[test]
     fn test_language_catalog_constants_and_answer_aliases() {
-        for input in ["pi", "e", "phi", "_", "ANS", "ans"] {
+        for input in ["pi", "e", "phi", "_", "ANS", "ans", "above"] {
             let tokens = tokenize(input);
             assert!(has_token(&tokens, input, TokenType::Variable), "{input}");
         }
@@ -493,4 +671,45 @@ mod tests {
         assert!(has_token(&tokens, "usd", TokenType::Currency));
         assert!(has_token(&tokens, "here", TokenType::Text));
     }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        let types = |input: &str| -> Vec<TokenType> {
+            tokenize(input)
+                .into_iter()
+                .filter(|t| t.text == "(" || t.text == ")")
+                .map(|t| t.token_type)
+                .collect()
+        };
+        assert_eq!(
+            types("(1 + 2) * 3"),
+            [TokenType::Punctuation, TokenType::Punctuation]
+        );
+        assert_eq!(types("(1 + 2"), [TokenType::UnmatchedBracket]);
+        assert_eq!(
+            types("1 + 2) * (3"),
+            [TokenType::UnmatchedBracket, TokenType::UnmatchedBracket]
+        );
+        assert_eq!(
+            types("((1 + 2) * 3"),
+            [
+                TokenType::UnmatchedBracket,
+                TokenType::Punctuation,
+                TokenType::Punctuation
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_bracket() {
+        assert_eq!(match_bracket("sqrt(16)", 4), Some(7));
+        assert_eq!(match_bracket("sqrt(16)", 7), Some(4));
+        assert_eq!(match_bracket("sqrt(16)", 0), None);
+        assert_eq!(match_bracket("1 + 2)", 5), None);
+        assert_eq!(match_bracket("", 0), None);
+        // Character (not byte) offsets
+        assert_eq!(match_bracket("(€5 + €3)", 8), Some(0));
+        // Brackets inside comments are ignored
+        assert_eq!(match_bracket("(1) # (note)", 6), None);
+    }
 }
@@ -1,8 +1,12 @@
+pub mod complete;
 pub mod highlight;
 mod text;
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
-pub use highlight::{expression_prefix, tokenize, tokenize_with_variables, Token, TokenType};
+pub use complete::{complete, Completion, CompletionKind};
+pub use highlight::{
+    expression_prefix, match_bracket, tokenize, tokenize_with_variables, Token, TokenType,
+};
 pub use text::char_to_byte_idx;
@@ -3,7 +3,8 @@
 use crate::config::Config;
 use crate::line_layout::{measure_wrapped_cursor, wrapped_height};
 use crate::persistence::atomic_write;
-use numr_core::{Decimal, Engine, FetchConfig, RateError, Value};
+use crate::recent::RecentFiles;
+use numr_core::{Decimal, Engine, EvalError, FetchConfig, RateError, Value};
 use numr_editor::char_to_byte_idx;
 use std::collections::{HashMap, HashSet};
 use std::fs;
@@ -18,6 +19,8 @@ use std::time::{Duration, Instant};
 /// Status message for successful save
 pub(crate) const STATUS_SAVED: &str = "Saved";
 pub(crate) const STATUS_RATES_UNAVAILABLE: &str = "Rates unavailable";
+/// Status message when switching files would discard unsaved changes
+pub(crate) const STATUS_UNSAVED_SWITCH: &str = "Save changes before switching files";
 
 /// Timeout for "Saved" status message (milliseconds)
 const STATUS_SAVED_TIMEOUT_MS: u128 = 1500;
@@ -67,6 +70,8 @@ struct Document {
     path: Option<PathBuf>,
     dirty: bool,
     persist_rates: bool,
+    /// Show conversion results beside their original value (`5 km → 3.11 mi`)
+    show_conversion_source: bool,
     engine: Engine,
 }
 
@@ -75,6 +80,8 @@ struct DocumentRenderState {
     variable_names: HashSet<String>,
     result_texts: Vec<Option<String>>,
     error_texts: Vec<Option<String>>,
+    /// Full-precision amount and scale of each result, for the debug panel
+    detail_texts: Vec<Option<String>>,
     max_result_width: usize,
     totals_text: String,
 }
@@ -88,6 +95,7 @@ impl Document {
             path,
             dirty: false,
             persist_rates: true,
+            show_conversion_source: false,
             engine: Engine::new(),
         };
         document.refresh_results();
@@ -107,6 +115,7 @@ impl Document {
             path: None,
             dirty: false,
             persist_rates: false,
+            show_conversion_source: false,
             engine: Engine::new(),
         };
         document.refresh_results();
@@ -168,6 +177,13 @@ impl Document {
             .and_then(Option::as_deref)
     }
 
+    pub fn current_line_detail(&self, line_idx: usize) -> Option<&str> {
+        self.render_state
+            .detail_texts
+            .get(line_idx)
+            .and_then(Option::as_deref)
+    }
+
     pub fn load(&mut self) -> io::Result<()> {
         if let Some(path) = &self.path {
             let content = fs::read_to_string(path)?;
@@ -181,6 +197,24 @@ impl Document {
         Ok(())
     }
 
+    /// Replace the contents with another file, keeping the engine and its rates.
+    /// A file that does not exist yet opens as an empty document.
+    pub fn open(&mut self, path: PathBuf) -> io::Result<()> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        self.lines = content.lines().map(String::from).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.path = Some(path);
+        self.refresh_results();
+        self.dirty = false;
+        Ok(())
+    }
+
     pub fn save(&mut self) -> io::Result<()> {
         let path = self.path.as_ref().ok_or_else(|| {
             io::Error::new(io::ErrorKind::InvalidInput, "document has no save path")
@@ -217,6 +251,11 @@ impl Document {
         self.recompute_results();
     }
 
+    pub fn set_show_conversion_source(&mut self, show: bool) {
+        self.show_conversion_source = show;
+        self.refresh_results();
+    }
+
     /// Re-evaluate all lines without marking dirty (for loads, rate updates, etc.)
     pub fn refresh_results(&mut self) {
         self.recompute_results();
@@ -325,27 +364,41 @@ impl Document {
         self.engine.clear();
         self.results.clear();
 
+        let mut conversion_sources = Vec::with_capacity(self.lines.len());
         for line in &self.lines {
-            let value = if line.trim().is_empty() {
-                Value::Empty
+            let (value, source) = if line.trim().is_empty() {
+                (Value::Empty, None)
             } else {
-                self.engine.eval(line)
+                let value = self.engine.eval(line);
+                let source = self
+                    .engine
+                    .lines()
+                    .last()
+                    .and_then(|result| result.converted_from.clone());
+                (value, source)
             };
             self.results.push(value);
+            conversion_sources.push(source);
         }
 
+        // User function names are highlighted like variables
         let variable_names = self
             .engine
             .variables()
             .into_iter()
             .map(|(name, _)| name)
+            .chain(self.engine.functions())
             .collect();
         let result_texts: Vec<Option<String>> = self
             .results
             .iter()
-            .map(|value| {
+            .zip(&conversion_sources)
+            .map(|(value, source)| {
                 if value.is_error() || value.is_empty() {
                     None
+                } else if let Some(source) = source.as_ref().filter(|_| self.show_conversion_source)
+                {
+                    Some(value.format_with_source(source, self.engine.format_options()))
                 } else {
                     Some(value.to_string())
                 }
@@ -361,10 +414,23 @@ impl Document {
             .results
             .iter()
             .map(|value| match value {
+                // Point the debug panel at the column where parsing stopped
+                Value::Error(EvalError::Parse(error)) => Some(match error.offset() {
+                    Some(offset) => format!("{error} (column {})", offset + 1),
+                    None => error.to_string(),
+                }),
                 Value::Error(error) => Some(error.to_string()),
                 _ => None,
             })
             .collect();
+        let detail_texts = self
+            .results
+            .iter()
+            .map(|value| {
+                let amount = value.as_decimal()?;
+                Some(format!("{amount} (scale {})", value.scale()?))
+            })
+            .collect();
         let totals_text = self
             .engine
             .grouped_totals()
@@ -376,6 +442,7 @@ impl Document {
             variable_names,
             result_texts,
             error_texts,
+            detail_texts,
             max_result_width,
             totals_text,
         };
@@ -717,6 +784,9 @@ pub struct App {
     pub show_line_numbers: bool,
     pub show_header: bool,
     pub show_quit_confirmation: bool,
+    pub show_recent_files: bool,
+    pub recent_selection: usize,
+    recent_files: RecentFiles,
     config: Config,
 }
 
@@ -744,6 +814,9 @@ impl App {
             ..Self::default()
         };
 
+        let show_conversion_source = app.config.preferences.show_conversion_source;
+        app.document
+            .set_show_conversion_source(show_conversion_source);
         if let Err(error) = app.document.load_rates_from_cache() {
             app.set_status(&format!("Rates cache error: {error}"));
         }
@@ -755,6 +828,13 @@ impl App {
                 }
             }
         }
+
+        let (recent_files, recent_warning) = RecentFiles::load();
+        app.recent_files = recent_files;
+        if let Some(warning) = recent_warning {
+            app.set_status(&warning);
+        }
+        app.remember_current_file();
         app
     }
 
@@ -808,6 +888,7 @@ impl App {
         self.config.preferences.show_line_numbers = self.show_line_numbers;
         self.config.preferences.show_header = self.show_header;
         self.config.preferences.debug_mode = self.debug_mode;
+        self.config.preferences.show_conversion_source = self.show_conversion_source();
         if let Err(error) = self.config.save() {
             self.set_status(&format!("Config error: {error}"));
         }
@@ -830,6 +911,17 @@ impl App {
         self.save_config();
     }
 
+    pub fn show_conversion_source(&self) -> bool {
+        self.document.show_conversion_source
+    }
+
+    /// Toggle showing the original value beside conversion results
+    pub fn toggle_conversion_source(&mut self) {
+        let show = !self.show_conversion_source();
+        self.document.set_show_conversion_source(show);
+        self.save_config();
+    }
+
     /// Toggle wrap mode
     pub fn toggle_wrap(&mut self) {
         self.wrap_mode = !self.wrap_mode;
@@ -889,6 +981,83 @@ impl App {
         }
     }
 
+    /// Paths of recently opened files, most recent first
+    pub fn recent_files(&self) -> &[PathBuf] {
+        self.recent_files.paths()
+    }
+
+    /// Move the current file to the front of the recent-files list
+    fn remember_current_file(&mut self) {
+        let Some(path) = self.document.path() else {
+            return;
+        };
+        self.recent_files.add(path);
+        if let Err(error) = self.recent_files.save() {
+            self.set_status(&format!("Recent files error: {error}"));
+        }
+    }
+
+    /// Open the recent-files popup with the most recent other file selected
+    pub fn show_recent_files_popup(&mut self) {
+        if self.recent_files.is_empty() {
+            self.set_status("No recent files");
+            return;
+        }
+        let current = self
+            .document
+            .path()
+            .and_then(|path| std::path::absolute(path).ok());
+        self.recent_selection = self
+            .recent_files()
+            .iter()
+            .position(|path| Some(path) != current.as_ref())
+            .unwrap_or(0);
+        self.show_recent_files = true;
+    }
+
+    /// Select the previous entry in the recent-files popup
+    pub fn recent_select_up(&mut self) {
+        self.recent_selection = self.recent_selection.saturating_sub(1);
+    }
+
+    /// Select the next entry in the recent-files popup
+    pub fn recent_select_down(&mut self) {
+        let last = self.recent_files().len().saturating_sub(1);
+        self.recent_selection = (self.recent_selection + 1).min(last);
+    }
+
+    /// Switch to the file selected in the recent-files popup.
+    /// Unsaved changes are never discarded: the switch is refused instead.
+    pub fn open_selected_recent_file(&mut self) {
+        self.show_recent_files = false;
+        let Some(path) = self.recent_files().get(self.recent_selection).cloned() else {
+            return;
+        };
+        let current = self
+            .document
+            .path()
+            .and_then(|path| std::path::absolute(path).ok());
+        if current.as_ref() == Some(&path) {
+            return;
+        }
+        if self.document.dirty() {
+            self.set_status(STATUS_UNSAVED_SWITCH);
+            return;
+        }
+
+        match self.document.open(path) {
+            Ok(()) => {
+                self.view = ViewState {
+                    viewport_width: self.view.viewport_width,
+                    viewport_height: self.view.viewport_height,
+                    ..ViewState::default()
+                };
+                self.remember_current_file();
+            }
+            Err(error) => self.set_status(&format!("Open failed: {error}")),
+        }
+    }
+
     /// Toggle help popup
     pub fn toggle_help(&mut self) {
         self.show_help = !self.show_help;
@@ -1152,6 +1321,11 @@ impl App {
         self.document.current_line_error(self.view.cursor_y)
     }
 
+    /// Get the full-precision result of the current line (for debug panel)
+    pub fn current_line_detail(&self) -> Option<&str> {
+        self.document.current_line_detail(self.view.cursor_y)
+    }
+
     /// Update exchange rates and save to cache
     pub fn update_rates(&mut self, result: Result<numr_core::FetchResult, String>) {
         match result {
@@ -1198,6 +1372,9 @@ impl Default for App {
             show_line_numbers: false,
             show_header: false,
             show_quit_confirmation: false,
+            show_recent_files: false,
+            recent_selection: 0,
+            recent_files: RecentFiles::default(),
             config: Config::default(),
         }
     }
@@ -1483,4 +1660,76 @@ mod tests {
         app.document.refresh_results();
         assert!(app.is_dirty());
     }
+
+    #[test]
+    fn test_conversion_source_display() {
+        let mut document =
+            Document::from_lines(vec!["5 km in m".to_string(), "5 km + 2 km".to_string()]);
+        assert_eq!(document.result_text(0), Some("5000 m"));
+
+        document.set_show_conversion_source(true);
+        assert_eq!(document.result_text(0), Some("5 km → 5000 m"));
+        assert_eq!(document.result_text(1), Some("7 km"));
+        assert_eq!(document.max_result_width(), "5 km → 5000 m".chars().count());
+        assert!(!document.dirty);
+    }
+
+    #[test]
+    fn test_parse_errors_report_their_column() {
+        let app = App {
+            document: Document::from_lines(vec!["10 + )".to_string(), "1 / 0".to_string()]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            app.document.current_line_error(0),
+            Some("could not understand line (column 6)")
+        );
+        assert_eq!(app.document.current_line_error(1), Some("division by zero"));
+    }
+
+    #[test]
+    fn test_result_details_show_scale() {
+        let document = Document::from_lines(vec![
+            "1.50".to_string(),
+            "1 mi in km".to_string(),
+            "1 / 0".to_string(),
+        ]);
+
+        assert_eq!(document.current_line_detail(0), Some("1.50 (scale 2)"));
+        assert_eq!(document.current_line_detail(1), Some("1.609344 (scale 6)"));
+        assert_eq!(document.current_line_detail(2), None);
+    }
+
+    #[test]
+    fn recent_file_switch_keeps_unsaved_changes() {
+        let directory = temporary_directory("recent");
+        fs::create_dir_all(&directory).unwrap();
+        let first = directory.join("first.numr");
+        let second = directory.join("second.numr");
+        fs::write(&first, "1 + 1\n").unwrap();
+        fs::write(&second, "2 * 3\n").unwrap();
+
+        let mut app = App::default();
+        app.document.open(first.clone()).unwrap();
+        app.recent_files.add(&second);
+        app.recent_files.add(&first);
+
+        app.show_recent_files_popup();
+        assert!(app.show_recent_files);
+        assert_eq!(app.recent_selection, 1);
+
+        app.insert_char('0');
+        app.open_selected_recent_file();
+        assert_eq!(app.path(), Some(first.as_path()));
+        assert_eq!(app.status_message.as_deref(), Some(STATUS_UNSAVED_SWITCH));
+
+        app.save().unwrap();
+        app.show_recent_files_popup();
+        app.open_selected_recent_file();
+        assert_eq!(app.path(), Some(second.as_path()));
+        assert_eq!(app.lines(), &["2 * 3".to_string()]);
+        assert_eq!(app.recent_files()[0], second);
+        fs::remove_dir_all(directory).unwrap();
+    }
 }
@@ -36,6 +36,7 @@ pub struct Preferences {
     pub show_line_numbers: bool,
     pub show_header: bool,
     pub debug_mode: bool,
+    pub show_conversion_source: bool,
 }
 
 /// File path configuration
@@ -141,6 +142,7 @@ mod tests {
                 show_line_numbers: true,
                 show_header: true,
                 debug_mode: true,
+                show_conversion_source: true,
             },
             files: FilesConfig {
                 default_path: Some("~/custom/path.numr".to_string()),
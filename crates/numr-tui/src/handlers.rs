@@ -123,6 +123,26 @@ pub fn handle_help(
     }
 }
 
+/// Navigate the recent-files popup; every key is consumed while it is open.
+pub fn handle_recent_files(key_code: crossterm::event::KeyCode, app: &mut App) {
+    use crossterm::event::KeyCode;
+
+    match key_code {
+        KeyCode::Esc | KeyCode::Char('q') => app.show_recent_files = false,
+        KeyCode::Up | KeyCode::Char('k') => app.recent_select_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.recent_select_down(),
+        KeyCode::Enter => app.open_selected_recent_file(),
+        KeyCode::Char(c @ '1'..='9') => {
+            let index = c as usize - '1' as usize;
+            if index < app.recent_files().len() {
+                app.recent_selection = index;
+                app.open_selected_recent_file();
+            }
+        }
+        _ => {}
+    }
+}
+
 /// A single long-lived exchange-rate worker.
 ///
 /// Repeated refresh keys while a request is active are coalesced by `request`,
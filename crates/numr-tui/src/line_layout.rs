@@ -134,6 +134,7 @@ pub(crate) fn token_color(token_type: TokenType) -> Color {
         TokenType::Text => palette::TEXT,
         TokenType::Whitespace => Color::Reset,
         TokenType::Punctuation => palette::DIM,
+        TokenType::UnmatchedBracket => palette::ERROR,
     }
 }
 
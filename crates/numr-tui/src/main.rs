@@ -6,6 +6,7 @@ mod handlers;
 mod line_layout;
 mod persistence;
 mod popups;
+mod recent;
 mod theme;
 mod ui;
 
@@ -26,8 +27,8 @@ use app::{App, InputMode, KeybindingMode, PendingCommand};
 use clap::Parser;
 use directories::ProjectDirs;
 use handlers::{
-    handle_help, handle_keybinding_toggle, handle_quit, handle_quit_confirmation, handle_save,
-    QuitConfirmResult, QuitResult, RateFetcher,
+    handle_help, handle_keybinding_toggle, handle_quit, handle_quit_confirmation,
+    handle_recent_files, handle_save, QuitConfirmResult, QuitResult, RateFetcher,
 };
 use ratatui::layout::Rect;
 use std::path::PathBuf;
@@ -233,6 +234,11 @@ fn run_app(
                         }
                     }
 
+                    if app.show_recent_files {
+                        handle_recent_files(key.code, app);
+                        continue;
+                    }
+
                     // Handle keybinding mode toggle (Shift+Tab works in both modes)
                     if key.code == KeyCode::BackTab {
                         handle_keybinding_toggle(app);
@@ -324,6 +330,7 @@ fn handle_standard_mode(
         KeyCode::Char('r') if ctrl => {
             rate_fetcher.request(app);
         }
+        KeyCode::Char('o') if ctrl => app.show_recent_files_popup(),
         KeyCode::Char('k') if ctrl => app.delete_to_line_end(),
         KeyCode::Char('u') if ctrl => app.delete_to_line_start(),
         KeyCode::Char('w') if ctrl => app.delete_word_backward(),
@@ -334,6 +341,7 @@ fn handle_standard_mode(
         KeyCode::Char('g') if ctrl => app.move_to_first_line(),
         KeyCode::Char('l') if ctrl => app.toggle_line_numbers(),
         KeyCode::Char('z') if alt => app.toggle_wrap(),
+        KeyCode::Char('c') if alt => app.toggle_conversion_source(),
         KeyCode::Char('h') if ctrl => app.toggle_header(),
         KeyCode::F(12) => app.toggle_debug(),
         KeyCode::Char(c) if accepts_text_input(key.modifiers) => app.insert_char(c),
@@ -410,6 +418,7 @@ fn handle_vim_normal_mode(
         KeyCode::Char('r') if ctrl => {
             rate_fetcher.request(app);
         }
+        KeyCode::Char('o') if ctrl => app.show_recent_files_popup(),
         // Enter insert mode
         KeyCode::Char('i') => app.mode = InputMode::Insert,
         KeyCode::Char('a') => {
@@ -469,6 +478,7 @@ fn handle_vim_normal_mode(
         KeyCode::Char('W') => app.toggle_wrap(),
         KeyCode::Char('N') => app.toggle_line_numbers(),
         KeyCode::Char('H') => app.toggle_header(),
+        KeyCode::Char('S') => app.toggle_conversion_source(),
         KeyCode::F(12) => app.toggle_debug(),
         _ => {}
     }
@@ -1,4 +1,4 @@
-//! Popup dialogs (help, quit confirmation)
+//! Popup dialogs (help, quit confirmation, recent files)
 
 use ratatui::{
     layout::{Constraint, Rect},
@@ -11,6 +11,8 @@ use ratatui::{
     Frame,
 };
 
+use std::path::{Path, PathBuf};
+
 use crate::app::KeybindingMode;
 use crate::theme as palette;
 
@@ -115,6 +117,58 @@ pub fn draw_quit_popup(frame: &mut Frame, area: Rect) {
     frame.render_widget(paragraph, content_area);
 }
 
+/// Draw the recent-files quick-switch popup
+pub fn draw_recent_files_popup(
+    frame: &mut Frame,
+    area: Rect,
+    paths: &[PathBuf],
+    selected: usize,
+    current: Option<&Path>,
+) {
+    let width = 60_u16.min(area.width.saturating_sub(6));
+    let height = (paths.len() as u16 + 6).min(area.height.saturating_sub(2));
+    let content_area = render_popup_frame(frame, area, width, height, Some("Recent files"));
+    let current = current.and_then(|path| std::path::absolute(path).ok());
+
+    let mut text: Vec<Line> = paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("?");
+            let directory = path
+                .parent()
+                .and_then(|dir| dir.to_str())
+                .unwrap_or_default();
+            let is_current = current.as_deref() == Some(path.as_path());
+            let marker = if index == selected { "> " } else { "  " };
+            let name_style = if index == selected {
+                Style::new().fg(palette::ACCENT).bold()
+            } else {
+                Style::new()
+            };
+            Line::from(vec![
+                Span::styled(format!("{marker}{} ", index + 1), palette::DIM),
+                Span::styled(name.to_string(), name_style),
+                Span::styled(if is_current { " (open) " } else { " " }, palette::DIM),
+                Span::styled(directory.to_string(), palette::DIM),
+            ])
+        })
+        .collect();
+    text.push(Line::from(""));
+    text.push(Line::from(vec![
+        "[enter]".fg(palette::VARIABLE).bold(),
+        " open  ".into(),
+        "[esc]".fg(palette::DIM).bold(),
+        " cancel".into(),
+    ]));
+
+    let paragraph = Paragraph::new(text).block(Block::new().padding(Padding::horizontal(2)));
+    frame.render_widget(paragraph, content_area);
+}
+
 /// Draw the help popup with scroll support
 pub fn draw_help_popup(
     frame: &mut Frame,
@@ -208,8 +262,10 @@ fn vim_help_rows() -> Vec<Row<'static>> {
         Row::new(vec!["", ""]),
         Row::new(vec!["General", ""]).style(Style::new().bold().fg(palette::VARIABLE)),
         Row::new(vec!["W / N / H", "Toggle wrap/numbers/header"]),
+        Row::new(vec!["S", "Toggle conversion source"]),
         Row::new(vec!["Ctrl+s", "Save file"]),
         Row::new(vec!["Ctrl+r", "Refresh rates"]),
+        Row::new(vec!["Ctrl+o", "Recent files"]),
         Row::new(vec!["F12", "Toggle debug"]),
         Row::new(vec!["? / F1", "Toggle help"]),
         Row::new(vec!["q", "Quit"]),
@@ -239,9 +295,11 @@ fn standard_help_rows() -> Vec<Row<'static>> {
         Row::new(vec!["General", ""]).style(Style::new().bold().fg(palette::VARIABLE)),
         Row::new(vec!["? / F1", "Toggle help"]),
         Row::new(vec!["Option+z", "Toggle wrap"]),
+        Row::new(vec!["Option+c", "Toggle conversion source"]),
         Row::new(vec!["Ctrl+l / Ctrl+h", "Toggle numbers/header"]),
         Row::new(vec!["Ctrl+s", "Save file"]),
         Row::new(vec!["Ctrl+r", "Refresh rates"]),
+        Row::new(vec!["Ctrl+o", "Recent files"]),
         Row::new(vec!["Ctrl+q", "Quit"]),
     ]
 }
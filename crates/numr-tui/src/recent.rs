@@ -0,0 +1,169 @@
+//! Recently opened files, remembered across sessions in the config directory
+
+use directories::ProjectDirs;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::persistence::atomic_write;
+
+/// Number of files kept in the recent-files list
+pub const MAX_RECENT_FILES: usize = 10;
+
+/// Most-recently-opened-first list of document paths.
+///
+/// The list is stored as one path per line. A list without a store (as in
+/// tests) is kept in memory only.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+    store: Option<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Get the recent-files store path
+    pub fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "numr").map(|dirs| dirs.config_dir().join("recent_files"))
+    }
+
+    /// Load the list from the config directory.
+    /// A missing store is an empty list; unreadable stores are reported as warnings.
+    pub fn load() -> (Self, Option<String>) {
+        match Self::path() {
+            Some(path) => Self::load_from(path),
+            None => (Self::default(), None),
+        }
+    }
+
+    fn load_from(store: PathBuf) -> (Self, Option<String>) {
+        let (content, warning) = match fs::read_to_string(&store) {
+            Ok(content) => (content, None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (String::new(), None),
+            Err(e) => (
+                String::new(),
+                Some(format!("Could not read recent files: {e}")),
+            ),
+        };
+
+        let mut recent = Self {
+            paths: Vec::new(),
+            store: Some(store),
+        };
+        // Oldest first so that `add` rebuilds the stored order with dedupe and cap applied
+        for line in content.lines().rev() {
+            if !line.trim().is_empty() {
+                recent.add(PathBuf::from(line));
+            }
+        }
+        (recent, warning)
+    }
+
+    /// Paths, most recently opened first
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Move `path` to the front of the list, dropping any earlier entry for
+    /// the same file and the oldest entries beyond `MAX_RECENT_FILES`.
+    pub fn add(&mut self, path: impl AsRef<Path>) {
+        let path = std::path::absolute(path.as_ref()).unwrap_or_else(|_| path.as_ref().into());
+        self.paths.retain(|existing| *existing != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Write the list to its store, if it has one
+    pub fn save(&self) -> io::Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let mut content = String::new();
+        for path in &self.paths {
+            // Paths that are not valid UTF-8 cannot round-trip through the text store
+            if let Some(path) = path.to_str() {
+                content.push_str(path);
+                content.push('\n');
+            }
+        }
+        atomic_write(store, content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(name)
+    }
+
+    #[test]
+    fn add_puts_the_newest_file_first() {
+        let mut recent = RecentFiles::default();
+        recent.add(file("a.numr"));
+        recent.add(file("b.numr"));
+
+        assert_eq!(recent.paths(), &[file("b.numr"), file("a.numr")]);
+    }
+
+    #[test]
+    fn add_dedupes_by_moving_the_file_to_the_front() {
+        let mut recent = RecentFiles::default();
+        recent.add(file("a.numr"));
+        recent.add(file("b.numr"));
+        recent.add(file("a.numr"));
+
+        assert_eq!(recent.paths(), &[file("a.numr"), file("b.numr")]);
+    }
+
+    #[test]
+    fn add_resolves_relative_paths_before_comparing() {
+        let mut recent = RecentFiles::default();
+        recent.add("budget.numr");
+        recent.add(std::env::current_dir().unwrap().join("budget.numr"));
+
+        assert_eq!(recent.paths().len(), 1);
+        assert!(recent.paths()[0].is_absolute());
+    }
+
+    #[test]
+    fn add_caps_the_list_by_dropping_the_oldest_files() {
+        let mut recent = RecentFiles::default();
+        for i in 0..MAX_RECENT_FILES + 3 {
+            recent.add(file(&format!("{i}.numr")));
+        }
+
+        assert_eq!(recent.paths().len(), MAX_RECENT_FILES);
+        assert_eq!(
+            recent.paths()[0],
+            file(&format!("{}.numr", MAX_RECENT_FILES + 2))
+        );
+        assert_eq!(recent.paths().last(), Some(&file("3.numr")));
+    }
+
+    #[test]
+    fn store_round_trips_in_order() {
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let store = std::env::temp_dir().join(format!("numr-recent-{nonce}"));
+
+        let (mut recent, warning) = RecentFiles::load_from(store.clone());
+        assert!(warning.is_none());
+        assert!(recent.is_empty());
+        recent.add(file("a.numr"));
+        recent.add(file("b.numr"));
+        recent.save().unwrap();
+
+        let (reloaded, warning) = RecentFiles::load_from(store.clone());
+        assert!(warning.is_none());
+        assert_eq!(reloaded.paths(), &[file("b.numr"), file("a.numr")]);
+        fs::remove_file(store).unwrap();
+    }
+}
@@ -14,7 +14,7 @@ use crate::app::{App, InputMode, KeybindingMode};
 use crate::line_layout::{
     highlight_line, marked_line, take_marker_cells, wrapped_result_row, LineMarkers,
 };
-use crate::popups::{draw_help_popup, draw_quit_popup};
+use crate::popups::{draw_help_popup, draw_quit_popup, draw_recent_files_popup};
 use crate::theme as palette;
 
 #[cfg(test)]
@@ -47,10 +47,22 @@ fn line_number_width(app: &App) -> u16 {
     }
 }
 
+/// Height of the debug panel: errors get room to wrap, result details one row
+fn debug_panel_height(app: &App) -> u16 {
+    if !app.debug_mode {
+        0
+    } else if app.current_line_error().is_some() {
+        5
+    } else if app.current_line_detail().is_some() {
+        3
+    } else {
+        0
+    }
+}
+
 pub fn viewport_dimensions(app: &App, area: Rect) -> (usize, usize) {
     let max_result_width = result_column_width(app, area);
-    let has_error = app.current_line_error().is_some();
-    let debug_height = if app.debug_mode && has_error { 5 } else { 0 };
+    let debug_height = debug_panel_height(app);
     let header_height = if app.show_header { 1 } else { 0 };
     let footer_h = footer_height(app, area.width);
 
@@ -88,9 +100,8 @@ pub fn draw(frame: &mut Frame, app: &App) {
 
     let max_result_width = result_column_width(app, area);
 
-    // Reserve space for debug panel if in debug mode and there's an error
-    let has_error = app.current_line_error().is_some();
-    let debug_height = if app.debug_mode && has_error { 5 } else { 0 };
+    // Reserve space for the debug panel in debug mode
+    let debug_height = debug_panel_height(app);
     let header_height = if app.show_header { 1 } else { 0 };
     let footer_h = footer_height(app, area.width);
 
@@ -130,8 +141,7 @@ pub fn draw(frame: &mut Frame, app: &App) {
         draw_results(frame, result_area, app);
     }
 
-    // Draw debug panel if enabled and there's an error
-    if app.debug_mode && has_error {
+    if debug_height > 0 {
         draw_debug_panel(frame, debug_area, app);
     }
 
@@ -141,6 +151,16 @@ pub fn draw(frame: &mut Frame, app: &App) {
         draw_help_popup(frame, area, app.help_scroll, app.keybinding_mode);
     }
 
+    if app.show_recent_files {
+        draw_recent_files_popup(
+            frame,
+            area,
+            app.recent_files(),
+            app.recent_selection,
+            app.path(),
+        );
+    }
+
     if app.show_quit_confirmation {
         draw_quit_popup(frame, area);
     }
@@ -390,6 +410,12 @@ fn draw_debug_panel(frame: &mut Frame, area: Rect, app: &App) {
             .wrap(Wrap { trim: true });
 
         frame.render_widget(paragraph, area);
+    } else if let Some(detail) = app.current_line_detail() {
+        // Full-precision result, to explain rounding in the results column
+        let block = Block::bordered()
+            .title(" value ")
+            .border_style(Style::new().dim());
+        frame.render_widget(Paragraph::new(detail.to_string()).block(block), area);
     }
 }
 
@@ -604,6 +630,7 @@ mod tests {
             (TokenType::Text, palette::TEXT),
             (TokenType::Whitespace, Color::Reset),
             (TokenType::Punctuation, palette::DIM),
+            (TokenType::UnmatchedBracket, palette::ERROR),
         ];
         for (token_type, expected) in cases {
             assert_eq!(token_color(token_type), expected, "{token_type:?}");